@@ -1,2 +1,20 @@
 
-INSERT INTO todo (id, cid, title, "status") VALUES (100,123, 'todo 100', 'CLOSED');
\ No newline at end of file
+-- passwords : demo1 -> 'welcome', demo2 -> 'welcome2'
+INSERT INTO "user" (id, username, pwd) VALUES (123, 'demo1', '$argon2id$v=19$m=19456,t=2,p=1$D5erY0Tek1XIfro0ef9XrQ$4WCPCd5tcEbrvnDZQyKbYPDh7A42JlKht71xfawqUyI');
+INSERT INTO "user" (id, username, pwd) VALUES (124, 'demo2', '$argon2id$v=19$m=19456,t=2,p=1$z1gngBF9RKgfo8wvvX7KAA$gL1lZr6DXGB2H9CaNjp6Im1A+tr/cgRD/41Y1PvpOM0');
+
+INSERT INTO todo (id, cid, title, description, priority) VALUES (101, 123, 'todo 101', 'The **first** todo of demo1', 'high');
+INSERT INTO todo (id, cid, title, "status", completed_at) VALUES (100, 123, 'todo 100', 'closed', NOW());
+INSERT INTO todo (id, cid, title) VALUES (200, 124, 'todo 200 of demo2');
+
+INSERT INTO tag (id, cid, name) VALUES (1, 123, 'work'), (2, 123, 'home'), (3, 124, 'work');
+INSERT INTO todo_tag (todo_id, tag_id) VALUES (101, 1), (101, 2), (100, 1), (200, 3);
+
+INSERT INTO project (id, cid, name, color) VALUES (1, 123, 'project 1', '#3366ff');
+UPDATE todo SET project_id = 1 WHERE id = 101;
+
+INSERT INTO comment (id, todo_id, cid, body) VALUES (1, 101, 123, 'comment 1 on todo 101'), (2, 101, 123, 'comment 2 on todo 101');
+
+UPDATE "user" SET admin = TRUE WHERE id = 123;
+
+INSERT INTO notification_preference (user_id, email) VALUES (123, 'demo1@example.com'), (124, 'demo2@example.com');
@@ -0,0 +1,3 @@
+DROP TABLE IF EXISTS todo;
+DROP TABLE IF EXISTS "user";
+DROP TYPE IF EXISTS todo_status;
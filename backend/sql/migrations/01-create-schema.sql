@@ -0,0 +1,22 @@
+CREATE TYPE todo_status AS ENUM ('open', 'closed');
+
+-- "user" is a reserved word in postgres, so it must always be quoted
+CREATE TABLE IF NOT EXISTS "user" (
+    id BIGSERIAL PRIMARY KEY,
+    ctime TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    username VARCHAR(128) NOT NULL UNIQUE,
+    pwd VARCHAR(256) NOT NULL, -- argon2 PHC string
+    token_salt TEXT NOT NULL DEFAULT gen_random_uuid()::text
+);
+
+ALTER SEQUENCE user_id_seq RESTART WITH 1000;
+
+CREATE TABLE IF NOT EXISTS todo (
+    id BIGSERIAL PRIMARY KEY,
+    cid BIGINT NOT NULL REFERENCES "user" (id) ON DELETE CASCADE, -- the owner
+    ctime TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    title VARCHAR(63) NOT NULL,
+    status todo_status NOT NULL DEFAULT 'open'
+);
+
+ALTER SEQUENCE todo_id_seq RESTART WITH 1000;
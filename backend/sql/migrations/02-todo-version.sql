@@ -0,0 +1,2 @@
+-- Optimistic concurrency, the ETag of a todo
+ALTER TABLE todo ADD COLUMN version BIGINT NOT NULL DEFAULT 1;
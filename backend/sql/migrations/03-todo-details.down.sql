@@ -0,0 +1,8 @@
+ALTER TABLE todo
+    DROP COLUMN IF EXISTS description,
+    DROP COLUMN IF EXISTS due_at,
+    DROP COLUMN IF EXISTS priority,
+    DROP COLUMN IF EXISTS completed_at,
+    DROP COLUMN IF EXISTS mtime;
+
+DROP TYPE IF EXISTS todo_priority;
@@ -0,0 +1,13 @@
+-- Rich todo fields
+CREATE TYPE todo_priority AS ENUM ('low', 'medium', 'high', 'urgent');
+
+ALTER TABLE todo
+    ADD COLUMN description TEXT,
+    ADD COLUMN due_at TIMESTAMP WITH TIME ZONE,
+    ADD COLUMN priority todo_priority NOT NULL DEFAULT 'medium',
+    -- set when the status becomes closed, cleared when it's open again
+    ADD COLUMN completed_at TIMESTAMP WITH TIME ZONE,
+    ADD COLUMN mtime TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW();
+
+UPDATE todo SET mtime = ctime;
+UPDATE todo SET completed_at = ctime WHERE status = 'closed';
@@ -0,0 +1,16 @@
+-- Tags, per user, and the todos they label
+CREATE TABLE tag (
+    id BIGSERIAL PRIMARY KEY,
+    cid BIGINT NOT NULL REFERENCES "user"(id) ON DELETE CASCADE,
+    ctime TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    name VARCHAR(64) NOT NULL,
+    UNIQUE (cid, name)
+);
+ALTER SEQUENCE tag_id_seq RESTART WITH 1000;
+
+CREATE TABLE todo_tag (
+    todo_id BIGINT NOT NULL REFERENCES todo(id) ON DELETE CASCADE,
+    tag_id BIGINT NOT NULL REFERENCES tag(id) ON DELETE CASCADE,
+    PRIMARY KEY (todo_id, tag_id)
+);
+CREATE INDEX todo_tag_tag_id_idx ON todo_tag (tag_id);
@@ -0,0 +1,5 @@
+ALTER TABLE todo
+    DROP COLUMN IF EXISTS project_id,
+    DROP COLUMN IF EXISTS archived;
+
+DROP TABLE IF EXISTS project;
@@ -0,0 +1,18 @@
+-- Projects, grouping the todos of a user
+CREATE TABLE project (
+    id BIGSERIAL PRIMARY KEY,
+    cid BIGINT NOT NULL REFERENCES "user"(id) ON DELETE CASCADE,
+    ctime TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    mtime TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    name VARCHAR(63) NOT NULL,
+    -- #rrggbb
+    color VARCHAR(7) NOT NULL DEFAULT '#808080',
+    archived BOOLEAN NOT NULL DEFAULT FALSE
+);
+ALTER SEQUENCE project_id_seq RESTART WITH 1000;
+
+ALTER TABLE todo
+    ADD COLUMN project_id BIGINT REFERENCES project(id) ON DELETE SET NULL,
+    -- follows the archived flag of its project
+    ADD COLUMN archived BOOLEAN NOT NULL DEFAULT FALSE;
+CREATE INDEX todo_project_id_idx ON todo (project_id);
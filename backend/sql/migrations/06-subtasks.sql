@@ -0,0 +1,3 @@
+-- Subtasks, a todo can have a parent todo of the same user
+ALTER TABLE todo ADD COLUMN parent_id BIGINT REFERENCES todo(id) ON DELETE CASCADE;
+CREATE INDEX todo_parent_id_idx ON todo (parent_id);
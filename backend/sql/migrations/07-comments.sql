@@ -0,0 +1,12 @@
+-- Comments on todos, by their author
+CREATE TABLE comment (
+    id BIGSERIAL PRIMARY KEY,
+    todo_id BIGINT NOT NULL REFERENCES todo(id) ON DELETE CASCADE,
+    cid BIGINT NOT NULL REFERENCES "user"(id) ON DELETE CASCADE,
+    body TEXT NOT NULL,
+    ctime TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    -- null until the body is edited
+    edited_at TIMESTAMP WITH TIME ZONE
+);
+ALTER SEQUENCE comment_id_seq RESTART WITH 1000;
+CREATE INDEX comment_todo_id_idx ON comment (todo_id);
@@ -0,0 +1,15 @@
+-- Files attached to todos, their content is in the storage under storage_key
+CREATE TABLE attachment (
+    id BIGSERIAL PRIMARY KEY,
+    todo_id BIGINT NOT NULL REFERENCES todo(id) ON DELETE CASCADE,
+    cid BIGINT NOT NULL REFERENCES "user"(id) ON DELETE CASCADE,
+    ctime TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    filename VARCHAR(255) NOT NULL,
+    content_type VARCHAR(255) NOT NULL,
+    size BIGINT NOT NULL,
+    -- hex encoded
+    sha256 CHAR(64) NOT NULL,
+    storage_key VARCHAR(64) NOT NULL UNIQUE
+);
+ALTER SEQUENCE attachment_id_seq RESTART WITH 1000;
+CREATE INDEX attachment_todo_id_idx ON attachment (todo_id);
@@ -0,0 +1,4 @@
+DROP TABLE IF EXISTS todo_share;
+DROP TYPE IF EXISTS todo_permission;
+
+ALTER TABLE todo DROP COLUMN IF EXISTS assignee_id;
@@ -0,0 +1,14 @@
+-- The user a todo is delegated to, and the users it's shared with
+ALTER TABLE todo ADD COLUMN assignee_id BIGINT REFERENCES "user"(id) ON DELETE SET NULL;
+CREATE INDEX todo_assignee_id_idx ON todo (assignee_id);
+
+CREATE TYPE todo_permission AS ENUM ('view', 'edit');
+
+CREATE TABLE todo_share (
+    todo_id BIGINT NOT NULL REFERENCES todo(id) ON DELETE CASCADE,
+    user_id BIGINT NOT NULL REFERENCES "user"(id) ON DELETE CASCADE,
+    permission todo_permission NOT NULL,
+    ctime TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (todo_id, user_id)
+);
+CREATE INDEX todo_share_user_id_idx ON todo_share (user_id);
@@ -0,0 +1,2 @@
+-- RRULE subset, like 'FREQ=WEEKLY;BYDAY=MO,FR'
+ALTER TABLE todo ADD COLUMN recurrence VARCHAR(255);
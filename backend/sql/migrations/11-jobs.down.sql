@@ -0,0 +1,5 @@
+ALTER TABLE "user" DROP COLUMN IF EXISTS admin;
+
+DROP INDEX IF EXISTS todo_due_at_idx;
+DROP TABLE IF EXISTS job;
+DROP TYPE IF EXISTS job_status;
@@ -0,0 +1,26 @@
+-- The background jobs, polled by the workers, and the admins who can see them
+CREATE TYPE job_status AS ENUM ('queued', 'running', 'done', 'dead');
+
+CREATE TABLE job (
+    id BIGSERIAL PRIMARY KEY,
+    -- the handler, like 'reminder'. Not an enum, a new kind must not need a migration
+    kind VARCHAR(63) NOT NULL,
+    payload JSONB NOT NULL DEFAULT '{}',
+    status job_status NOT NULL DEFAULT 'queued',
+    attempts INT NOT NULL DEFAULT 0,
+    max_attempts INT NOT NULL,
+    -- when queued, the next attempt. When running, the end of the lease of the worker
+    run_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    last_error TEXT,
+    -- a job is enqueued only once per key, like 'reminder:101:1767225600'
+    dedup_key VARCHAR(255) UNIQUE,
+    ctime TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    mtime TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+ALTER SEQUENCE job_id_seq RESTART WITH 1000;
+
+CREATE INDEX job_status_run_at_idx ON job (run_at) WHERE status IN ('queued', 'running');
+CREATE INDEX todo_due_at_idx ON todo (due_at) WHERE status = 'open';
+
+ALTER TABLE "user" ADD COLUMN admin BOOLEAN NOT NULL DEFAULT FALSE;
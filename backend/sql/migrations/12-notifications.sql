@@ -0,0 +1,12 @@
+-- Where the users are emailed, and about what. Without a row, the defaults and no email
+CREATE TABLE notification_preference (
+    user_id BIGINT PRIMARY KEY REFERENCES "user"(id) ON DELETE CASCADE,
+    email VARCHAR(255),
+    -- a todo is assigned to the user
+    assigned BOOLEAN NOT NULL DEFAULT TRUE,
+    -- a todo of the user, owned or assigned, is commented by someone else
+    commented BOOLEAN NOT NULL DEFAULT TRUE,
+    -- a todo of the user is about to be due
+    due BOOLEAN NOT NULL DEFAULT TRUE,
+    mtime TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
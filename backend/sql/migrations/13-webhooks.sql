@@ -0,0 +1,33 @@
+-- The endpoints the todo events of a user are posted to, and the log of every delivery attempt
+CREATE TABLE webhook (
+    id BIGSERIAL PRIMARY KEY,
+    cid BIGINT NOT NULL REFERENCES "user"(id) ON DELETE CASCADE, -- the owner
+    -- only the todos of that project, all the todos of the owner when null
+    project_id BIGINT REFERENCES project(id) ON DELETE CASCADE,
+    url VARCHAR(2048) NOT NULL,
+    -- the HMAC key of the signature header
+    secret VARCHAR(128) NOT NULL,
+    active BOOLEAN NOT NULL DEFAULT TRUE,
+    ctime TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    mtime TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+ALTER SEQUENCE webhook_id_seq RESTART WITH 1000;
+CREATE INDEX webhook_cid_idx ON webhook (cid);
+
+CREATE TABLE webhook_delivery (
+    id BIGSERIAL PRIMARY KEY,
+    webhook_id BIGINT NOT NULL REFERENCES webhook(id) ON DELETE CASCADE,
+    -- the id of the event, the same for all the attempts of a delivery
+    event_id UUID NOT NULL,
+    event_type VARCHAR(63) NOT NULL,
+    attempt INT NOT NULL,
+    -- null when no response was received
+    status_code INT,
+    error TEXT,
+    duration_ms BIGINT NOT NULL,
+    ctime TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+ALTER SEQUENCE webhook_delivery_id_seq RESTART WITH 1000;
+CREATE INDEX webhook_delivery_webhook_id_idx ON webhook_delivery (webhook_id, id);
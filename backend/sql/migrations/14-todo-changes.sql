@@ -0,0 +1,19 @@
+-- The todo changes, in order, for the event stream clients to get the ones they missed
+CREATE TABLE todo_change (
+    id BIGSERIAL PRIMARY KEY,
+    -- the transaction of the change, the changes are read in the order of their transactions
+    xid BIGINT NOT NULL DEFAULT pg_current_xact_id()::TEXT::BIGINT,
+    event VARCHAR(31) NOT NULL,
+    -- no foreign key, the changes of a deleted todo are kept
+    todo_id BIGINT NOT NULL,
+    -- the users who could see the todo when it changed
+    user_ids BIGINT[] NOT NULL,
+    -- the todo after the change, before it for a deletion
+    data JSONB NOT NULL,
+    ctime TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+ALTER SEQUENCE todo_change_id_seq RESTART WITH 1000;
+CREATE INDEX todo_change_user_ids_idx ON todo_change USING GIN (user_ids);
+CREATE INDEX todo_change_xid_idx ON todo_change (xid, id);
+CREATE INDEX todo_change_ctime_idx ON todo_change (ctime);
@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS webhook_delivery_ctime_idx;
+DROP INDEX IF EXISTS job_finished_mtime_idx;
@@ -0,0 +1,3 @@
+-- The finished jobs and the webhook deliveries are deleted by the worker after the retention time
+CREATE INDEX job_finished_mtime_idx ON job (mtime) WHERE status IN ('done', 'dead');
+CREATE INDEX webhook_delivery_ctime_idx ON webhook_delivery (ctime);
@@ -0,0 +1,70 @@
+use clap::Parser;
+
+use super::{Cli, Command, MigrateAction};
+
+#[test]
+fn cli_parse_serve() -> Result<(), Box<dyn std::error::Error>> {
+    // ACT
+    let cli = Cli::try_parse_from([
+        "rust_warp_postgres",
+        "serve",
+        "--port",
+        "9090",
+        "--bind-address",
+        "0.0.0.0",
+        "--web-folder",
+        "../frontend/web-folder",
+        "--config",
+        "config.toml",
+    ])?;
+
+    // ASSERT
+    assert_eq!(cli.config.as_deref(), Some("config.toml"));
+    match cli.command {
+        Command::Serve {
+            port,
+            bind_address,
+            web_folder,
+        } => {
+            assert_eq!(port, Some(9090));
+            assert_eq!(
+                bind_address.map(|address| address.to_string()).as_deref(),
+                Some("0.0.0.0")
+            );
+            assert_eq!(web_folder.as_deref(), Some("../frontend/web-folder"));
+        }
+        other => unreachable!("Should be serve: {other:?}"),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn cli_parse_migrate() -> Result<(), Box<dyn std::error::Error>> {
+    for (action, expected) in [
+        ("up", MigrateAction::Up),
+        ("status", MigrateAction::Status),
+        ("down", MigrateAction::Down),
+    ] {
+        let cli = Cli::try_parse_from(["rust_warp_postgres", "migrate", action])?;
+
+        match cli.command {
+            Command::Migrate { action } => assert_eq!(action, expected),
+            other => unreachable!("Should be migrate: {other:?}"),
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn cli_parse_errors() {
+    // no subcommand, unknown subcommand, invalid port
+    for args in [
+        vec!["rust_warp_postgres"],
+        vec!["rust_warp_postgres", "../frontend/web-folder"],
+        vec!["rust_warp_postgres", "serve", "--port", "not_a_port"],
+    ] {
+        assert!(Cli::try_parse_from(&args).is_err(), "{args:?} should fail");
+    }
+}
@@ -0,0 +1,75 @@
+use std::fs;
+
+use super::{Email, Error, FileMailer, Mailer, MemoryMailer, Template};
+
+const TEST_EMAIL_DIRECTORY: &str = "target/test_emails/";
+
+const TEMPLATE: Template = Template {
+    subject: "About {{title}}",
+    text: "Hello {{username}}, {{title}} {{unknown}}",
+    html: "<p>Hello {{username}}, {{title}}</p>",
+};
+
+#[test]
+fn email_template_render() {
+    // ACT
+    let email = TEMPLATE.render(
+        "demo1@example.com",
+        &[("username", "demo1"), ("title", "<b>fish</b> & chips\nnow")],
+    );
+
+    // ASSERT
+    assert_eq!(email.to, "demo1@example.com");
+    assert_eq!(email.subject, "About <b>fish</b> & chips now");
+    assert_eq!(
+        email.text,
+        "Hello demo1, <b>fish</b> & chips\nnow {{unknown}}"
+    );
+    assert_eq!(
+        email.html,
+        "<p>Hello demo1, &lt;b&gt;fish&lt;/b&gt; &amp; chips\nnow</p>"
+    );
+}
+
+#[tokio::test]
+async fn email_memory_mailer() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE
+    let mailer = MemoryMailer::new("Todo <todo@localhost>".parse()?);
+    let email = TEMPLATE.render("demo1@example.com", &[("username", "demo1")]);
+    let invalid = Email {
+        to: String::from("not an address"),
+        ..email.clone()
+    };
+
+    // ACT
+    mailer.send(&email).await?;
+    let invalid = mailer.send(&invalid).await;
+
+    // ASSERT
+    assert_eq!(mailer.sent(), vec![email]);
+    assert!(matches!(invalid, Err(Error::InvalidAddress(_))));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn email_file_mailer() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE
+    let _ = fs::remove_dir_all(TEST_EMAIL_DIRECTORY);
+    let mailer = FileMailer::new(TEST_EMAIL_DIRECTORY, "Todo <todo@localhost>".parse()?);
+    let email = TEMPLATE.render("demo1@example.com", &[("username", "demo1")]);
+
+    // ACT
+    mailer.send(&email).await?;
+
+    // ASSERT
+    let files: Vec<_> = fs::read_dir(TEST_EMAIL_DIRECTORY)?.collect::<Result<_, _>>()?;
+    assert_eq!(files.len(), 1);
+    let content = fs::read_to_string(files[0].path())?;
+    assert!(content.contains("To: demo1@example.com"));
+    assert!(content.contains("From: Todo <todo@localhost>"));
+    assert!(content.contains("Content-Type: text/html"));
+    assert!(content.contains("Content-Type: text/plain"));
+
+    Ok(())
+}
@@ -0,0 +1,114 @@
+use bytes::Bytes;
+use futures::{stream, StreamExt, TryStreamExt};
+
+use super::{AttachmentModelAccessController, NewAttachment};
+use crate::{
+    config::config,
+    model::{
+        self,
+        db::{initialize_database, DatabaseMode},
+        todo::ModelAccessController,
+    },
+    security::{new_token, user_context_from_token},
+    storage::{self, storage, ByteStream},
+};
+
+fn content(chunks: Vec<Bytes>) -> ByteStream {
+    stream::iter(chunks.into_iter().map(Ok)).boxed()
+}
+
+fn new_attachment(filename: &str) -> NewAttachment {
+    NewAttachment {
+        filename: Some(String::from(filename)),
+        content_type: Some(String::from("text/plain")),
+    }
+}
+
+#[tokio::test]
+async fn model_attachment_crud() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+    let utx = user_context_from_token(&database, &new_token(&database, 123).await?).await?;
+    let hello = vec![Bytes::from_static(b"hello "), Bytes::from_static(b"world")];
+
+    // ACT
+    let created = AttachmentModelAccessController::create(
+        &database,
+        &utx,
+        101,
+        new_attachment("C:\\screenshots\\hello.txt"),
+        content(hello),
+    )
+    .await?;
+    let listed = AttachmentModelAccessController::list(&database, &utx, 101).await?;
+    let (_, downloaded) =
+        AttachmentModelAccessController::get_content(&database, &utx, 101, created.id).await?;
+    let downloaded: Vec<Bytes> = downloaded.try_collect().await?;
+    AttachmentModelAccessController::delete(&database, &utx, 101, created.id).await?;
+    let stored_after_delete = storage().get(&created.storage_key).await;
+
+    // ASSERT
+    assert!(created.id >= 1000, "ID should be >= 1000");
+    assert_eq!(created.filename, "hello.txt");
+    assert_eq!(created.content_type, "text/plain");
+    assert_eq!(created.size, 11);
+    assert_eq!(
+        created.sha256,
+        "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9"
+    );
+    assert_eq!(listed.len(), 1);
+    assert_eq!(downloaded.concat(), b"hello world");
+    assert!(matches!(
+        stored_after_delete,
+        Err(storage::Error::NotFound(_))
+    ));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn model_attachment_limits_and_cleanup() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+    let utx = user_context_from_token(&database, &new_token(&database, 123).await?).await?;
+    let utx_124 = user_context_from_token(&database, &new_token(&database, 124).await?).await?;
+    let max_size = usize::try_from(config().attachments.max_size)?;
+    let too_large = vec![Bytes::from(vec![0; max_size]), Bytes::from_static(b"1")];
+    let attached = AttachmentModelAccessController::create(
+        &database,
+        &utx,
+        101,
+        new_attachment("notes.txt"),
+        content(vec![Bytes::from_static(b"notes")]),
+    )
+    .await?;
+
+    // ACT
+    let rejected = AttachmentModelAccessController::create(
+        &database,
+        &utx,
+        101,
+        new_attachment("big.bin"),
+        content(too_large),
+    )
+    .await;
+    let other_user = AttachmentModelAccessController::list(&database, &utx_124, 101).await;
+    ModelAccessController::delete(&database, &utx, 101, None).await?;
+    let stored_after_todo_delete = storage().get(&attached.storage_key).await;
+
+    // ASSERT
+    assert!(matches!(
+        rejected,
+        Err(model::Error::StorageError(storage::Error::TooLarge(_)))
+    ));
+    assert!(matches!(
+        other_user,
+        Err(model::Error::EntityNotFound("todo", _))
+    ));
+    assert!(matches!(
+        stored_after_todo_delete,
+        Err(storage::Error::NotFound(_))
+    ));
+
+    Ok(())
+}
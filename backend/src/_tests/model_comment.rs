@@ -0,0 +1,108 @@
+use super::{CommentModelAccessController, PartialComment};
+use crate::{
+    model::{
+        self,
+        db::{initialize_database, DatabaseMode},
+        todo::{ModelAccessController, TodoListOptions},
+    },
+    security::{new_token, user_context_from_token},
+};
+
+#[tokio::test]
+async fn model_comment_crud() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+    let utx = user_context_from_token(&database, &new_token(&database, 123).await?).await?;
+    let body = |body: &str| PartialComment {
+        body: Some(String::from(body)),
+    };
+
+    let before = ModelAccessController::get(&database, &utx, 101).await?;
+
+    // ACT
+    let created = CommentModelAccessController::create(&database, &utx, 101, body("new")).await?;
+    let updated =
+        CommentModelAccessController::update(&database, &utx, 101, created.id, body("edited"))
+            .await?;
+    let comments = CommentModelAccessController::list(&database, &utx, 101).await?;
+    let todo = ModelAccessController::get(&database, &utx, 101).await?;
+    CommentModelAccessController::delete(&database, &utx, 101, 1).await?;
+    let after_delete = ModelAccessController::get(&database, &utx, 101).await?;
+    let listed = ModelAccessController::list(&database, &utx, &TodoListOptions::default()).await?;
+
+    // ASSERT
+    assert!(created.id >= 1000, "ID should be >= 1000");
+    assert_eq!(created.cid, 123);
+    assert_eq!(created.edited_at, None);
+    assert_eq!(updated.body, "edited");
+    assert!(updated.edited_at.is_some());
+    let ids: Vec<i64> = comments.iter().map(|comment| comment.id).collect();
+    assert_eq!(ids, [1, 2, created.id]);
+    assert_eq!(todo.comment_count, 3);
+    // the comment count is in the ETag, so adding or deleting a comment makes a new version
+    assert_eq!(todo.version, before.version + 1);
+    assert_eq!(after_delete.version, todo.version + 1);
+    assert_eq!(listed.items[0].id, 101);
+    assert_eq!(listed.items[0].comment_count, 2);
+    assert_eq!(listed.items[1].comment_count, 0);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn model_comment_author_only() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+    let utx = user_context_from_token(&database, &new_token(&database, 123).await?).await?;
+    let utx_124 = user_context_from_token(&database, &new_token(&database, 124).await?).await?;
+    // a comment of another author on a todo of 123
+    sqlx::query("INSERT INTO comment (id, todo_id, cid, body) VALUES (3, 101, 124, 'by demo2')")
+        .execute(&database)
+        .await?;
+
+    // ACT
+    let edit_other_author = CommentModelAccessController::update(
+        &database,
+        &utx,
+        101,
+        3,
+        PartialComment {
+            body: Some(String::from("edited")),
+        },
+    )
+    .await;
+    let delete_other_author = CommentModelAccessController::delete(&database, &utx, 101, 3).await;
+    let empty_body = CommentModelAccessController::create(
+        &database,
+        &utx,
+        101,
+        PartialComment {
+            body: Some(String::from(" ")),
+        },
+    )
+    .await;
+    let other_user_todo = CommentModelAccessController::list(&database, &utx_124, 101).await;
+    let wrong_todo = CommentModelAccessController::delete(&database, &utx, 100, 1).await;
+
+    // ASSERT
+    for result in [edit_other_author, delete_other_author] {
+        assert!(matches!(
+            result,
+            Err(model::Error::AccessDenied("comment", _))
+        ));
+    }
+    match empty_body {
+        Err(model::Error::ValidationFailed(errors)) => assert_eq!(errors[0].field, "body"),
+        other => unreachable!("Wrong result: {other:?}"),
+    }
+    assert!(matches!(
+        other_user_todo,
+        Err(model::Error::EntityNotFound("todo", _))
+    ));
+    assert!(matches!(
+        wrong_todo,
+        Err(model::Error::EntityNotFound("comment", _))
+    ));
+
+    Ok(())
+}
@@ -1,15 +1,23 @@
-use super::initialize_database;
+use super::{initialize_database, quote_identifier, quote_literal, DatabaseMode};
 #[tokio::test]
 async fn model_database_initialize_database() -> Result<(), Box<dyn std::error::Error>> {
     // ARRANGE
-    let database = initialize_database().await?;
+    let database = initialize_database(DatabaseMode::Dev).await?;
 
     //ACT
     let result = sqlx::query("SELECT * FROM todo")
         .fetch_all(&database)
         .await?;
 
-    assert_eq!(2, result.len(), "number of seed todos");
+    assert_eq!(3, result.len(), "number of seed todos");
 
     Ok(())
 }
+
+#[test]
+fn model_database_quoted_names() {
+    // ACT & ASSERT
+    assert_eq!(quote_identifier("todo"), "\"todo\"");
+    assert_eq!(quote_identifier("to\"do"), "\"to\"\"do\"");
+    assert_eq!(quote_literal("it's"), "'it''s'");
+}
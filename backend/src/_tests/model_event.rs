@@ -0,0 +1,344 @@
+use std::time::Duration;
+
+use chrono::{TimeZone, Utc};
+use tokio::time::timeout;
+
+use super::{publish, ChangeLog, ChangeLogEntry, EventHub, LogPosition, TodoEvent};
+use crate::{
+    model::{
+        db::{initialize_database, DatabaseMode},
+        project::{PartialProject, ProjectModelAccessController},
+        share::{PartialShare, Permission, ShareModelAccessController},
+        subtask::CloseParentRule,
+        test_utils::create_subtask,
+        todo::{patch_todo, ModelAccessController, PartialTodo, Status, TodoPatch},
+    },
+    security::{new_token, user_context_from_token},
+};
+
+#[tokio::test]
+async fn model_event_changes_notified() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+    let utx = user_context_from_token(&database, &new_token(&database, 123).await?).await?;
+    let share = PartialShare {
+        permission: Some(Permission::View),
+    };
+    ShareModelAccessController::set(&database, &utx, 101, 124, share).await?;
+    let hub = EventHub::new(database.clone());
+    let mut changes = hub.subscribe().await?;
+
+    // ACT
+    let data = PartialTodo {
+        title: Some(String::from("notified")),
+        ..PartialTodo::default()
+    };
+    ModelAccessController::update(&database, &utx, 101, data, None).await?;
+    ModelAccessController::delete(&database, &utx, 101, None).await?;
+    let updated = timeout(Duration::from_secs(5), changes.recv()).await??;
+    let deleted = timeout(Duration::from_secs(5), changes.recv()).await??;
+
+    // ASSERT
+    assert_eq!(updated.event, "todo.updated");
+    assert_eq!(updated.todo_id, 101);
+    assert_eq!(updated.data["title"], "notified", "read once by the hub");
+    assert_eq!(updated.user_ids, vec![123, 124]);
+    assert_eq!(deleted.event, "todo.deleted");
+    assert_eq!(
+        deleted.user_ids,
+        vec![123, 124],
+        "the users it was shared with"
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn model_event_change_log() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+    let utx = user_context_from_token(&database, &new_token(&database, 123).await?).await?;
+    let before = ChangeLog::last_position(&database).await?;
+
+    // ACT
+    let data = PartialTodo {
+        title: Some(String::from("logged")),
+        ..PartialTodo::default()
+    };
+    let todo = ModelAccessController::create(&database, &utx, data).await?;
+    ModelAccessController::delete(&database, &utx, todo.id, None).await?;
+    let entries = ChangeLog::after(&database, 123, before, 10).await?;
+    let other_entries = ChangeLog::after(&database, 124, before, 10).await?;
+    let resumed = ChangeLog::after(&database, 123, entries[0].position(), 10).await?;
+    let created = ChangeLog::get(&database, entries[0].id).await?;
+    let position = ChangeLog::position(&database, entries[0].id).await?;
+    let purged = ChangeLog::purge(&database, Duration::ZERO).await?;
+
+    // ASSERT
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].event, "todo.created");
+    assert_eq!(entries[0].data["title"], "logged");
+    assert_eq!(entries[1].event, "todo.deleted");
+    assert_eq!(entries[1].todo_id, todo.id);
+    assert!(other_entries.is_empty(), "not a todo of demo2");
+    assert_eq!(resumed.len(), 1);
+    assert_eq!(resumed[0].id, entries[1].id);
+    assert_eq!(
+        created.map(|entry| entry.data),
+        Some(entries[0].data.clone()),
+        "the todo as created, even once deleted"
+    );
+    assert_eq!(position, entries[0].position());
+    assert!(purged >= 2);
+    assert_eq!(
+        ChangeLog::position(&database, entries[0].id).await?,
+        LogPosition::default(),
+        "purged, from the start"
+    );
+    assert_eq!(
+        ChangeLog::after(&database, 123, LogPosition::default(), 10)
+            .await?
+            .len(),
+        0
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn model_event_next_occurrence_logged() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+    let utx = user_context_from_token(&database, &new_token(&database, 123).await?).await?;
+    let data = PartialTodo {
+        title: Some(String::from("water the plants")),
+        due_at: Some(Some(Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap())),
+        recurrence: Some(Some(String::from("FREQ=WEEKLY"))),
+        ..PartialTodo::default()
+    };
+    let todo = ModelAccessController::create(&database, &utx, data).await?;
+    let before = ChangeLog::last_position(&database).await?;
+
+    // ACT
+    let data = PartialTodo {
+        status: Some(Status::Closed),
+        ..PartialTodo::default()
+    };
+    ModelAccessController::update(&database, &utx, todo.id, data, None).await?;
+    let entries = ChangeLog::after(&database, 123, before, 10).await?;
+
+    // ASSERT
+    assert_eq!(
+        events_of(&entries),
+        [("todo.created", todo.id + 1), ("todo.updated", todo.id)]
+    );
+    assert_eq!(entries[0].data["title"], "water the plants");
+    assert_eq!(entries[0].data["status"], "Open");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn model_event_cascade_close_logged() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+    let utx = user_context_from_token(&database, &new_token(&database, 123).await?).await?;
+    let child = create_subtask(&database, &utx, 101, Status::Open).await?;
+    let grandchild = create_subtask(&database, &utx, child, Status::Open).await?;
+    let before = ChangeLog::last_position(&database).await?;
+
+    // ACT
+    let patch = TodoPatch::Partial(PartialTodo {
+        status: Some(Status::Closed),
+        ..PartialTodo::default()
+    });
+    patch_todo(&database, &utx, 101, patch, None, CloseParentRule::Cascade).await?;
+    let entries = ChangeLog::after(&database, 123, before, 10).await?;
+
+    // ASSERT
+    assert_eq!(
+        events_of(&entries),
+        [
+            ("todo.updated", child),
+            ("todo.updated", grandchild),
+            ("todo.updated", 101)
+        ]
+    );
+    assert_eq!(entries[0].data["status"], "Closed");
+    assert_eq!(entries[1].data["status"], "Closed");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn model_event_cascade_delete_and_archive_logged() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+    let utx = user_context_from_token(&database, &new_token(&database, 123).await?).await?;
+    let child = create_subtask(&database, &utx, 100, Status::Open).await?;
+    let before = ChangeLog::last_position(&database).await?;
+
+    // ACT
+    ModelAccessController::delete(&database, &utx, 100, None).await?;
+    let data = PartialProject {
+        archived: Some(true),
+        ..PartialProject::default()
+    };
+    ProjectModelAccessController::update(&database, &utx, 1, data).await?;
+    let data = PartialTodo {
+        title: Some(String::from("archived")),
+        ..PartialTodo::default()
+    };
+    ModelAccessController::update(&database, &utx, 101, data, None).await?;
+    ProjectModelAccessController::delete(&database, &utx, 1).await?;
+    let entries = ChangeLog::after(&database, 123, before, 10).await?;
+
+    // ASSERT
+    assert_eq!(
+        events_of(&entries),
+        [
+            ("todo.deleted", 100),
+            ("todo.deleted", child),
+            // archived with its project
+            ("todo.updated", 101),
+            // out of the default todo list, still in the change log
+            ("todo.updated", 101),
+            // unarchived and detached from the deleted project
+            ("todo.updated", 101)
+        ]
+    );
+    assert_eq!(entries[1].data["parent_id"], 100);
+    assert_eq!(entries[2].data["archived"], true);
+    assert_eq!(
+        entries[3].data["archived"], true,
+        "for the clients to hide it"
+    );
+    assert!(entries[4].data["project_id"].is_null());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn model_event_share_logged() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+    let utx = user_context_from_token(&database, &new_token(&database, 123).await?).await?;
+    let share = PartialShare {
+        permission: Some(Permission::View),
+    };
+    let before = ChangeLog::last_position(&database).await?;
+
+    // ACT
+    ShareModelAccessController::set(&database, &utx, 101, 124, share).await?;
+    ShareModelAccessController::delete(&database, &utx, 101, 124).await?;
+    let owner_entries = ChangeLog::after(&database, 123, before, 10).await?;
+    let unshared_entries = ChangeLog::after(&database, 124, before, 10).await?;
+
+    // ASSERT
+    assert_eq!(
+        events_of(&owner_entries),
+        [("todo.updated", 101), ("todo.updated", 101)]
+    );
+    assert_eq!(
+        events_of(&unshared_entries),
+        [("todo.updated", 101), ("todo.deleted", 101)],
+        "shared, then gone for them"
+    );
+    assert_eq!(
+        owner_entries[1].data["version"],
+        unshared_entries[0].data["version"]
+            .as_i64()
+            .unwrap_or_default()
+            + 1
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn model_event_reassign_logged() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+    let utx = user_context_from_token(&database, &new_token(&database, 123).await?).await?;
+    let assign = |assignee_id| PartialTodo {
+        assignee_id: Some(assignee_id),
+        ..PartialTodo::default()
+    };
+    let before = ChangeLog::last_position(&database).await?;
+
+    // ACT
+    ModelAccessController::update(&database, &utx, 101, assign(Some(124)), None).await?;
+    ModelAccessController::update(&database, &utx, 101, assign(Some(123)), None).await?;
+    ModelAccessController::update(&database, &utx, 100, assign(Some(124)), None).await?;
+    ModelAccessController::update(&database, &utx, 100, assign(None), None).await?;
+    let owner_entries = ChangeLog::after(&database, 123, before, 10).await?;
+    let unassigned_entries = ChangeLog::after(&database, 124, before, 10).await?;
+
+    // ASSERT
+    assert_eq!(
+        events_of(&owner_entries),
+        [
+            ("todo.updated", 101),
+            ("todo.updated", 101),
+            ("todo.updated", 100),
+            ("todo.updated", 100)
+        ]
+    );
+    assert_eq!(
+        events_of(&unassigned_entries),
+        [
+            ("todo.updated", 101),
+            ("todo.deleted", 101),
+            ("todo.updated", 100),
+            ("todo.deleted", 100)
+        ],
+        "reassigned and unassigned, then gone for them"
+    );
+    assert_eq!(unassigned_entries[1].data["assignee_id"], 123);
+    assert!(unassigned_entries[3].data["assignee_id"].is_null());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn model_event_changes_read_in_transaction_order() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+    let utx = user_context_from_token(&database, &new_token(&database, 123).await?).await?;
+    let first_todo = ModelAccessController::get(&database, &utx, 100).await?;
+    let second_todo = ModelAccessController::get(&database, &utx, 101).await?;
+    let before = ChangeLog::last_position(&database).await?;
+
+    // ACT - the first transaction publishes after the second one, and commits last
+    let mut first = database.begin().await?;
+    sqlx::query("SELECT pg_current_xact_id()")
+        .execute(&mut *first)
+        .await?;
+    let mut second = database.begin().await?;
+    publish(&mut second, TodoEvent::Updated, &second_todo).await?;
+    second.commit().await?;
+    publish(&mut first, TodoEvent::Updated, &first_todo).await?;
+    let while_first_open = ChangeLog::after(&database, 123, before, 10).await?;
+    first.commit().await?;
+    let entries = ChangeLog::after(&database, 123, before, 10).await?;
+
+    // ASSERT - the second change waited, not the second transaction
+    assert!(while_first_open.is_empty());
+    assert_eq!(
+        events_of(&entries),
+        [("todo.updated", 100), ("todo.updated", 101)],
+        "in the order of their transactions"
+    );
+    assert!(entries[0].id > entries[1].id);
+
+    Ok(())
+}
+
+// Test utils
+
+fn events_of(entries: &[ChangeLogEntry]) -> Vec<(&str, i64)> {
+    entries
+        .iter()
+        .map(|entry| (entry.event.as_str(), entry.todo_id))
+        .collect()
+}
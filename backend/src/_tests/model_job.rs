@@ -0,0 +1,203 @@
+use std::time::Duration;
+
+use chrono::{TimeDelta, Utc};
+use serde_json::json;
+
+use super::{backoff, JobKind, JobListOptions, JobModelAccessController, JobQueue, JobStatus};
+use crate::{
+    model::{
+        self,
+        db::{initialize_database, DatabaseMode},
+        todo::{ModelAccessController, PartialTodo},
+    },
+    security::{new_token, user_context_from_token},
+};
+
+#[tokio::test]
+async fn model_job_retry_then_dead() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+    let dedup_key = Some(String::from("test:1"));
+    let job = JobQueue::enqueue(
+        &database,
+        JobKind::Reminder,
+        json!({}),
+        2,
+        dedup_key.clone(),
+    )
+    .await?
+    .ok_or("job not enqueued")?;
+
+    // ACT
+    let duplicate =
+        JobQueue::enqueue(&database, JobKind::Reminder, json!({}), 2, dedup_key).await?;
+    let first = JobQueue::claim(&database, 10).await?;
+    let leased = JobQueue::claim(&database, 10).await?;
+    let retried = JobQueue::fail(&database, &first[0], "first error", Duration::ZERO)
+        .await?
+        .ok_or("lease lost")?;
+    let second = JobQueue::claim(&database, 10).await?;
+    let dead = JobQueue::fail(&database, &second[0], "second error", Duration::ZERO)
+        .await?
+        .ok_or("lease lost")?;
+    let after_dead = JobQueue::claim(&database, 10).await?;
+
+    // ASSERT
+    assert!(duplicate.is_none());
+    assert_eq!(first.len(), 1);
+    assert_eq!(first[0].id, job.id);
+    assert_eq!(first[0].status, JobStatus::Running);
+    assert!(leased.is_empty(), "a running job is not claimed again");
+    assert_eq!(retried.status, JobStatus::Queued);
+    assert_eq!(retried.last_error.as_deref(), Some("first error"));
+    assert_eq!(second[0].attempts, 2);
+    assert_eq!(dead.status, JobStatus::Dead);
+    assert_eq!(dead.last_error.as_deref(), Some("second error"));
+    assert!(after_dead.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn model_job_lease_expired_then_dead() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+    let job = JobQueue::enqueue(&database, JobKind::Reminder, json!({}), 2, None)
+        .await?
+        .ok_or("job not enqueued")?;
+    // the worker crashes while it runs the job, its lease expires
+    let expire_lease = || {
+        sqlx::query("UPDATE job SET run_at = NOW() - INTERVAL '1 second' WHERE id = $1")
+            .bind(job.id)
+            .execute(&database)
+    };
+
+    // ACT
+    let first = JobQueue::claim(&database, 10).await?;
+    expire_lease().await?;
+    let second = JobQueue::claim(&database, 10).await?;
+    expire_lease().await?;
+    let after_last = JobQueue::claim(&database, 10).await?;
+    let (status, last_error): (JobStatus, Option<String>) =
+        sqlx::query_as("SELECT status, last_error FROM job WHERE id = $1")
+            .bind(job.id)
+            .fetch_one(&database)
+            .await?;
+
+    // ASSERT
+    assert_eq!(first[0].attempts, 1);
+    assert_eq!(second[0].id, job.id, "claimed again once its lease expired");
+    assert_eq!(second[0].attempts, 2);
+    assert!(after_last.is_empty(), "not claimed past max_attempts");
+    assert_eq!(status, JobStatus::Dead);
+    assert_eq!(last_error.as_deref(), Some("lease expired"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn model_job_lease_lost() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE - a slow worker's lease expires, another worker claims the job again
+    let database = initialize_database(DatabaseMode::Dev).await?;
+    let job = JobQueue::enqueue(&database, JobKind::Reminder, json!({}), 3, None)
+        .await?
+        .ok_or("job not enqueued")?;
+    let slow = JobQueue::claim(&database, 10).await?;
+    sqlx::query("UPDATE job SET run_at = NOW() - INTERVAL '1 second' WHERE id = $1")
+        .bind(job.id)
+        .execute(&database)
+        .await?;
+    let current = JobQueue::claim(&database, 10).await?;
+
+    // ACT
+    let slow_done = JobQueue::complete(&database, &slow[0]).await?;
+    let slow_failed = JobQueue::fail(&database, &slow[0], "slow error", Duration::ZERO).await?;
+    let current_done = JobQueue::complete(&database, &current[0]).await?;
+
+    // ASSERT
+    assert!(slow_done.is_none(), "the job is running again");
+    assert!(slow_failed.is_none());
+    let current_done = current_done.ok_or("lease lost")?;
+    assert_eq!(current_done.status, JobStatus::Done);
+    assert_eq!(current_done.attempts, 2);
+    assert_eq!(current_done.last_error, None);
+
+    Ok(())
+}
+
+#[test]
+fn model_job_backoff() {
+    let base = Duration::from_secs(10);
+
+    assert_eq!(backoff(base, 1), Duration::from_secs(10));
+    assert_eq!(backoff(base, 2), Duration::from_secs(20));
+    assert_eq!(backoff(base, 4), Duration::from_secs(80));
+    assert_eq!(backoff(base, 100), Duration::from_hours(1));
+}
+
+#[tokio::test]
+async fn model_job_enqueue_reminders_once() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+    let utx = user_context_from_token(&database, &new_token(&database, 123).await?).await?;
+    for (id, due_in) in [(101, TimeDelta::minutes(30)), (100, TimeDelta::minutes(10))] {
+        let data = PartialTodo {
+            due_at: Some(Some(Utc::now() + due_in)),
+            ..PartialTodo::default()
+        };
+        ModelAccessController::update(&database, &utx, id, data, None).await?;
+    }
+
+    // ACT
+    let enqueued = JobQueue::enqueue_reminders(&database, 60, 5).await?;
+    let enqueued_again = JobQueue::enqueue_reminders(&database, 60, 5).await?;
+
+    // ASSERT
+    // todo 100 is closed
+    assert_eq!(enqueued, 1);
+    assert_eq!(enqueued_again, 0);
+    let jobs = JobModelAccessController::list(&database, &utx, JobListOptions::default()).await?;
+    assert_eq!(jobs.len(), 1);
+    assert_eq!(jobs[0].kind, "reminder");
+    assert_eq!(jobs[0].payload["todo_id"], 101);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn model_job_list_admin_only() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+    let utx = user_context_from_token(&database, &new_token(&database, 124).await?).await?;
+
+    // ACT
+    let jobs = JobModelAccessController::list(&database, &utx, JobListOptions::default()).await;
+
+    // ASSERT
+    assert!(matches!(jobs, Err(model::Error::AccessDenied("jobs", _))));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn model_job_purge_finished() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+    for _ in 0..2 {
+        JobQueue::enqueue(&database, JobKind::Reminder, json!({}), 1, None).await?;
+    }
+    let claimed = JobQueue::claim(&database, 1).await?;
+    JobQueue::complete(&database, &claimed[0]).await?;
+
+    // ACT
+    let kept = JobQueue::purge(&database, Duration::from_hours(1)).await?;
+    let purged = JobQueue::purge(&database, Duration::ZERO).await?;
+    let left = JobQueue::claim(&database, 10).await?;
+
+    // ASSERT
+    assert_eq!(kept, 0, "the done job is within the retention time");
+    assert_eq!(purged, 1);
+    assert_eq!(left.len(), 1, "the queued job is kept");
+
+    Ok(())
+}
@@ -0,0 +1,129 @@
+use std::{fs, path::Path};
+
+use super::{load_migrations, migration_status, revert_last_migration, run_migrations};
+use crate::model::{
+    self,
+    db::{initialize_database, DatabaseMode},
+};
+
+const TEST_MIGRATIONS_DIRECTORY: &str = "target/test_migrations/";
+
+#[tokio::test]
+async fn model_migration_applied_once() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE - initialize_database already applied every migration
+    let database = initialize_database(DatabaseMode::Dev).await?;
+    let migrations = load_migrations(Path::new("sql/migrations/"))?;
+
+    // ACT
+    let applied = run_migrations(&database, Path::new("sql/migrations/")).await?;
+
+    // ASSERT
+    assert!(applied.is_empty(), "nothing left to apply");
+    let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM schema_migrations")
+        .fetch_one(&database)
+        .await?;
+    assert_eq!(count, i64::try_from(migrations.len())?);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn model_migration_failure_and_checksum() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+    let _ = fs::remove_dir_all(TEST_MIGRATIONS_DIRECTORY);
+    fs::create_dir_all(TEST_MIGRATIONS_DIRECTORY)?;
+
+    // semicolons in comments, strings and function bodies would have broken the old splitting
+    let migration = "-- a comment; with a semicolon
+        CREATE TABLE test_migration (id BIGINT, label TEXT DEFAULT 'a;b');
+        CREATE FUNCTION test_migration_fn() RETURNS INTEGER AS $$ BEGIN RETURN 1; END; $$ LANGUAGE plpgsql;";
+    fs::write(format!("{TEST_MIGRATIONS_DIRECTORY}90-ok.sql"), migration)?;
+    fs::write(
+        format!("{TEST_MIGRATIONS_DIRECTORY}91-broken.sql"),
+        "INSERT INTO test_migration (id) VALUES (1); SELECT * FROM missing_table;",
+    )?;
+
+    // ACT - the broken migration fails the run, and is rolled back
+    let result = run_migrations(&database, Path::new(TEST_MIGRATIONS_DIRECTORY)).await;
+
+    // ASSERT
+    match result {
+        Err(model::Error::MigrationFailed(version, _)) => assert_eq!(version, "91-broken"),
+        other => unreachable!("Should be MigrationFailed: {other:?}"),
+    }
+    let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM test_migration")
+        .fetch_one(&database)
+        .await?;
+    assert_eq!(count, 0, "the broken migration must be rolled back");
+
+    // ACT - an applied migration modified afterwards
+    fs::remove_file(format!("{TEST_MIGRATIONS_DIRECTORY}91-broken.sql"))?;
+    fs::write(
+        format!("{TEST_MIGRATIONS_DIRECTORY}90-ok.sql"),
+        format!("{migration}\n-- edited"),
+    )?;
+    let result = run_migrations(&database, Path::new(TEST_MIGRATIONS_DIRECTORY)).await;
+
+    // ASSERT
+    match result {
+        Err(model::Error::MigrationChecksumMismatch(version)) => assert_eq!(version, "90-ok"),
+        other => unreachable!("Should be MigrationChecksumMismatch: {other:?}"),
+    }
+
+    fs::remove_dir_all(TEST_MIGRATIONS_DIRECTORY)?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn model_migration_revert_and_status() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+    let directory = Path::new("sql/migrations/");
+    let last_version = load_migrations(directory)?
+        .pop()
+        .map(|migration| migration.version)
+        .unwrap_or_default();
+
+    // ACT
+    let reverted = revert_last_migration(&database, directory).await?;
+    let statuses = migration_status(&database, directory).await?;
+
+    // ASSERT
+    assert_eq!(reverted, Some(last_version.clone()));
+    let last_status = statuses.last().ok_or("no migration")?;
+    assert_eq!(last_status.version, last_version);
+    assert!(last_status.applied_at.is_none(), "reverted, so pending");
+
+    // ACT - and applied again
+    let applied = run_migrations(&database, directory).await?;
+
+    // ASSERT
+    assert_eq!(applied, vec![last_version]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn model_migration_status_read_only() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE - a database never migrated
+    let database = initialize_database(DatabaseMode::Dev).await?;
+    let directory = Path::new("sql/migrations/");
+    sqlx::query("DROP TABLE schema_migrations")
+        .execute(&database)
+        .await?;
+
+    // ACT
+    let statuses = migration_status(&database, directory).await?;
+
+    // ASSERT
+    assert_eq!(statuses.len(), load_migrations(directory)?.len());
+    assert!(statuses.iter().all(|status| status.applied_at.is_none()));
+    let created: bool = sqlx::query_scalar("SELECT to_regclass('schema_migrations') IS NOT NULL")
+        .fetch_one(&database)
+        .await?;
+    assert!(!created, "the status must not create the table");
+
+    Ok(())
+}
@@ -0,0 +1,80 @@
+use super::{recipients, NotificationModelAccessController, PartialNotificationPreferences};
+use crate::{
+    model::{
+        self,
+        comment::{CommentModelAccessController, PartialComment},
+        db::{initialize_database, DatabaseMode},
+        job::{JobListOptions, JobModelAccessController},
+        share::{PartialShare, Permission, ShareModelAccessController},
+    },
+    security::{new_token, user_context_from_token},
+};
+
+#[tokio::test]
+async fn model_notification_preferences_update() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+    let utx = user_context_from_token(&database, &new_token(&database, 123).await?).await?;
+
+    // ACT
+    let before = NotificationModelAccessController::get(&database, &utx).await?;
+    let data = PartialNotificationPreferences {
+        commented: Some(false),
+        ..PartialNotificationPreferences::default()
+    };
+    let updated = NotificationModelAccessController::update(&database, &utx, data).await?;
+    let data = PartialNotificationPreferences {
+        email: Some(Some(String::from("not an address"))),
+        ..PartialNotificationPreferences::default()
+    };
+    let invalid = NotificationModelAccessController::update(&database, &utx, data).await;
+
+    // ASSERT
+    assert_eq!(before.email.as_deref(), Some("demo1@example.com"));
+    assert!(before.commented);
+    assert_eq!(updated.email.as_deref(), Some("demo1@example.com"));
+    assert!(!updated.commented);
+    assert!(updated.assigned && updated.due);
+    match invalid {
+        Err(model::Error::ValidationFailed(errors)) => assert_eq!(errors[0].field, "email"),
+        other => panic!("expected an email validation error, got {other:?}"),
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn model_notification_comment_notifies_owner() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+    let owner = user_context_from_token(&database, &new_token(&database, 123).await?).await?;
+    let other = user_context_from_token(&database, &new_token(&database, 124).await?).await?;
+    let share = PartialShare {
+        permission: Some(Permission::View),
+    };
+    ShareModelAccessController::set(&database, &owner, 101, 124, share).await?;
+
+    // ACT
+    let data = PartialComment {
+        body: Some(String::from("by the other user")),
+    };
+    let comment = CommentModelAccessController::create(&database, &other, 101, data).await?;
+
+    // ASSERT
+    let jobs = JobModelAccessController::list(&database, &owner, JobListOptions::default()).await?;
+    assert_eq!(jobs.len(), 1);
+    assert_eq!(jobs[0].kind, "notification");
+    assert_eq!(jobs[0].payload["event"], "Commented");
+    assert_eq!(jobs[0].payload["user_id"], 123);
+    assert_eq!(jobs[0].payload["comment_id"], comment.id);
+
+    Ok(())
+}
+
+#[test]
+fn model_notification_recipients() {
+    assert_eq!(recipients(123, None, None), vec![123]);
+    assert_eq!(recipients(123, Some(124), None), vec![123, 124]);
+    assert_eq!(recipients(123, Some(123), None), vec![123]);
+    assert_eq!(recipients(123, Some(124), Some(123)), vec![124]);
+}
@@ -0,0 +1,189 @@
+use super::{PartialProject, ProjectModelAccessController};
+use crate::{
+    model::{
+        self,
+        db::{initialize_database, DatabaseMode},
+        todo::{ModelAccessController, PartialTodo, TodoListOptions},
+    },
+    security::{new_token, user_context_from_token},
+};
+
+#[tokio::test]
+async fn model_project_crud() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+    let utx = user_context_from_token(&database, &new_token(&database, 123).await?).await?;
+
+    // ACT
+    let created = ProjectModelAccessController::create(
+        &database,
+        &utx,
+        PartialProject {
+            name: Some(String::from("new project")),
+            ..PartialProject::default()
+        },
+    )
+    .await?;
+    let updated = ProjectModelAccessController::update(
+        &database,
+        &utx,
+        created.id,
+        PartialProject {
+            color: Some(String::from("#00ff00")),
+            ..PartialProject::default()
+        },
+    )
+    .await?;
+    let projects = ProjectModelAccessController::list(&database, &utx).await?;
+    let deleted = ProjectModelAccessController::delete(&database, &utx, created.id).await?;
+    let get_deleted = ProjectModelAccessController::get(&database, &utx, created.id).await;
+
+    // ASSERT
+    assert!(created.id >= 1000, "ID should be >= 1000");
+    assert_eq!(created.color, "#808080");
+    assert!(!created.archived);
+    assert_eq!(updated.name, "new project");
+    assert_eq!(updated.color, "#00ff00");
+    let names: Vec<&str> = projects.iter().map(|p| p.name.as_str()).collect();
+    assert_eq!(names, ["new project", "project 1"]);
+    assert_eq!(deleted.id, created.id);
+    assert!(matches!(
+        get_deleted,
+        Err(model::Error::EntityNotFound("project", _))
+    ));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn model_project_validation_and_isolation() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+    let utx_124 = user_context_from_token(&database, &new_token(&database, 124).await?).await?;
+
+    // ACT
+    let invalid = ProjectModelAccessController::create(
+        &database,
+        &utx_124,
+        PartialProject {
+            name: Some(String::new()),
+            color: Some(String::from("red")),
+            archived: None,
+        },
+    )
+    .await;
+    let other_user_project = ProjectModelAccessController::get(&database, &utx_124, 1).await;
+    let todo_in_other_user_project = ModelAccessController::create(
+        &database,
+        &utx_124,
+        PartialTodo {
+            project_id: Some(Some(1)),
+            ..PartialTodo::default()
+        },
+    )
+    .await;
+
+    // ASSERT
+    match invalid {
+        Err(model::Error::ValidationFailed(errors)) => {
+            let fields: Vec<&str> = errors.iter().map(|error| error.field).collect();
+            assert_eq!(fields, ["name", "color"]);
+        }
+        other => unreachable!("Wrong result: {other:?}"),
+    }
+    assert!(matches!(
+        other_user_project,
+        Err(model::Error::EntityNotFound("project", _))
+    ));
+    match todo_in_other_user_project {
+        Err(model::Error::ValidationFailed(errors)) => assert_eq!(errors[0].field, "project_id"),
+        other => unreachable!("Wrong result: {other:?}"),
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn model_project_archive_cascade() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+    let utx = user_context_from_token(&database, &new_token(&database, 123).await?).await?;
+    let archive = |archived| PartialProject {
+        archived: Some(archived),
+        ..PartialProject::default()
+    };
+
+    // ACT
+    ProjectModelAccessController::update(&database, &utx, 1, archive(true)).await?;
+    let archived_todo = ModelAccessController::get(&database, &utx, 101).await?;
+    let listed = ModelAccessController::list(&database, &utx, &TodoListOptions::default()).await?;
+    let new_todo_in_archived = ModelAccessController::create(
+        &database,
+        &utx,
+        PartialTodo {
+            project_id: Some(Some(1)),
+            ..PartialTodo::default()
+        },
+    )
+    .await;
+    // still editable, the project is unchanged
+    let edited = ModelAccessController::update(
+        &database,
+        &utx,
+        101,
+        PartialTodo {
+            title: Some(String::from("edited while archived")),
+            ..PartialTodo::default()
+        },
+        None,
+    )
+    .await?;
+    ProjectModelAccessController::update(&database, &utx, 1, archive(false)).await?;
+    let unarchived_todo = ModelAccessController::get(&database, &utx, 101).await?;
+
+    // ASSERT
+    assert!(archived_todo.archived);
+    let ids: Vec<i64> = listed.items.iter().map(|todo| todo.id).collect();
+    assert_eq!(ids, [100], "the archived todos are not listed");
+    assert!(matches!(
+        new_todo_in_archived,
+        Err(model::Error::ValidationFailed(_))
+    ));
+    assert!(edited.archived);
+    assert!(!unarchived_todo.archived);
+    assert_eq!(unarchived_todo.project_id, Some(1));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn model_project_delete_detaches_todos() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+    let utx = user_context_from_token(&database, &new_token(&database, 123).await?).await?;
+    ProjectModelAccessController::update(
+        &database,
+        &utx,
+        1,
+        PartialProject {
+            archived: Some(true),
+            ..PartialProject::default()
+        },
+    )
+    .await?;
+    let before = ModelAccessController::get(&database, &utx, 101).await?;
+
+    // ACT
+    ProjectModelAccessController::delete(&database, &utx, 1).await?;
+    let after = ModelAccessController::get(&database, &utx, 101).await?;
+
+    // ASSERT
+    assert_eq!(before.project_id, Some(1));
+    assert_eq!(after.project_id, None);
+    assert!(!after.archived);
+    // a new version, so the cached ETag of the todo is stale
+    assert_eq!(after.version, before.version + 1);
+    assert!(after.mtime > before.mtime);
+
+    Ok(())
+}
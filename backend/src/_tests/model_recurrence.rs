@@ -0,0 +1,206 @@
+use chrono::{DateTime, Utc};
+
+use super::Recurrence;
+use crate::{
+    model::{
+        self,
+        db::{initialize_database, DatabaseMode},
+        share::{PartialShare, Permission, ShareModelAccessController},
+        todo::{ModelAccessController, PartialTodo, Status, Todo, TodoListOptions},
+    },
+    security::{new_token, user_context_from_token},
+};
+
+fn date(value: &str) -> DateTime<Utc> {
+    format!("{value}T09:30:00Z").parse().expect("valid date")
+}
+
+fn next(rule: &str, after: &str) -> DateTime<Utc> {
+    let recurrence: Recurrence = rule.parse().expect("valid rule");
+    recurrence.next_after(date(after))
+}
+
+#[test]
+fn model_recurrence_parse() {
+    // ACT
+    let normalized = "freq=weekly; byday=fr,MO ;interval=2"
+        .parse::<Recurrence>()
+        .map(|recurrence| recurrence.to_string());
+    let invalid = [
+        "",
+        "FREQ=YEARLY",
+        "FREQ=DAILY;BYDAY=MO",
+        "FREQ=WEEKLY;BYDAY=XX",
+        "FREQ=MONTHLY;BYMONTHDAY=32",
+        "FREQ=DAILY;INTERVAL=0",
+        "FREQ=DAILY;COUNT=3",
+    ]
+    .map(str::parse::<Recurrence>);
+
+    // ASSERT
+    assert_eq!(
+        normalized.ok().as_deref(),
+        Some("FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,FR")
+    );
+    for result in invalid {
+        assert!(
+            matches!(&result, Err(error) if error.field == "recurrence"),
+            "{result:?}"
+        );
+    }
+}
+
+#[test]
+fn model_recurrence_next_after() {
+    // 2024-01-01 is a monday
+    assert_eq!(
+        next("FREQ=DAILY;INTERVAL=2", "2024-01-01"),
+        date("2024-01-03")
+    );
+    assert_eq!(next("FREQ=WEEKLY", "2024-01-03"), date("2024-01-10"));
+    assert_eq!(
+        next("FREQ=WEEKLY;BYDAY=MO,TH", "2024-01-01"),
+        date("2024-01-04")
+    );
+    assert_eq!(
+        next("FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,TH", "2024-01-04"),
+        date("2024-01-15")
+    );
+    assert_eq!(next("FREQ=MONTHLY", "2024-01-15"), date("2024-02-15"));
+    assert_eq!(
+        next("FREQ=MONTHLY;BYMONTHDAY=20", "2024-01-15"),
+        date("2024-01-20")
+    );
+    assert_eq!(
+        next("FREQ=MONTHLY;BYMONTHDAY=31", "2024-01-31"),
+        date("2024-02-29")
+    );
+    assert_eq!(
+        next("FREQ=MONTHLY;BYMONTHDAY=31", "2024-02-29"),
+        date("2024-03-31")
+    );
+    let monthly: Recurrence = "FREQ=MONTHLY;INTERVAL=2".parse().expect("valid rule");
+    assert_eq!(
+        monthly.anchored(date("2024-01-31")).to_string(),
+        "FREQ=MONTHLY;INTERVAL=2;BYMONTHDAY=31"
+    );
+}
+
+#[tokio::test]
+async fn model_recurrence_close_creates_next() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+    let utx = user_context_from_token(&database, &new_token(&database, 123).await?).await?;
+    let created = ModelAccessController::create(
+        &database,
+        &utx,
+        PartialTodo {
+            title: Some(String::from("water the plants")),
+            due_at: Some(Some(date("2024-01-01"))),
+            tags: Some(vec![String::from("home")]),
+            recurrence: Some(Some(String::from("freq=weekly;byday=mo,th"))),
+            ..PartialTodo::default()
+        },
+    )
+    .await?;
+    let invalid = ModelAccessController::update(
+        &database,
+        &utx,
+        created.id,
+        PartialTodo {
+            recurrence: Some(Some(String::from("FREQ=HOURLY"))),
+            ..PartialTodo::default()
+        },
+        None,
+    )
+    .await;
+
+    // ACT
+    let closed = ModelAccessController::update(
+        &database,
+        &utx,
+        created.id,
+        PartialTodo {
+            status: Some(Status::Closed),
+            ..PartialTodo::default()
+        },
+        None,
+    )
+    .await?;
+    let listed = ModelAccessController::list(&database, &utx, &TodoListOptions::default()).await?;
+
+    // ASSERT
+    assert_eq!(
+        created.recurrence.as_deref(),
+        Some("FREQ=WEEKLY;BYDAY=MO,TH")
+    );
+    assert!(matches!(invalid, Err(model::Error::ValidationFailed(_))));
+    assert_eq!(closed.status, Status::Closed);
+    assert_eq!(closed.recurrence, None);
+    let next = &listed.items[0];
+    assert!(next.id > created.id);
+    assert_eq!(next.title, "water the plants");
+    assert_eq!(next.status, Status::Open);
+    assert_eq!(next.due_at, Some(date("2024-01-04")));
+    assert_eq!(next.tags, ["home"]);
+    assert_eq!(next.recurrence.as_deref(), Some("FREQ=WEEKLY;BYDAY=MO,TH"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn model_recurrence_monthly_keeps_its_day() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+    let utx = user_context_from_token(&database, &new_token(&database, 123).await?).await?;
+    let utx_124 = user_context_from_token(&database, &new_token(&database, 124).await?).await?;
+    let created = ModelAccessController::create(
+        &database,
+        &utx,
+        PartialTodo {
+            title: Some(String::from("pay the rent")),
+            due_at: Some(Some(date("2024-01-31"))),
+            recurrence: Some(Some(String::from("FREQ=MONTHLY"))),
+            ..PartialTodo::default()
+        },
+    )
+    .await?;
+    let share = PartialShare {
+        permission: Some(Permission::Edit),
+    };
+    ShareModelAccessController::set(&database, &utx, created.id, 124, share).await?;
+    let close_and_next = |id| {
+        let (database, utx) = (&database, &utx);
+        async move {
+            let closed = PartialTodo {
+                status: Some(Status::Closed),
+                ..PartialTodo::default()
+            };
+            ModelAccessController::update(database, utx, id, closed, None).await?;
+            let listed =
+                ModelAccessController::list(database, utx, &TodoListOptions::default()).await?;
+            Ok::<Todo, model::Error>(listed.items[0].clone())
+        }
+    };
+
+    // ACT
+    let february = close_and_next(created.id).await?;
+    let march = close_and_next(february.id).await?;
+    let shares = ShareModelAccessController::list(&database, &utx, march.id).await?;
+    let seen_by_124 = ModelAccessController::get(&database, &utx_124, march.id).await?;
+
+    // ASSERT
+    assert_eq!(february.due_at, Some(date("2024-02-29")));
+    assert_eq!(
+        february.recurrence.as_deref(),
+        Some("FREQ=MONTHLY;BYMONTHDAY=31")
+    );
+    assert!(march.id > february.id);
+    assert_eq!(march.due_at, Some(date("2024-03-31")), "back to the 31st");
+    assert_eq!(shares.len(), 1);
+    assert_eq!(shares[0].user_id, 124);
+    assert_eq!(shares[0].permission, Permission::Edit);
+    assert_eq!(seen_by_124.id, march.id);
+
+    Ok(())
+}
@@ -0,0 +1,187 @@
+use super::{PartialShare, Permission, ShareModelAccessController};
+use crate::{
+    model::{
+        self,
+        db::{initialize_database, DatabaseMode},
+        tag::TagModelAccessController,
+        todo::{ModelAccessController, PartialTodo, Status, TodoListOptions, TodoScope},
+    },
+    security::{new_token, user_context_from_token},
+};
+
+fn share(permission: Permission) -> PartialShare {
+    PartialShare {
+        permission: Some(permission),
+    }
+}
+
+fn retitle(title: &str) -> PartialTodo {
+    PartialTodo {
+        title: Some(String::from(title)),
+        ..PartialTodo::default()
+    }
+}
+
+#[tokio::test]
+async fn model_share_view() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+    let utx_123 = user_context_from_token(&database, &new_token(&database, 123).await?).await?;
+    let utx_124 = user_context_from_token(&database, &new_token(&database, 124).await?).await?;
+
+    // ACT
+    let not_shared = ModelAccessController::get(&database, &utx_123, 200).await;
+    ShareModelAccessController::set(&database, &utx_124, 200, 123, share(Permission::View)).await?;
+    let visible = ModelAccessController::get(&database, &utx_123, 200).await?;
+    let scope = |scope| TodoListOptions {
+        scope,
+        ..TodoListOptions::default()
+    };
+    let all = ModelAccessController::list(&database, &utx_123, &scope(None)).await?;
+    let mine =
+        ModelAccessController::list(&database, &utx_123, &scope(Some(TodoScope::Mine))).await?;
+    let only_shared =
+        ModelAccessController::list(&database, &utx_123, &scope(Some(TodoScope::Shared))).await?;
+    let update = ModelAccessController::update(&database, &utx_123, 200, retitle("x"), None).await;
+    let delete = ModelAccessController::delete(&database, &utx_123, 200, None).await;
+    let reshare =
+        ShareModelAccessController::set(&database, &utx_123, 200, 123, share(Permission::Edit))
+            .await;
+    let shares = ShareModelAccessController::list(&database, &utx_123, 200).await?;
+
+    // ASSERT
+    assert!(matches!(
+        not_shared,
+        Err(model::Error::EntityNotFound("todo", _))
+    ));
+    assert_eq!(visible.cid, 124);
+    let ids = |page: &model::Page<model::Todo>| -> Vec<i64> {
+        page.items.iter().map(|todo| todo.id).collect()
+    };
+    assert_eq!(ids(&all), [200, 101, 100]);
+    assert_eq!(ids(&mine), [101, 100]);
+    assert_eq!(ids(&only_shared), [200]);
+    for result in [update, delete, reshare.map(|_| model::Todo::default())] {
+        assert!(matches!(result, Err(model::Error::AccessDenied("todo", _))));
+    }
+    assert_eq!(shares.len(), 1);
+    assert_eq!(shares[0].permission, Permission::View);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn model_share_edit() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+    let utx_123 = user_context_from_token(&database, &new_token(&database, 123).await?).await?;
+    let utx_124 = user_context_from_token(&database, &new_token(&database, 124).await?).await?;
+    ShareModelAccessController::set(&database, &utx_124, 200, 123, share(Permission::Edit)).await?;
+
+    // ACT
+    let updated = ModelAccessController::update(
+        &database,
+        &utx_123,
+        200,
+        PartialTodo {
+            tags: Some(vec![String::from("shared")]),
+            ..retitle("edited by demo1")
+        },
+        None,
+    )
+    .await?;
+    let owner_tags = TagModelAccessController::list(&database, &utx_124).await?;
+    let editor_tags = TagModelAccessController::list(&database, &utx_123).await?;
+    ShareModelAccessController::delete(&database, &utx_124, 200, 123).await?;
+    let unshared = ModelAccessController::get(&database, &utx_123, 200).await;
+
+    // ASSERT
+    assert_eq!(updated.title, "edited by demo1");
+    assert_eq!(updated.cid, 124);
+    assert!(owner_tags.iter().any(|tag| tag.name == "shared"));
+    assert!(!editor_tags.iter().any(|tag| tag.name == "shared"));
+    assert!(matches!(
+        unshared,
+        Err(model::Error::EntityNotFound("todo", _))
+    ));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn model_share_new_todo_version() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+    let utx_124 = user_context_from_token(&database, &new_token(&database, 124).await?).await?;
+    let before = ModelAccessController::get(&database, &utx_124, 200).await?;
+
+    // ACT
+    ShareModelAccessController::set(&database, &utx_124, 200, 123, share(Permission::View)).await?;
+    let shared = ModelAccessController::get(&database, &utx_124, 200).await?;
+    ShareModelAccessController::delete(&database, &utx_124, 200, 123).await?;
+    let unshared = ModelAccessController::get(&database, &utx_124, 200).await?;
+
+    // ASSERT - a client holding the todo sees it changed
+    assert_eq!(shared.version, before.version + 1);
+    assert_eq!(unshared.version, before.version + 2);
+    assert!(unshared.mtime >= shared.mtime);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn model_share_assignee() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+    let utx_123 = user_context_from_token(&database, &new_token(&database, 123).await?).await?;
+    let utx_124 = user_context_from_token(&database, &new_token(&database, 124).await?).await?;
+    let assign = |assignee_id| PartialTodo {
+        assignee_id: Some(Some(assignee_id)),
+        ..PartialTodo::default()
+    };
+
+    // ACT
+    let unknown_user =
+        ModelAccessController::update(&database, &utx_124, 200, assign(999), None).await;
+    let with_owner =
+        ShareModelAccessController::set(&database, &utx_124, 200, 124, share(Permission::View))
+            .await;
+    ModelAccessController::update(&database, &utx_124, 200, assign(123), None).await?;
+    let assigned = ModelAccessController::list(
+        &database,
+        &utx_123,
+        &TodoListOptions {
+            scope: Some(TodoScope::Assigned),
+            ..TodoListOptions::default()
+        },
+    )
+    .await?;
+    let closed = ModelAccessController::update(
+        &database,
+        &utx_123,
+        200,
+        PartialTodo {
+            status: Some(Status::Closed),
+            ..PartialTodo::default()
+        },
+        None,
+    )
+    .await?;
+
+    // ASSERT
+    for (result, field) in [
+        (unknown_user.map(|_| ()), "assignee_id"),
+        (with_owner.map(|_| ()), "user_id"),
+    ] {
+        match result {
+            Err(model::Error::ValidationFailed(errors)) => assert_eq!(errors[0].field, field),
+            other => unreachable!("Wrong result: {other:?}"),
+        }
+    }
+    let ids: Vec<i64> = assigned.items.iter().map(|todo| todo.id).collect();
+    assert_eq!(ids, [200]);
+    assert_eq!(closed.assignee_id, Some(123));
+    assert_eq!(closed.status, Status::Closed);
+
+    Ok(())
+}
@@ -0,0 +1,171 @@
+use std::time::Duration;
+
+use super::{check_parent, CloseParentRule};
+use crate::{
+    model::{
+        self,
+        db::{initialize_database, DatabaseMode, PostgresDatabase},
+        test_utils::create_subtask,
+        todo::{patch_todo, ModelAccessController, PartialTodo, Status, TodoPatch},
+    },
+    security::{new_token, user_context_from_token, UserContext},
+};
+
+#[tokio::test]
+async fn model_subtask_tree_progress() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+    let utx = user_context_from_token(&database, &new_token(&database, 123).await?).await?;
+    let open = create_subtask(&database, &utx, 101, Status::Open).await?;
+    let closed = create_subtask(&database, &utx, 101, Status::Closed).await?;
+    let grandchild = create_subtask(&database, &utx, open, Status::Closed).await?;
+
+    // ACT
+    let tree = ModelAccessController::get_tree(&database, &utx, 101).await?;
+    let other_user_tree = ModelAccessController::get_tree(&database, &utx, 200).await;
+
+    // ASSERT
+    assert_eq!(tree.todo.id, 101);
+    assert_eq!((tree.progress.closed, tree.progress.total), (1, 2));
+    let children: Vec<i64> = tree.children.iter().map(|child| child.todo.id).collect();
+    assert_eq!(children, vec![open, closed]);
+    assert_eq!(tree.children[0].children[0].todo.id, grandchild);
+    assert_eq!(tree.children[0].children[0].todo.parent_id, Some(open));
+    assert_eq!(
+        (
+            tree.children[0].progress.closed,
+            tree.children[0].progress.total
+        ),
+        (1, 1)
+    );
+    assert_eq!(tree.children[1].progress.total, 0);
+    assert!(matches!(
+        other_user_tree,
+        Err(model::Error::EntityNotFound("todo", _))
+    ));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn model_subtask_concurrent_moves_no_cycle() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+    let utx = user_context_from_token(&database, &new_token(&database, 123).await?).await?;
+    let first = create_subtask(&database, &utx, 101, Status::Open).await?;
+    let second = create_subtask(&database, &utx, 101, Status::Open).await?;
+
+    // ACT - the first moves under the second, while the second is moved under the first
+    let mut first_move = database.begin().await?;
+    check_parent(&mut first_move, &utx, Some(first), second).await?;
+    sqlx::query("UPDATE todo SET parent_id = $2 WHERE id = $1")
+        .bind(first)
+        .bind(second)
+        .execute(&mut *first_move)
+        .await?;
+    let second_move = tokio::spawn({
+        let database = database.clone();
+        let utx = UserContext { user_id: 123 };
+        async move {
+            let mut transaction = database.begin().await?;
+            check_parent(&mut transaction, &utx, Some(second), first).await
+        }
+    });
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    let waited = !second_move.is_finished();
+    first_move.commit().await?;
+    let second_checked = second_move.await?;
+
+    // ASSERT
+    assert!(waited, "the second move waits for the first one");
+    assert!(matches!(
+        second_checked,
+        Err(model::Error::ValidationFailed(_))
+    ));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn model_subtask_parent_invalid() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+    let utx = user_context_from_token(&database, &new_token(&database, 123).await?).await?;
+    let child = create_subtask(&database, &utx, 101, Status::Open).await?;
+    let grandchild = create_subtask(&database, &utx, child, Status::Open).await?;
+
+    // ACT
+    let itself = set_parent(&database, &utx, 101, 101).await;
+    let cycle = set_parent(&database, &utx, 101, grandchild).await;
+    let other_user = set_parent(&database, &utx, child, 200).await;
+    let moved = set_parent(&database, &utx, grandchild, 100).await?;
+
+    // ASSERT
+    for result in [itself, cycle, other_user] {
+        match result {
+            Err(model::Error::ValidationFailed(errors)) => {
+                assert_eq!(errors[0].field, "parent_id");
+            }
+            other => panic!("expected a parent_id validation error, got {other:?}"),
+        }
+    }
+    assert_eq!(moved.parent_id, Some(100));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn model_subtask_close_parent_rules() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+    let utx = user_context_from_token(&database, &new_token(&database, 123).await?).await?;
+    let child = create_subtask(&database, &utx, 101, Status::Open).await?;
+    let grandchild = create_subtask(&database, &utx, child, Status::Open).await?;
+
+    // ACT
+    let required = close(&database, &utx, 101, CloseParentRule::Require).await;
+    let cascaded = close(&database, &utx, 101, CloseParentRule::Cascade).await?;
+    let grandchild = ModelAccessController::get(&database, &utx, grandchild).await?;
+
+    // ASSERT
+    match required {
+        Err(model::Error::ValidationFailed(errors)) => assert_eq!(errors[0].field, "status"),
+        other => panic!("expected a status validation error, got {other:?}"),
+    }
+    assert_eq!(cascaded.status, Status::Closed);
+    assert_eq!(grandchild.status, Status::Closed);
+    assert!(grandchild.completed_at.is_some());
+    assert_eq!(grandchild.version, 2);
+
+    Ok(())
+}
+
+// Test utils
+
+async fn set_parent(
+    database: &PostgresDatabase,
+    utx: &UserContext,
+    id: i64,
+    parent_id: i64,
+) -> Result<model::Todo, model::Error> {
+    let data = PartialTodo {
+        parent_id: Some(Some(parent_id)),
+        ..PartialTodo::default()
+    };
+
+    ModelAccessController::update(database, utx, id, data, None).await
+}
+
+async fn close(
+    database: &PostgresDatabase,
+    utx: &UserContext,
+    id: i64,
+    rule: CloseParentRule,
+) -> Result<model::Todo, model::Error> {
+    let patch = TodoPatch::Partial(PartialTodo {
+        status: Some(Status::Closed),
+        ..PartialTodo::default()
+    });
+
+    patch_todo(database, utx, id, patch, None, rule).await
+}
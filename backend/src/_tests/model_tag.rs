@@ -0,0 +1,88 @@
+use super::{normalize_tags, TagModelAccessController};
+use crate::{
+    model::{
+        db::{initialize_database, DatabaseMode},
+        todo::{ModelAccessController, PartialTodo, TodoListOptions},
+    },
+    security::{new_token, user_context_from_token},
+};
+
+#[tokio::test]
+async fn model_tag_list_counts() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+    let utx = user_context_from_token(&database, &new_token(&database, 123).await?).await?;
+
+    // ACT
+    let tags = TagModelAccessController::list(&database, &utx).await?;
+
+    // ASSERT - the tag 'work' of demo2 is not counted
+    let tags: Vec<(&str, i64)> = tags.iter().map(|t| (t.name.as_str(), t.count)).collect();
+    assert_eq!(tags, [("home", 1), ("work", 2)]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn model_tag_todo_sync() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+    let utx = user_context_from_token(&database, &new_token(&database, 123).await?).await?;
+
+    // ACT
+    let created = ModelAccessController::create(
+        &database,
+        &utx,
+        PartialTodo {
+            title: Some(String::from("tagged")),
+            tags: Some(vec![
+                String::from(" urgent "),
+                String::from("work"),
+                String::from("urgent"),
+            ]),
+            ..PartialTodo::default()
+        },
+    )
+    .await?;
+    let untagged = ModelAccessController::update(
+        &database,
+        &utx,
+        101,
+        PartialTodo {
+            tags: Some(Vec::new()),
+            ..PartialTodo::default()
+        },
+        None,
+    )
+    .await?;
+    let by_tag = ModelAccessController::list(
+        &database,
+        &utx,
+        &TodoListOptions {
+            tag: Some(String::from("work")),
+            ..TodoListOptions::default()
+        },
+    )
+    .await?;
+    let tags = TagModelAccessController::list(&database, &utx).await?;
+
+    // ASSERT
+    assert_eq!(created.tags, ["urgent", "work"]);
+    assert!(untagged.tags.is_empty());
+    let ids: Vec<i64> = by_tag.items.iter().map(|todo| todo.id).collect();
+    assert_eq!(ids, [created.id, 100]);
+    let tags: Vec<(&str, i64)> = tags.iter().map(|t| (t.name.as_str(), t.count)).collect();
+    assert_eq!(tags, [("home", 0), ("urgent", 1), ("work", 2)]);
+
+    Ok(())
+}
+
+#[test]
+fn model_tag_normalize() {
+    assert!(normalize_tags(&[String::from("  ")]).is_err());
+    assert!(normalize_tags(&["x".repeat(65)]).is_err());
+    assert_eq!(
+        normalize_tags(&[String::from("b"), String::from("a "), String::from("b")]).ok(),
+        Some(vec![String::from("b"), String::from("a")])
+    );
+}
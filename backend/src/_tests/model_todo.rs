@@ -1,24 +1,26 @@
-use super::{ModelAccessController, PartialTodo};
+use super::{ModelAccessController, PartialTodo, TodoListOptions, TodoPatch};
 use crate::{
     model::{
         self,
-        db::initialize_database,
+        db::{initialize_database, DatabaseMode},
+        page::Cursor,
         todo::{self, Todo},
     },
-    security::{user_context_from_token, UserContext},
+    security::{new_token, user_context_from_token, UserContext},
 };
 
 #[tokio::test]
 async fn model_todo_create() -> Result<(), Box<dyn std::error::Error>> {
     // ARRANGE
-    let database = initialize_database().await?;
+    let database = initialize_database(DatabaseMode::Dev).await?;
 
     let data_fixture = PartialTodo {
         title: Some(String::from("test - model_todo_create 1")),
         ..PartialTodo::default()
     };
 
-    let user_context = user_context_from_token(&database, "123").await?;
+    let user_context =
+        user_context_from_token(&database, &new_token(&database, 123).await?).await?;
 
     // ACT
     let todo_created =
@@ -34,12 +36,15 @@ async fn model_todo_create() -> Result<(), Box<dyn std::error::Error>> {
 #[tokio::test]
 async fn model_todo_list() -> Result<(), Box<dyn std::error::Error>> {
     // ARRANGE
-    let database = initialize_database().await?;
+    let database = initialize_database(DatabaseMode::Dev).await?;
 
-    let user_context = user_context_from_token(&database, "123").await?;
+    let user_context =
+        user_context_from_token(&database, &new_token(&database, 123).await?).await?;
 
     //ACT
-    let result = ModelAccessController::list(&database, &user_context).await?;
+    let result = ModelAccessController::list(&database, &user_context, &TodoListOptions::default())
+        .await?
+        .items;
 
     //ASSERT
     assert_eq!(2, result.len(), "number of seed todos");
@@ -50,9 +55,9 @@ async fn model_todo_list() -> Result<(), Box<dyn std::error::Error>> {
     assert_eq!(result[0].cid, 123);
     assert_eq!(result[0].title, "todo 101");
     // the other todo
-    assert_eq!(result[0].id, 101);
-    assert_eq!(result[0].cid, 123);
-    assert_eq!(result[0].title, "todo 100");
+    assert_eq!(result[1].id, 100);
+    assert_eq!(result[1].cid, 123);
+    assert_eq!(result[1].title, "todo 100");
 
     Ok(())
 }
@@ -60,9 +65,10 @@ async fn model_todo_list() -> Result<(), Box<dyn std::error::Error>> {
 #[tokio::test]
 async fn model_todo_get_ok() -> Result<(), Box<dyn std::error::Error>> {
     // ARRANGE
-    let database = initialize_database().await?;
+    let database = initialize_database(DatabaseMode::Dev).await?;
 
-    let user_context = user_context_from_token(&database, "123").await?;
+    let user_context =
+        user_context_from_token(&database, &new_token(&database, 123).await?).await?;
 
     // ACT
     let todo = ModelAccessController::get(&database, &user_context, 100).await?;
@@ -77,9 +83,10 @@ async fn model_todo_get_ok() -> Result<(), Box<dyn std::error::Error>> {
 #[tokio::test]
 async fn model_todo_get_wrong_id() -> Result<(), Box<dyn std::error::Error>> {
     // ARRANGE
-    let database = initialize_database().await?;
+    let database = initialize_database(DatabaseMode::Dev).await?;
 
-    let user_context = user_context_from_token(&database, "123").await?;
+    let user_context =
+        user_context_from_token(&database, &new_token(&database, 123).await?).await?;
 
     // ACT
     let result = ModelAccessController::get(&database, &user_context, 999).await;
@@ -92,21 +99,22 @@ async fn model_todo_get_wrong_id() -> Result<(), Box<dyn std::error::Error>> {
             assert_eq!(String::from("999"), id);
         }
         other_error => unreachable!("Wrong error: {other_error:?}"),
-    };
+    }
     Ok(())
 }
 
 #[tokio::test]
 async fn model_todo_update_ok() -> Result<(), Box<dyn std::error::Error>> {
     // ARRANGE
-    let database = initialize_database().await?;
+    let database = initialize_database(DatabaseMode::Dev).await?;
 
     let data_fixture = PartialTodo {
         title: Some(String::from("test - model_todo_create 1")),
         ..PartialTodo::default()
     };
 
-    let user_context = user_context_from_token(&database, "123").await?;
+    let user_context =
+        user_context_from_token(&database, &new_token(&database, 123).await?).await?;
 
     let todo_created =
         ModelAccessController::create(&database, &user_context, data_fixture.clone()).await?;
@@ -126,10 +134,14 @@ async fn model_todo_update_ok() -> Result<(), Box<dyn std::error::Error>> {
         &user_context,
         todo_created.id,
         update_data_fixture.clone(),
+        None,
     )
     .await?;
 
-    let list_result = ModelAccessController::list(&database, &user_context).await?;
+    let list_result =
+        ModelAccessController::list(&database, &user_context, &TodoListOptions::default())
+            .await?
+            .items;
 
     // ASSERT
     assert_eq!(list_result.len(), 3);
@@ -142,13 +154,14 @@ async fn model_todo_update_ok() -> Result<(), Box<dyn std::error::Error>> {
 #[tokio::test]
 async fn model_todo_delete_simple() -> Result<(), Box<dyn std::error::Error>> {
     // ARRANGE
-    let database = initialize_database().await?;
+    let database = initialize_database(DatabaseMode::Dev).await?;
 
-    let utx: UserContext = user_context_from_token(&database, "123").await?;
+    let utx: UserContext =
+        user_context_from_token(&database, &new_token(&database, 123).await?).await?;
 
     // ACT
 
-    let todo = ModelAccessController::delete(&database, &utx, 100).await?;
+    let todo = ModelAccessController::delete(&database, &utx, 100, None).await?;
 
     // ASSERT
 
@@ -156,9 +169,468 @@ async fn model_todo_delete_simple() -> Result<(), Box<dyn std::error::Error>> {
     assert_eq!("todo 100", todo.title);
 
     // ASSERT
-    let todos: Vec<Todo> = ModelAccessController::list(&database, &utx).await?;
+    let todos: Vec<Todo> =
+        ModelAccessController::list(&database, &utx, &TodoListOptions::default())
+            .await?
+            .items;
 
     assert_eq!(todos.len(), 1);
 
     Ok(())
 }
+
+#[tokio::test]
+async fn model_todo_users_isolation() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+
+    let utx_123 = user_context_from_token(&database, &new_token(&database, 123).await?).await?;
+    let utx_124 = user_context_from_token(&database, &new_token(&database, 124).await?).await?;
+
+    let update_data_fixture = PartialTodo {
+        title: Some(String::from("test - model_todo_users_isolation")),
+        ..PartialTodo::default()
+    };
+
+    // ACT & ASSERT - each user only lists its own todos
+    let todos_123 = ModelAccessController::list(&database, &utx_123, &TodoListOptions::default())
+        .await?
+        .items;
+    let todos_124 = ModelAccessController::list(&database, &utx_124, &TodoListOptions::default())
+        .await?
+        .items;
+    assert!(todos_123.iter().all(|todo| todo.cid == 123));
+    assert_eq!(todos_124.len(), 1);
+    assert_eq!(todos_124[0].id, 200);
+
+    // ACT & ASSERT - the todos of user 123 don't exist for user 124
+    let results = [
+        ModelAccessController::get(&database, &utx_124, 100).await,
+        ModelAccessController::update(&database, &utx_124, 100, update_data_fixture, None).await,
+        ModelAccessController::delete(&database, &utx_124, 100, None).await,
+    ];
+    for result in results {
+        match result {
+            Err(model::Error::EntityNotFound(typ, id)) => {
+                assert_eq!("todo", typ);
+                assert_eq!(String::from("100"), id);
+            }
+            other => unreachable!("Should be EntityNotFound: {other:?}"),
+        }
+    }
+
+    // ASSERT - todo 100 is untouched for its owner
+    let todo = ModelAccessController::get(&database, &utx_123, 100).await?;
+    assert_eq!(todo.title, "todo 100");
+
+    // ACT & ASSERT - created todos belong to the user of the context
+    let todo = ModelAccessController::create(&database, &utx_124, PartialTodo::default()).await?;
+    assert_eq!(todo.cid, 124);
+    assert!(ModelAccessController::get(&database, &utx_123, todo.id)
+        .await
+        .is_err());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn model_todo_list_filter_sort() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+    let utx = user_context_from_token(&database, &new_token(&database, 123).await?).await?;
+    for title in ["b 50%", "a 50_", "c other"] {
+        let data = PartialTodo {
+            title: Some(String::from(title)),
+            ..PartialTodo::default()
+        };
+        ModelAccessController::create(&database, &utx, data).await?;
+    }
+
+    // ACT
+    let by_title = ModelAccessController::list(
+        &database,
+        &utx,
+        &TodoListOptions {
+            title: Some(String::from("50")),
+            sort: Some(String::from("title:asc")),
+            ..TodoListOptions::default()
+        },
+    )
+    .await?;
+    let percent_only = ModelAccessController::list(
+        &database,
+        &utx,
+        &TodoListOptions {
+            title: Some(String::from("%")),
+            ..TodoListOptions::default()
+        },
+    )
+    .await?;
+    let closed = ModelAccessController::list(
+        &database,
+        &utx,
+        &TodoListOptions {
+            status: Some(todo::Status::Closed),
+            ..TodoListOptions::default()
+        },
+    )
+    .await?;
+    let created_in_future = ModelAccessController::list(
+        &database,
+        &utx,
+        &TodoListOptions {
+            created_after: Some(chrono::Utc::now() + chrono::Duration::hours(1)),
+            ..TodoListOptions::default()
+        },
+    )
+    .await?;
+
+    // ASSERT
+    let titles: Vec<&str> = by_title.items.iter().map(|t| t.title.as_str()).collect();
+    assert_eq!(titles, ["a 50_", "b 50%"]);
+    assert_eq!(by_title.total, 2);
+    assert_eq!(by_title.next_cursor, None);
+    // the LIKE wildcards are matched as themselves
+    assert_eq!(percent_only.items.len(), 1);
+    assert_eq!(percent_only.items[0].title, "b 50%");
+    assert_eq!(closed.items.len(), 1);
+    assert_eq!(closed.items[0].id, 100);
+    assert_eq!(created_in_future.total, 0);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn model_todo_list_pages() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+    let utx = user_context_from_token(&database, &new_token(&database, 123).await?).await?;
+    for index in 0..3 {
+        let data = PartialTodo {
+            title: Some(format!("same title {}", index % 2)),
+            ..PartialTodo::default()
+        };
+        ModelAccessController::create(&database, &utx, data).await?;
+    }
+
+    // ACT - walk the pages, with ties on the sort key
+    let mut options = TodoListOptions {
+        sort: Some(String::from("title:desc")),
+        limit: Some(2),
+        ..TodoListOptions::default()
+    };
+    let mut ids = Vec::new();
+    let mut pages = 0;
+    loop {
+        let page = ModelAccessController::list(&database, &utx, &options).await?;
+        assert_eq!(page.total, 5, "total over all the pages");
+        ids.extend(page.items.iter().map(|todo| todo.id));
+        pages += 1;
+        match page.next_cursor {
+            Some(cursor) => options.cursor = Some(cursor),
+            None => break,
+        }
+    }
+
+    // ASSERT
+    assert_eq!(pages, 3);
+    let mut unique_ids = ids.clone();
+    unique_ids.sort_unstable();
+    unique_ids.dedup();
+    assert_eq!(unique_ids.len(), 5, "no todo skipped or repeated: {ids:?}");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn model_todo_list_invalid_options() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+    let utx = user_context_from_token(&database, &new_token(&database, 123).await?).await?;
+    let page = ModelAccessController::list(
+        &database,
+        &utx,
+        &TodoListOptions {
+            limit: Some(1),
+            ..TodoListOptions::default()
+        },
+    )
+    .await?;
+
+    // ACT
+    let result = ModelAccessController::list(
+        &database,
+        &utx,
+        &TodoListOptions {
+            sort: Some(String::from("title")),
+            limit: Some(0),
+            // made for the default sort
+            cursor: page.next_cursor,
+            ..TodoListOptions::default()
+        },
+    )
+    .await;
+
+    // ASSERT
+    match result {
+        Err(model::Error::ValidationFailed(errors)) => {
+            let fields: Vec<&str> = errors.iter().map(|error| error.field).collect();
+            assert_eq!(fields, ["limit", "cursor"]);
+        }
+        other => unreachable!("Wrong result: {other:?}"),
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn model_todo_list_forged_cursor() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+    let utx = user_context_from_token(&database, &new_token(&database, 123).await?).await?;
+    let by_ctime = TodoListOptions {
+        sort: Some(String::from("ctime:asc")),
+        limit: Some(1),
+        ..TodoListOptions::default()
+    };
+    let first_page = ModelAccessController::list(&database, &utx, &by_ctime).await?;
+
+    // ACT - a cursor made by the server keeps working
+    let second_page = ModelAccessController::list(
+        &database,
+        &utx,
+        &TodoListOptions {
+            cursor: first_page.next_cursor,
+            ..by_ctime.clone()
+        },
+    )
+    .await?;
+
+    // ASSERT
+    assert_eq!(second_page.items.len(), 1);
+    assert_ne!(second_page.items[0].id, first_page.items[0].id);
+
+    for (sort, key) in [
+        ("id:desc", "x"),
+        ("ctime:asc", "yesterday"),
+        ("status:asc", "pending"),
+        ("priority:asc", "1"),
+    ] {
+        // ACT
+        let cursor = Cursor {
+            sort: String::from(sort),
+            key: String::from(key),
+            id: 1,
+        };
+        let result = ModelAccessController::list(
+            &database,
+            &utx,
+            &TodoListOptions {
+                sort: Some(String::from(sort)),
+                cursor: Some(cursor.encode()),
+                ..TodoListOptions::default()
+            },
+        )
+        .await;
+
+        // ASSERT - not a query error
+        match result {
+            Err(model::Error::ValidationFailed(errors)) => {
+                assert_eq!(errors[0].field, "cursor");
+                assert_eq!(errors[0].message, "is not a valid cursor");
+            }
+            other => unreachable!("Wrong result for {sort}: {other:?}"),
+        }
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn model_todo_patch() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+    let utx = user_context_from_token(&database, &new_token(&database, 123).await?).await?;
+
+    // ACT
+    let status_only = ModelAccessController::patch(
+        &database,
+        &utx,
+        101,
+        TodoPatch::Partial(PartialTodo {
+            status: Some(todo::Status::Closed),
+            ..PartialTodo::default()
+        }),
+        None,
+    )
+    .await?;
+    let merged = ModelAccessController::patch(
+        &database,
+        &utx,
+        101,
+        TodoPatch::Merge(serde_json::json!({ "title": "merged" })),
+        None,
+    )
+    .await?;
+    let json_patched = ModelAccessController::patch(
+        &database,
+        &utx,
+        101,
+        TodoPatch::Json(serde_json::from_value(serde_json::json!([
+            { "op": "test", "path": "/title", "value": "merged" },
+            { "op": "replace", "path": "/status", "value": "Open" }
+        ]))?),
+        None,
+    )
+    .await?;
+    let title_removed = ModelAccessController::patch(
+        &database,
+        &utx,
+        101,
+        TodoPatch::Merge(serde_json::json!({ "title": null })),
+        None,
+    )
+    .await;
+    let failed_test = ModelAccessController::patch(
+        &database,
+        &utx,
+        101,
+        TodoPatch::Json(serde_json::from_value(serde_json::json!([
+            { "op": "test", "path": "/title", "value": "not the title" }
+        ]))?),
+        None,
+    )
+    .await;
+
+    // ASSERT
+    // absent fields are untouched
+    assert_eq!(status_only.title, "todo 101");
+    assert_eq!(status_only.status, todo::Status::Closed);
+    assert_eq!(merged.title, "merged");
+    assert_eq!(merged.status, todo::Status::Closed);
+    assert_eq!(json_patched.title, "merged");
+    assert_eq!(json_patched.status, todo::Status::Open);
+    assert!(matches!(
+        title_removed,
+        Err(model::Error::ValidationFailed(_))
+    ));
+    assert!(matches!(
+        failed_test,
+        Err(model::Error::ValidationFailed(_))
+    ));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn model_todo_version_conflict() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+    let utx = user_context_from_token(&database, &new_token(&database, 123).await?).await?;
+    let todo = ModelAccessController::get(&database, &utx, 101).await?;
+    let data = PartialTodo {
+        title: Some(String::from("first tab")),
+        ..PartialTodo::default()
+    };
+
+    // ACT
+    let updated =
+        ModelAccessController::update(&database, &utx, 101, data.clone(), Some(vec![todo.version]))
+            .await?;
+    // the second tab still has the first version
+    let stale_update =
+        ModelAccessController::update(&database, &utx, 101, data, Some(vec![todo.version])).await;
+    let stale_delete =
+        ModelAccessController::delete(&database, &utx, 101, Some(vec![todo.version])).await;
+
+    // ASSERT
+    assert_eq!(updated.version, todo.version + 1);
+    for result in [stale_update, stale_delete] {
+        match result {
+            Err(model::Error::VersionConflict(typ, id, version)) => {
+                assert_eq!("todo", typ);
+                assert_eq!("101", id);
+                assert_eq!(version, updated.version);
+            }
+            other => unreachable!("Wrong result: {other:?}"),
+        }
+    }
+    ModelAccessController::delete(&database, &utx, 101, Some(vec![updated.version])).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn model_todo_details() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+    let utx = user_context_from_token(&database, &new_token(&database, 123).await?).await?;
+    let due_at = chrono::Utc::now() + chrono::Duration::days(2);
+
+    // ACT
+    let created = ModelAccessController::create(
+        &database,
+        &utx,
+        PartialTodo {
+            title: Some(String::from("with details")),
+            description: Some(Some(String::from("# Markdown"))),
+            due_at: Some(Some(due_at)),
+            priority: Some(todo::Priority::Urgent),
+            ..PartialTodo::default()
+        },
+    )
+    .await?;
+    let closed = ModelAccessController::update(
+        &database,
+        &utx,
+        created.id,
+        PartialTodo {
+            status: Some(todo::Status::Closed),
+            ..PartialTodo::default()
+        },
+        None,
+    )
+    .await?;
+    let still_closed = ModelAccessController::update(
+        &database,
+        &utx,
+        created.id,
+        PartialTodo {
+            // null clears, absent keeps
+            description: Some(None),
+            ..PartialTodo::default()
+        },
+        None,
+    )
+    .await?;
+    let reopened = ModelAccessController::update(
+        &database,
+        &utx,
+        created.id,
+        PartialTodo {
+            status: Some(todo::Status::Open),
+            ..PartialTodo::default()
+        },
+        None,
+    )
+    .await?;
+
+    // ASSERT
+    assert_eq!(created.description.as_deref(), Some("# Markdown"));
+    assert_eq!(
+        created.due_at.map(|due| due.timestamp_micros()),
+        Some(due_at.timestamp_micros())
+    );
+    assert_eq!(created.priority, todo::Priority::Urgent);
+    assert_eq!(created.status, todo::Status::Open);
+    assert_eq!(created.completed_at, None);
+    assert!(closed.completed_at.is_some());
+    assert!(closed.mtime >= created.mtime);
+    assert_eq!(still_closed.completed_at, closed.completed_at);
+    assert_eq!(still_closed.description, None);
+    assert!(still_closed.due_at.is_some());
+    assert_eq!(reopened.completed_at, None);
+    assert_eq!(reopened.priority, todo::Priority::Urgent);
+
+    Ok(())
+}
@@ -0,0 +1,99 @@
+use super::{UserCredentials, UserModelAccessController};
+use crate::{
+    model::{
+        self,
+        db::{initialize_database, DatabaseMode},
+    },
+    security::{self, user_context_from_token, verify_password},
+};
+
+#[tokio::test]
+async fn model_user_create_ok() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+
+    let data_fixture = UserCredentials {
+        username: String::from("test - model_user_create_ok"),
+        password: String::from("test password"),
+    };
+
+    // ACT
+    let user = UserModelAccessController::create(&database, data_fixture.clone()).await?;
+
+    // ASSERT
+    assert!(user.id >= 1000, "ID should be >= 1000");
+    assert_eq!(user.username, data_fixture.username);
+
+    let user_for_auth = UserModelAccessController::get_for_auth(&database, user.id).await?;
+    assert_ne!(
+        user_for_auth.pwd, data_fixture.password,
+        "password must be hashed"
+    );
+    verify_password(&data_fixture.password, &user_for_auth.pwd).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn model_user_get_wrong_id() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+
+    // ACT
+    let result = UserModelAccessController::get(&database, 999).await;
+
+    // ASSERT
+    match result {
+        Ok(_) => unreachable!("Should not succeed"),
+        Err(model::Error::EntityNotFound(typ, id)) => {
+            assert_eq!("user", typ);
+            assert_eq!(String::from("999"), id);
+        }
+        other_error => unreachable!("Wrong error: {other_error:?}"),
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn model_user_login_and_logout() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+
+    // ACT
+    let (user, token) = security::login(&database, "demo1", "welcome").await?;
+    let user_context = user_context_from_token(&database, &token).await?;
+
+    // ASSERT
+    assert_eq!(user.id, 123);
+    assert_eq!(user_context.user_id, 123);
+
+    // ACT - logout invalidates the token
+    security::logout(&database, &user_context).await?;
+    let result = user_context_from_token(&database, &token).await;
+
+    // ASSERT
+    assert!(
+        matches!(result, Err(security::Error::InvalidTokenSignature)),
+        "token should be invalid after logout"
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn model_user_login_wrong_credentials() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+
+    // ACT & ASSERT
+    for (username, password) in [("demo1", "wrong password"), ("unknown", "welcome")] {
+        let result = security::login(&database, username, password).await;
+
+        assert!(
+            matches!(result, Err(security::Error::LoginFail)),
+            "{username} / {password} should fail"
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,24 @@
+use crate::{
+    model::{
+        self,
+        db::PostgresDatabase,
+        todo::{ModelAccessController, PartialTodo, Status},
+    },
+    security::UserContext,
+};
+
+pub async fn create_subtask(
+    database: &PostgresDatabase,
+    utx: &UserContext,
+    parent_id: i64,
+    status: Status,
+) -> Result<i64, model::Error> {
+    let data = PartialTodo {
+        title: Some(format!("subtask of {parent_id}")),
+        status: Some(status),
+        parent_id: Some(Some(parent_id)),
+        ..PartialTodo::default()
+    };
+
+    Ok(ModelAccessController::create(database, utx, data).await?.id)
+}
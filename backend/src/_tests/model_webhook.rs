@@ -0,0 +1,146 @@
+use std::time::Duration;
+
+use serde_json::json;
+
+use super::{
+    new_event, purge_deliveries, record_delivery, PartialWebhook, WebhookModelAccessController,
+    TEST_EVENT_TYPE,
+};
+use crate::{
+    model::{
+        self,
+        db::{initialize_database, DatabaseMode},
+        job::{JobListOptions, JobModelAccessController},
+        todo::{ModelAccessController, PartialTodo},
+    },
+    security::{new_token, user_context_from_token},
+};
+
+#[tokio::test]
+async fn model_webhook_create_and_validate() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+    let utx = user_context_from_token(&database, &new_token(&database, 123).await?).await?;
+    let other = user_context_from_token(&database, &new_token(&database, 124).await?).await?;
+
+    // ACT
+    let data = PartialWebhook {
+        url: Some(String::from("https://example.com/hooks/todo")),
+        project_id: Some(Some(1)),
+        ..PartialWebhook::default()
+    };
+    let webhook = WebhookModelAccessController::create(&database, &utx, data).await?;
+    let data = PartialWebhook {
+        url: Some(String::from("ftp://example.com")),
+        ..PartialWebhook::default()
+    };
+    let invalid_url = WebhookModelAccessController::create(&database, &utx, data).await;
+    let data = PartialWebhook {
+        url: Some(String::from("https://example.com")),
+        project_id: Some(Some(1)),
+        ..PartialWebhook::default()
+    };
+    let other_project = WebhookModelAccessController::create(&database, &other, data).await;
+    let other_list = WebhookModelAccessController::list(&database, &other).await?;
+    let other_delete = WebhookModelAccessController::delete(&database, &other, webhook.id).await;
+
+    // ASSERT
+    assert!(webhook.id >= 1000, "id should be >= 1000");
+    assert_eq!(webhook.cid, 123);
+    assert_eq!(webhook.project_id, Some(1));
+    assert!(webhook.active);
+    assert!(webhook.secret.starts_with("whsec_"));
+    assert_eq!(
+        webhook.secret.len(),
+        "whsec_".len() + 64,
+        "32 bytes, hex encoded"
+    );
+    match invalid_url {
+        Err(model::Error::ValidationFailed(errors)) => assert_eq!(errors[0].field, "url"),
+        other => panic!("expected a url validation error, got {other:?}"),
+    }
+    match other_project {
+        Err(model::Error::ValidationFailed(errors)) => assert_eq!(errors[0].field, "project_id"),
+        other => panic!("expected a project_id validation error, got {other:?}"),
+    }
+    assert!(other_list.is_empty());
+    assert!(matches!(
+        other_delete,
+        Err(model::Error::EntityNotFound("webhook", _))
+    ));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn model_webhook_todo_events_enqueued() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+    let utx = user_context_from_token(&database, &new_token(&database, 123).await?).await?;
+    let data = PartialWebhook {
+        url: Some(String::from("https://example.com/project")),
+        project_id: Some(Some(1)),
+        ..PartialWebhook::default()
+    };
+    let project_webhook = WebhookModelAccessController::create(&database, &utx, data).await?;
+    let data = PartialWebhook {
+        url: Some(String::from("https://example.com/inactive")),
+        active: Some(false),
+        ..PartialWebhook::default()
+    };
+    let inactive_webhook = WebhookModelAccessController::create(&database, &utx, data).await?;
+
+    // ACT
+    let data = PartialTodo {
+        title: Some(String::from("without project")),
+        ..PartialTodo::default()
+    };
+    ModelAccessController::create(&database, &utx, data).await?;
+    let data = PartialTodo {
+        title: Some(String::from("in project 1")),
+        project_id: Some(Some(1)),
+        ..PartialTodo::default()
+    };
+    let todo = ModelAccessController::create(&database, &utx, data).await?;
+    let test_event =
+        WebhookModelAccessController::send_test(&database, &utx, inactive_webhook.id).await?;
+
+    // ASSERT
+    let jobs = JobModelAccessController::list(&database, &utx, JobListOptions::default()).await?;
+    let jobs: Vec<_> = jobs.iter().filter(|job| job.kind == "webhook").collect();
+    assert_eq!(jobs.len(), 2, "the project todo, then the test event");
+    assert_eq!(jobs[1].payload["webhook_id"], project_webhook.id);
+    assert_eq!(jobs[1].payload["event"]["type"], "todo.created");
+    assert_eq!(jobs[1].payload["event"]["data"]["id"], todo.id);
+    assert_eq!(jobs[0].payload["webhook_id"], inactive_webhook.id);
+    assert_eq!(jobs[0].payload["event"]["type"], TEST_EVENT_TYPE);
+    assert_eq!(jobs[0].payload["event"]["id"], test_event.id.to_string());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn model_webhook_deliveries_purged() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+    let utx = user_context_from_token(&database, &new_token(&database, 123).await?).await?;
+    let data = PartialWebhook {
+        url: Some(String::from("https://example.com/hooks/todo")),
+        ..PartialWebhook::default()
+    };
+    let webhook = WebhookModelAccessController::create(&database, &utx, data).await?;
+    let event = new_event(TEST_EVENT_TYPE, json!({}));
+    record_delivery(&database, webhook.id, &event, 1, Some(200), None, 12).await?;
+
+    // ACT
+    let kept = purge_deliveries(&database, Duration::from_hours(1)).await?;
+    let purged = purge_deliveries(&database, Duration::ZERO).await?;
+    let deliveries = WebhookModelAccessController::deliveries(&database, &utx, webhook.id).await?;
+
+    // ASSERT
+    assert_eq!(kept, 0, "the delivery is within the retention time");
+    assert_eq!(purged, 1);
+    assert!(deliveries.is_empty());
+
+    Ok(())
+}
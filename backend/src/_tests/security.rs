@@ -0,0 +1,33 @@
+use super::{login, verify_password, Error, DUMMY_PASSWORD_HASH};
+use crate::model::{initialize_database, DatabaseMode};
+
+#[tokio::test]
+async fn security_dummy_password_hash_verified() {
+    // ACT
+    let result = verify_password("welcome", DUMMY_PASSWORD_HASH).await;
+
+    // ASSERT
+    // a wrong password, not an invalid hash, so the verification costs the same as for a known user
+    assert!(
+        matches!(result, Err(Error::WrongPassword)),
+        "Expected WrongPassword, got {result:?}"
+    );
+}
+
+#[tokio::test]
+async fn security_login_unknown_user() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+
+    // ACT
+    let result = login(&database, "unknown", "welcome").await;
+
+    // ASSERT
+    assert!(
+        matches!(result, Err(Error::LoginFail)),
+        "Expected LoginFail, got {:?}",
+        result.map(|(user, _)| user.id)
+    );
+
+    Ok(())
+}
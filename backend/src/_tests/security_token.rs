@@ -0,0 +1,72 @@
+use std::time::Duration;
+
+use super::Token;
+use crate::security::Error;
+
+const KEY: &[u8] = b"test-secret";
+
+#[test]
+fn security_token_sign_validate_ok() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE
+    let token = Token::sign(123, Duration::from_mins(1), KEY);
+
+    // ACT
+    let parsed = Token::parse(&token)?;
+    parsed.validate(KEY)?;
+
+    // ASSERT
+    assert_eq!(parsed.user_id, 123);
+    assert_eq!(token.split('.').count(), 3, "user_id.expiration.signature");
+
+    Ok(())
+}
+
+#[test]
+fn security_token_malformed() {
+    for token in [
+        "123",
+        "abc.def.ghi",
+        "123.user_info_base64.signature_base64",
+    ] {
+        let result = Token::parse(token);
+
+        assert!(
+            matches!(result, Err(Error::MalformedToken)),
+            "{token} should be malformed"
+        );
+    }
+}
+
+#[test]
+fn security_token_bad_signature() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE
+    let token = Token::sign(123, Duration::from_mins(1), b"another-secret");
+    let own_token = Token::sign(123, Duration::from_mins(1), KEY);
+    // same signature, but for another user
+    let tampered = own_token.replacen("123", "124", 1);
+
+    // ACT & ASSERT
+    for token in [token, tampered] {
+        let result = Token::parse(&token)?.validate(KEY);
+        assert!(
+            matches!(result, Err(Error::InvalidTokenSignature)),
+            "{token} should have a bad signature"
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn security_token_expired() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE
+    let token = Token::sign(123, Duration::ZERO, KEY);
+
+    // ACT
+    let result = Token::parse(&token)?.validate(KEY);
+
+    // ASSERT
+    assert!(matches!(result, Err(Error::ExpiredToken)));
+
+    Ok(())
+}
@@ -0,0 +1,59 @@
+use bytes::Bytes;
+use futures::{stream, StreamExt, TryStreamExt};
+
+use super::{ByteStream, Error, LocalStorage, Storage};
+
+fn content(chunks: &[&'static str]) -> ByteStream {
+    let chunks: Vec<Result<Bytes, Error>> = chunks
+        .iter()
+        .map(|chunk| Ok(Bytes::from_static(chunk.as_bytes())))
+        .collect();
+    stream::iter(chunks).boxed()
+}
+
+fn temp_storage() -> LocalStorage {
+    LocalStorage::new(std::env::temp_dir().join(uuid::Uuid::new_v4().to_string()))
+}
+
+#[tokio::test]
+async fn storage_local_put_get_delete() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE
+    let storage = temp_storage();
+
+    // ACT
+    storage.put("key-1", content(&["hello ", "world"])).await?;
+    let read: Vec<Bytes> = storage.get("key-1").await?.try_collect().await?;
+    storage.delete("key-1").await?;
+    let deleted = storage.get("key-1").await;
+    let deleted_again = storage.delete("key-1").await;
+
+    // ASSERT
+    assert_eq!(read.concat(), b"hello world");
+    assert!(matches!(deleted, Err(Error::NotFound(_))));
+    assert!(deleted_again.is_ok());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn storage_local_failed_put_and_invalid_key() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE
+    let storage = temp_storage();
+    let failing = stream::iter(vec![
+        Ok(Bytes::from_static(b"partial")),
+        Err(Error::TooLarge(7)),
+    ])
+    .boxed();
+
+    // ACT
+    let failed = storage.put("key-2", failing).await;
+    let after_failure = storage.get("key-2").await;
+    let path_key = storage.put("../escape", content(&["x"])).await;
+
+    // ASSERT
+    assert!(matches!(failed, Err(Error::TooLarge(7))));
+    assert!(matches!(after_failure, Err(Error::NotFound(_))));
+    assert!(matches!(path_key, Err(Error::InvalidKey(_))));
+
+    Ok(())
+}
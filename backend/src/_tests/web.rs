@@ -0,0 +1,285 @@
+use std::sync::Arc;
+
+use anyhow::Result as AnyhowResult;
+use serde_json::{from_slice, Value};
+
+use crate::model::{initialize_database, DatabaseMode};
+use crate::security::new_token;
+use crate::web::{filter_utils::HEADER_XAUTH, problem::HEADER_REQUEST_ID};
+
+use super::routes;
+
+const WEB_FOLDER: &str = "../frontend/web-folder";
+
+#[tokio::test]
+async fn web_routes_api_mounted_under_base_path() -> AnyhowResult<()> {
+    // ARRANGE
+    let database = Arc::new(initialize_database(DatabaseMode::Dev).await?);
+    let routes = routes(WEB_FOLDER, "api/v1", Arc::clone(&database));
+
+    // ACT
+    let response = warp::test::request()
+        .method("GET")
+        .header(HEADER_XAUTH, new_token(&database, 123).await?)
+        .path("/api/v1/todos")
+        .reply(&routes)
+        .await;
+
+    // ASSERT
+    assert_eq!(response.status(), 200, "http status");
+    let body: Value = from_slice(response.body())?;
+    assert_eq!(
+        body["data"].as_array().map(Vec::len),
+        Some(2),
+        "number of todos"
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn web_routes_todo_share_mounted() -> AnyhowResult<()> {
+    // ARRANGE
+    let database = Arc::new(initialize_database(DatabaseMode::Dev).await?);
+    let routes = routes(WEB_FOLDER, "api", Arc::clone(&database));
+
+    // ACT - a PUT under /todos/{id}, not swallowed by the todo routes
+    let shared = warp::test::request()
+        .method("PUT")
+        .header(HEADER_XAUTH, new_token(&database, 123).await?)
+        .json(&serde_json::json!({ "permission": "View" }))
+        .path("/api/todos/101/shares/124")
+        .reply(&routes)
+        .await;
+    let seen_by_other = warp::test::request()
+        .method("GET")
+        .header(HEADER_XAUTH, new_token(&database, 124).await?)
+        .path("/api/todos/101")
+        .reply(&routes)
+        .await;
+
+    // ASSERT
+    assert_eq!(shared.status(), 200, "http status");
+    let shared: Value = from_slice(shared.body())?;
+    assert_eq!(shared["data"]["user_id"], 124);
+    assert_eq!(shared["data"]["permission"], "View");
+    assert_eq!(seen_by_other.status(), 200, "http status");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn web_routes_static_index() -> AnyhowResult<()> {
+    // ARRANGE
+    let database = Arc::new(initialize_database(DatabaseMode::Dev).await?);
+    let routes = routes(WEB_FOLDER, "api/v1", database);
+
+    // ACT
+    let response = warp::test::request()
+        .method("GET")
+        .path("/")
+        .reply(&routes)
+        .await;
+
+    // ASSERT
+    assert_eq!(response.status(), 200, "http status");
+    assert!(std::str::from_utf8(response.body())?.contains("<html"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn web_routes_api_not_found_not_swallowed() -> AnyhowResult<()> {
+    // ARRANGE
+    let database = Arc::new(initialize_database(DatabaseMode::Dev).await?);
+    let routes = routes(WEB_FOLDER, "api/v1", database);
+
+    // ACT
+    let response = warp::test::request()
+        .method("GET")
+        .path("/api/v1/index.html")
+        .reply(&routes)
+        .await;
+
+    // ASSERT
+    assert_eq!(response.status(), 404, "http status");
+    // the rejection went through handle_rejection, and not the static site
+    let _body: Value = from_slice(response.body())?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn web_routes_api_rejection_handled() -> AnyhowResult<()> {
+    // ARRANGE
+    let database = Arc::new(initialize_database(DatabaseMode::Dev).await?);
+    let routes = routes(WEB_FOLDER, "api", database);
+
+    // ACT
+    let response = warp::test::request()
+        .method("GET")
+        .path("/api/todos")
+        .reply(&routes)
+        .await;
+
+    // ASSERT
+    assert_eq!(response.status(), 401, "http status");
+    let body: Value = from_slice(response.body())?;
+    assert!(body.is_object());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn web_routes_error_status() -> AnyhowResult<()> {
+    // ARRANGE
+    let database = Arc::new(initialize_database(DatabaseMode::Dev).await?);
+    let routes = routes(WEB_FOLDER, "api", Arc::clone(&database));
+    let token = new_token(&database, 123).await?;
+
+    // (method, path, token, body, expected status, expected code)
+    let cases = [
+        (
+            "GET",
+            "/api/todos/999",
+            token.as_str(),
+            None,
+            404,
+            "ENTITY_NOT_FOUND",
+        ),
+        // todo of another user
+        (
+            "GET",
+            "/api/todos/200",
+            token.as_str(),
+            None,
+            404,
+            "ENTITY_NOT_FOUND",
+        ),
+        (
+            "GET",
+            "/api/todos",
+            "123.bad.token",
+            None,
+            401,
+            "TOKEN_MALFORMED",
+        ),
+        (
+            "PUT",
+            "/api/todos/100",
+            token.as_str(),
+            None,
+            405,
+            "METHOD_NOT_ALLOWED",
+        ),
+        (
+            "POST",
+            "/api/todos",
+            token.as_str(),
+            Some(r#"{"title": 42}"#),
+            422,
+            "INVALID_BODY",
+        ),
+        (
+            "POST",
+            "/api/todos",
+            token.as_str(),
+            Some(r#"{"title": ""}"#),
+            422,
+            "VALIDATION_FAILED",
+        ),
+        (
+            "GET",
+            "/not-a-file.html",
+            token.as_str(),
+            None,
+            404,
+            "NOT_FOUND",
+        ),
+    ];
+
+    for (method, path, token, body, expected_status, expected_code) in cases {
+        // ACT
+        let request = warp::test::request()
+            .method(method)
+            .header(HEADER_XAUTH, token)
+            .path(path);
+        let request = match body {
+            Some(body) => request
+                .header("content-type", "application/json")
+                .body(body),
+            None => request,
+        };
+        let response = request.reply(&routes).await;
+
+        // ASSERT
+        assert_eq!(response.status(), expected_status, "{method} {path}");
+        let body: Value = from_slice(response.body())?;
+        assert_eq!(body["code"], expected_code, "{method} {path}");
+        assert_eq!(body["status"], expected_status, "{method} {path}");
+        assert!(body["detail"].is_string(), "{method} {path}");
+        // the client token is never echoed back
+        assert!(
+            !body["detail"].as_str().unwrap_or_default().contains(token),
+            "{method} {path}"
+        );
+        assert!(body["requestId"].is_string(), "{method} {path}");
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn web_routes_problem_details() -> AnyhowResult<()> {
+    // ARRANGE
+    let database = Arc::new(initialize_database(DatabaseMode::Dev).await?);
+    let routes = routes(WEB_FOLDER, "api", Arc::clone(&database));
+    let long_title = "x".repeat(64);
+
+    // ACT
+    let response = warp::test::request()
+        .method("POST")
+        .header(HEADER_XAUTH, new_token(&database, 123).await?)
+        .header(HEADER_REQUEST_ID, "client-request-1")
+        .path("/api/todos")
+        .json(&serde_json::json!({ "title": long_title }))
+        .reply(&routes)
+        .await;
+
+    // ASSERT
+    assert_eq!(response.status(), 422, "http status");
+    assert_eq!(
+        response.headers()["content-type"],
+        "application/problem+json"
+    );
+    assert_eq!(response.headers()[HEADER_REQUEST_ID], "client-request-1");
+    let body: Value = from_slice(response.body())?;
+    assert_eq!(body["type"], "/problems/validation-failed");
+    assert_eq!(body["title"], "Unprocessable Entity");
+    assert_eq!(body["requestId"], "client-request-1");
+    assert_eq!(body["errors"][0]["field"], "title");
+    assert!(body["errors"][0]["message"].is_string());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn web_routes_request_id_generated() -> AnyhowResult<()> {
+    // ARRANGE
+    let database = Arc::new(initialize_database(DatabaseMode::Dev).await?);
+    let routes = routes(WEB_FOLDER, "api", Arc::clone(&database));
+
+    // ACT
+    let response = warp::test::request()
+        .method("GET")
+        .header(HEADER_XAUTH, new_token(&database, 123).await?)
+        .path("/api/todos")
+        .reply(&routes)
+        .await;
+
+    // ASSERT
+    assert_eq!(response.status(), 200, "http status");
+    assert_eq!(response.headers()[HEADER_REQUEST_ID].len(), 36, "uuid");
+
+    Ok(())
+}
@@ -0,0 +1,46 @@
+use std::sync::Arc;
+
+use anyhow::Result as AnyhowResult;
+use serde_json::{from_slice, Value};
+use warp::Filter;
+
+use crate::model::{initialize_database, DatabaseMode};
+use crate::security::new_token;
+use crate::web::{filter_utils::HEADER_XAUTH, handle_rejection};
+
+use super::rest_filters;
+
+#[tokio::test]
+async fn web_admin_job_list() -> AnyhowResult<()> {
+    // ARRANGE
+    let database = Arc::new(initialize_database(DatabaseMode::Dev).await?);
+    let admin_apis = rest_filters("api", Arc::clone(&database)).recover(handle_rejection);
+    sqlx::query("INSERT INTO job (kind, max_attempts, status) VALUES ('reminder', 5, 'dead')")
+        .execute(database.as_ref())
+        .await?;
+    let admin_token = new_token(&database, 123).await?;
+    let user_token = new_token(&database, 124).await?;
+
+    // ACT
+    let listed = warp::test::request()
+        .method("GET")
+        .header(HEADER_XAUTH, &admin_token)
+        .path("/api/admin/jobs?status=Dead")
+        .reply(&admin_apis)
+        .await;
+    let by_user = warp::test::request()
+        .method("GET")
+        .header(HEADER_XAUTH, &user_token)
+        .path("/api/admin/jobs")
+        .reply(&admin_apis)
+        .await;
+
+    // ASSERT
+    assert_eq!(listed.status(), 200, "http status");
+    let listed: Value = from_slice(listed.body())?;
+    assert_eq!(listed["data"][0]["kind"], "reminder");
+    assert_eq!(listed["data"][0]["status"], "Dead");
+    assert_eq!(by_user.status(), 403, "http status");
+
+    Ok(())
+}
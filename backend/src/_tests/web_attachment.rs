@@ -0,0 +1,78 @@
+use std::sync::Arc;
+
+use anyhow::{Context, Result as AnyhowResult};
+use serde_json::{from_slice, Value};
+use warp::Filter;
+
+use crate::model::{initialize_database, DatabaseMode};
+use crate::security::new_token;
+use crate::web::{filter_utils::HEADER_XAUTH, handle_rejection};
+
+use super::rest_filters;
+
+const BOUNDARY: &str = "test-boundary";
+
+fn multipart_body(field: &str, filename: &str, content: &str) -> String {
+    format!(
+        "--{BOUNDARY}\r\n\
+         Content-Disposition: form-data; name=\"{field}\"; filename=\"{filename}\"\r\n\
+         Content-Type: text/plain\r\n\r\n\
+         {content}\r\n\
+         --{BOUNDARY}--\r\n"
+    )
+}
+
+#[tokio::test]
+async fn web_attachment_upload_download() -> AnyhowResult<()> {
+    // ARRANGE
+    let database = Arc::new(initialize_database(DatabaseMode::Dev).await?);
+    let attachment_apis = rest_filters("api", Arc::clone(&database)).recover(handle_rejection);
+    let token = new_token(&database, 123).await?;
+
+    // ACT
+    let uploaded = warp::test::request()
+        .method("POST")
+        .header(HEADER_XAUTH, &token)
+        .header(
+            "content-type",
+            format!("multipart/form-data; boundary={BOUNDARY}"),
+        )
+        .body(multipart_body("file", "rapport été.txt", "some content"))
+        .path("/api/todos/101/attachments")
+        .reply(&attachment_apis)
+        .await;
+    let uploaded: Value = from_slice(uploaded.body())?;
+    let id = uploaded["data"]["id"].as_i64().context("id")?;
+    let downloaded = warp::test::request()
+        .method("GET")
+        .header(HEADER_XAUTH, &token)
+        .path(&format!("/api/todos/101/attachments/{id}"))
+        .reply(&attachment_apis)
+        .await;
+    let without_file = warp::test::request()
+        .method("POST")
+        .header(HEADER_XAUTH, &token)
+        .header(
+            "content-type",
+            format!("multipart/form-data; boundary={BOUNDARY}"),
+        )
+        .body(multipart_body("other", "a.txt", "x"))
+        .path("/api/todos/101/attachments")
+        .reply(&attachment_apis)
+        .await;
+
+    // ASSERT
+    assert_eq!(uploaded["data"]["filename"], "rapport été.txt");
+    assert_eq!(uploaded["data"]["size"], 12);
+    assert!(uploaded["data"].get("storage_key").is_none());
+    assert_eq!(downloaded.status(), 200, "http status");
+    assert_eq!(downloaded.headers()["content-type"], "text/plain");
+    assert_eq!(
+        downloaded.headers()["content-disposition"],
+        "attachment; filename=\"rapport _t_.txt\"; filename*=UTF-8''rapport%20%C3%A9t%C3%A9.txt"
+    );
+    assert_eq!(downloaded.body().as_ref(), b"some content");
+    assert_eq!(without_file.status(), 422, "http status");
+
+    Ok(())
+}
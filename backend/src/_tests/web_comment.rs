@@ -0,0 +1,69 @@
+use std::sync::Arc;
+
+use anyhow::{Context, Result as AnyhowResult};
+use serde_json::{from_slice, json, Value};
+use warp::Filter;
+
+use crate::model::{initialize_database, DatabaseMode};
+use crate::security::new_token;
+use crate::web::{filter_utils::HEADER_XAUTH, handle_rejection};
+
+use super::rest_filters;
+
+#[tokio::test]
+async fn web_comment_crud() -> AnyhowResult<()> {
+    // ARRANGE
+    let database = Arc::new(initialize_database(DatabaseMode::Dev).await?);
+    let comment_apis = rest_filters("api", Arc::clone(&database)).recover(handle_rejection);
+    let token = new_token(&database, 123).await?;
+
+    // ACT
+    let created = warp::test::request()
+        .method("POST")
+        .header(HEADER_XAUTH, &token)
+        .json(&json!({ "body": "looks good" }))
+        .path("/api/todos/101/comments")
+        .reply(&comment_apis)
+        .await;
+    let created: Value = from_slice(created.body())?;
+    let id = created["data"]["id"].as_i64().context("id")?;
+    let updated = warp::test::request()
+        .method("PATCH")
+        .header(HEADER_XAUTH, &token)
+        .json(&json!({ "body": "looks great" }))
+        .path(&format!("/api/todos/101/comments/{id}"))
+        .reply(&comment_apis)
+        .await;
+    let deleted = warp::test::request()
+        .method("DELETE")
+        .header(HEADER_XAUTH, &token)
+        .path(&format!("/api/todos/101/comments/{id}"))
+        .reply(&comment_apis)
+        .await;
+    let listed = warp::test::request()
+        .method("GET")
+        .header(HEADER_XAUTH, &token)
+        .path("/api/todos/101/comments")
+        .reply(&comment_apis)
+        .await;
+    let other_user = warp::test::request()
+        .method("GET")
+        .header(HEADER_XAUTH, new_token(&database, 124).await?)
+        .path("/api/todos/101/comments")
+        .reply(&comment_apis)
+        .await;
+
+    // ASSERT
+    assert_eq!(created["data"]["cid"], 123);
+    assert_eq!(created["data"]["body"], "looks good");
+    assert_eq!(updated.status(), 200, "http status");
+    let updated: Value = from_slice(updated.body())?;
+    assert_eq!(updated["data"]["body"], "looks great");
+    assert!(updated["data"]["edited_at"].is_string());
+    assert_eq!(deleted.status(), 200, "http status");
+    let listed: Value = from_slice(listed.body())?;
+    assert_eq!(listed["data"].as_array().map(Vec::len), Some(2));
+    assert_eq!(other_user.status(), 404, "http status");
+
+    Ok(())
+}
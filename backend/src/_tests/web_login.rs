@@ -0,0 +1,88 @@
+use std::str::from_utf8;
+use std::sync::Arc;
+
+use anyhow::{Context, Result as AnyhowResult};
+use serde_json::{from_str, json, Value};
+use warp::Filter;
+
+use crate::model::{initialize_database, DatabaseMode};
+use crate::web::{filter_utils::HEADER_XAUTH, handle_rejection, todo};
+
+use super::rest_filters;
+
+#[tokio::test]
+async fn web_login_then_logout() -> AnyhowResult<()> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+    let database = Arc::new(database);
+
+    let apis = rest_filters("api", Arc::clone(&database))
+        .or(todo::rest_filters("api", Arc::clone(&database)))
+        .recover(handle_rejection);
+
+    // ACT - login
+    let response = warp::test::request()
+        .method("POST")
+        .path("/api/login")
+        .json(&json!({"username": "demo1", "password": "welcome"}))
+        .reply(&apis)
+        .await;
+
+    // ASSERT
+    assert_eq!(response.status(), 200, "http status");
+    let body: Value = from_str(from_utf8(response.body())?)?;
+    assert_eq!(body["data"]["user"]["id"], 123);
+    let token = body["data"]["token"]
+        .as_str()
+        .context("token should be a string")?
+        .to_string();
+
+    // the token works on the todo apis
+    let response = warp::test::request()
+        .method("GET")
+        .header(HEADER_XAUTH, &token)
+        .path("/api/todos")
+        .reply(&apis)
+        .await;
+    assert_eq!(response.status(), 200, "http status");
+
+    // ACT - logout
+    let response = warp::test::request()
+        .method("POST")
+        .header(HEADER_XAUTH, &token)
+        .path("/api/logout")
+        .reply(&apis)
+        .await;
+    assert_eq!(response.status(), 200, "http status");
+
+    // ASSERT - the token is no longer accepted
+    let response = warp::test::request()
+        .method("GET")
+        .header(HEADER_XAUTH, &token)
+        .path("/api/todos")
+        .reply(&apis)
+        .await;
+    assert_ne!(response.status(), 200, "http status");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn web_login_wrong_password() -> AnyhowResult<()> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+    let apis = rest_filters("api", Arc::new(database)).recover(handle_rejection);
+
+    // ACT
+    let response = warp::test::request()
+        .method("POST")
+        .path("/api/login")
+        .json(&json!({"username": "demo1", "password": "wrong password"}))
+        .reply(&apis)
+        .await;
+
+    // ASSERT
+    assert_ne!(response.status(), 200, "http status");
+
+    Ok(())
+}
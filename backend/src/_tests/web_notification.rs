@@ -0,0 +1,51 @@
+use std::sync::Arc;
+
+use anyhow::Result as AnyhowResult;
+use serde_json::{from_slice, json, Value};
+use warp::Filter;
+
+use crate::model::{initialize_database, DatabaseMode};
+use crate::security::new_token;
+use crate::web::{filter_utils::HEADER_XAUTH, handle_rejection};
+
+use super::rest_filters;
+
+#[tokio::test]
+async fn web_notification_preferences() -> AnyhowResult<()> {
+    // ARRANGE
+    let database = Arc::new(initialize_database(DatabaseMode::Dev).await?);
+    let notification_apis = rest_filters("api", Arc::clone(&database)).recover(handle_rejection);
+    let token = new_token(&database, 124).await?;
+
+    // ACT
+    let updated = warp::test::request()
+        .method("PATCH")
+        .header(HEADER_XAUTH, &token)
+        .json(&json!({ "email": "other@example.com", "due": false }))
+        .path("/api/notifications/preferences")
+        .reply(&notification_apis)
+        .await;
+    let fetched = warp::test::request()
+        .method("GET")
+        .header(HEADER_XAUTH, &token)
+        .path("/api/notifications/preferences")
+        .reply(&notification_apis)
+        .await;
+    let invalid = warp::test::request()
+        .method("PATCH")
+        .header(HEADER_XAUTH, &token)
+        .json(&json!({ "email": "nope" }))
+        .path("/api/notifications/preferences")
+        .reply(&notification_apis)
+        .await;
+
+    // ASSERT
+    assert_eq!(updated.status(), 200, "http status");
+    let fetched: Value = from_slice(fetched.body())?;
+    assert_eq!(fetched["data"]["email"], "other@example.com");
+    assert_eq!(fetched["data"]["due"], false);
+    assert_eq!(fetched["data"]["assigned"], true);
+    assert_eq!(invalid.status(), 422, "http status");
+
+    Ok(())
+}
@@ -0,0 +1,64 @@
+use std::sync::Arc;
+
+use anyhow::{Context, Result as AnyhowResult};
+use serde_json::{from_slice, json, Value};
+use warp::Filter;
+
+use crate::model::{initialize_database, DatabaseMode};
+use crate::security::new_token;
+use crate::web::{filter_utils::HEADER_XAUTH, handle_rejection};
+
+use super::rest_filters;
+
+#[tokio::test]
+async fn web_project_todos() -> AnyhowResult<()> {
+    // ARRANGE
+    let database = Arc::new(initialize_database(DatabaseMode::Dev).await?);
+    let project_apis = rest_filters("api", Arc::clone(&database)).recover(handle_rejection);
+    let token = new_token(&database, 123).await?;
+
+    // ACT
+    let created = warp::test::request()
+        .method("POST")
+        .header(HEADER_XAUTH, &token)
+        .json(&json!({ "title": "in project 1" }))
+        .path("/api/projects/1/todos")
+        .reply(&project_apis)
+        .await;
+    let archived = warp::test::request()
+        .method("PATCH")
+        .header(HEADER_XAUTH, &token)
+        .json(&json!({ "archived": true }))
+        .path("/api/projects/1")
+        .reply(&project_apis)
+        .await;
+    let listed = warp::test::request()
+        .method("GET")
+        .header(HEADER_XAUTH, &token)
+        .path("/api/projects/1/todos?sort=id:asc")
+        .reply(&project_apis)
+        .await;
+    let other_user = warp::test::request()
+        .method("GET")
+        .header(HEADER_XAUTH, new_token(&database, 124).await?)
+        .path("/api/projects/1/todos")
+        .reply(&project_apis)
+        .await;
+
+    // ASSERT
+    assert_eq!(created.status(), 200, "http status");
+    let created: Value = from_slice(created.body())?;
+    let created_id = created["data"]["id"].as_i64().context("id")?;
+    assert_eq!(created["data"]["project_id"], 1);
+    assert_eq!(archived.status(), 200, "http status");
+    let archived: Value = from_slice(archived.body())?;
+    assert_eq!(archived["data"]["archived"], true);
+    let listed: Value = from_slice(listed.body())?;
+    assert_eq!(listed["total"], 2);
+    assert_eq!(listed["data"][0]["id"], 101);
+    assert_eq!(listed["data"][1]["id"], created_id);
+    assert_eq!(listed["data"][1]["archived"], true);
+    assert_eq!(other_user.status(), 404, "http status");
+
+    Ok(())
+}
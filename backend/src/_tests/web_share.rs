@@ -0,0 +1,59 @@
+use std::sync::Arc;
+
+use anyhow::Result as AnyhowResult;
+use serde_json::{from_slice, json, Value};
+use warp::Filter;
+
+use crate::model::{initialize_database, DatabaseMode};
+use crate::security::new_token;
+use crate::web::{filter_utils::HEADER_XAUTH, handle_rejection};
+
+use super::rest_filters;
+
+#[tokio::test]
+async fn web_share_set_list_delete() -> AnyhowResult<()> {
+    // ARRANGE
+    let database = Arc::new(initialize_database(DatabaseMode::Dev).await?);
+    let share_apis = rest_filters("api", Arc::clone(&database)).recover(handle_rejection);
+    let owner_token = new_token(&database, 123).await?;
+    let other_token = new_token(&database, 124).await?;
+
+    // ACT
+    let shared = warp::test::request()
+        .method("PUT")
+        .header(HEADER_XAUTH, &owner_token)
+        .json(&json!({ "permission": "View" }))
+        .path("/api/todos/101/shares/124")
+        .reply(&share_apis)
+        .await;
+    let listed = warp::test::request()
+        .method("GET")
+        .header(HEADER_XAUTH, &other_token)
+        .path("/api/todos/101/shares")
+        .reply(&share_apis)
+        .await;
+    let unshare_by_viewer = warp::test::request()
+        .method("DELETE")
+        .header(HEADER_XAUTH, &other_token)
+        .path("/api/todos/101/shares/124")
+        .reply(&share_apis)
+        .await;
+    let unshared = warp::test::request()
+        .method("DELETE")
+        .header(HEADER_XAUTH, &owner_token)
+        .path("/api/todos/101/shares/124")
+        .reply(&share_apis)
+        .await;
+
+    // ASSERT
+    assert_eq!(shared.status(), 200, "http status");
+    let shared: Value = from_slice(shared.body())?;
+    assert_eq!(shared["data"]["user_id"], 124);
+    assert_eq!(shared["data"]["permission"], "View");
+    let listed: Value = from_slice(listed.body())?;
+    assert_eq!(listed["data"][0]["user_id"], 124);
+    assert_eq!(unshare_by_viewer.status(), 403, "http status");
+    assert_eq!(unshared.status(), 200, "http status");
+
+    Ok(())
+}
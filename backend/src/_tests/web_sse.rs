@@ -0,0 +1,133 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use anyhow::{anyhow, Result as AnyhowResult};
+use serde_json::{from_str, Value};
+use tokio::time::{sleep, timeout};
+use warp::Filter;
+
+use crate::model::{
+    initialize_database, ChangeLog, DatabaseMode, EventHub, ModelAccessController, PartialTodo,
+};
+use crate::security::{new_token, user_context_from_token};
+use crate::web::{filter_utils::HEADER_XAUTH, handle_rejection};
+
+use super::rest_filters;
+
+#[tokio::test]
+async fn web_sse_todo_events_resume() -> AnyhowResult<()> {
+    // ARRANGE
+    let database = Arc::new(initialize_database(DatabaseMode::Dev).await?);
+    let hub = Arc::new(EventHub::new(database.as_ref().clone()));
+    let sse_apis = rest_filters("api", Arc::clone(&database), hub).recover(handle_rejection);
+    let (address, server) = warp::serve(sse_apis).bind_ephemeral(([127, 0, 0, 1], 0));
+    tokio::spawn(server);
+    let token = new_token(&database, 123).await?;
+    let utx = user_context_from_token(&database, &token).await?;
+    let other_utx = user_context_from_token(&database, &new_token(&database, 124).await?).await?;
+    let data = PartialTodo {
+        title: Some(String::from("seen")),
+        ..PartialTodo::default()
+    };
+    ModelAccessController::create(&database, &utx, data).await?;
+    let last_event_id = ChangeLog::last_position(&database).await?.id;
+    let data = PartialTodo {
+        title: Some(String::from("todo 200 of demo2 only")),
+        ..PartialTodo::default()
+    };
+    ModelAccessController::update(&database, &other_utx, 200, data, None).await?;
+    let data = PartialTodo {
+        title: Some(String::from("missed")),
+        ..PartialTodo::default()
+    };
+    let missed = ModelAccessController::create(&database, &utx, data).await?;
+
+    // ACT
+    let mut response = reqwest::Client::new()
+        .get(format!("http://{address}/api/todos/events"))
+        .header(HEADER_XAUTH, &token)
+        .header("Last-Event-ID", last_event_id)
+        .send()
+        .await?;
+    let replayed = timeout(Duration::from_secs(5), next_event(&mut response)).await??;
+    ModelAccessController::delete(&database, &utx, missed.id, None).await?;
+    let live = timeout(Duration::from_secs(5), next_event(&mut response)).await??;
+    let unauthenticated = reqwest::get(format!("http://{address}/api/todos/events")).await?;
+
+    // ASSERT
+    assert_eq!(
+        response.headers()["content-type"],
+        "text/event-stream",
+        "content type"
+    );
+    assert_eq!(replayed["event"], "todo.created", "not the todo of demo2");
+    let replayed_data: Value = from_str(&replayed["data"])?;
+    assert_eq!(replayed_data["id"], missed.id);
+    assert_eq!(replayed_data["title"], "missed");
+    assert_eq!(live["event"], "todo.deleted");
+    assert!(live["id"].parse::<i64>()? > replayed["id"].parse::<i64>()?);
+    assert_eq!(unauthenticated.status(), 401, "http status");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn web_sse_todo_events_behind_older_transaction() -> AnyhowResult<()> {
+    // ARRANGE
+    let database = Arc::new(initialize_database(DatabaseMode::Dev).await?);
+    let hub = Arc::new(EventHub::new(database.as_ref().clone()));
+    let sse_apis = rest_filters("api", Arc::clone(&database), hub).recover(handle_rejection);
+    let (address, server) = warp::serve(sse_apis).bind_ephemeral(([127, 0, 0, 1], 0));
+    tokio::spawn(server);
+    let token = new_token(&database, 123).await?;
+    let utx = user_context_from_token(&database, &token).await?;
+    let mut response = reqwest::Client::new()
+        .get(format!("http://{address}/api/todos/events"))
+        .header(HEADER_XAUTH, &token)
+        .send()
+        .await?;
+
+    // ACT - a change notified while an older transaction runs, which ends without any change
+    let mut older = database.begin().await?;
+    sqlx::query("SELECT pg_current_xact_id()")
+        .execute(&mut *older)
+        .await?;
+    let data = PartialTodo {
+        title: Some(String::from("behind")),
+        ..PartialTodo::default()
+    };
+    let todo = ModelAccessController::create(&database, &utx, data).await?;
+    sleep(Duration::from_millis(200)).await;
+    older.rollback().await?;
+    let event = timeout(Duration::from_secs(5), next_event(&mut response)).await??;
+
+    // ASSERT
+    assert_eq!(event["event"], "todo.created");
+    let data: Value = from_str(&event["data"])?;
+    assert_eq!(data["id"], todo.id);
+
+    Ok(())
+}
+
+// The fields of the next event, the keep-alive comments are skipped
+async fn next_event(response: &mut reqwest::Response) -> AnyhowResult<HashMap<String, String>> {
+    let mut buffer = String::new();
+    loop {
+        while let Some(end) = buffer.find("\n\n") {
+            let block: String = buffer.drain(..end + 2).collect();
+            let fields: HashMap<String, String> = block
+                .lines()
+                .filter(|line| !line.starts_with(':'))
+                .filter_map(|line| line.split_once(':'))
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect();
+            if !fields.is_empty() {
+                return Ok(fields);
+            }
+        }
+        let chunk = response
+            .chunk()
+            .await?
+            .ok_or_else(|| anyhow!("the event stream ended"))?;
+        buffer.push_str(std::str::from_utf8(&chunk)?);
+    }
+}
@@ -0,0 +1,36 @@
+use std::sync::Arc;
+
+use anyhow::Result as AnyhowResult;
+use serde_json::{from_slice, Value};
+use warp::Filter;
+
+use crate::model::{initialize_database, DatabaseMode};
+use crate::security::new_token;
+use crate::web::{filter_utils::HEADER_XAUTH, handle_rejection};
+
+use super::rest_filters;
+
+#[tokio::test]
+async fn web_tag_list() -> AnyhowResult<()> {
+    // ARRANGE
+    let database = Arc::new(initialize_database(DatabaseMode::Dev).await?);
+    let tag_apis = rest_filters("api", Arc::clone(&database)).recover(handle_rejection);
+
+    // ACT
+    let response = warp::test::request()
+        .method("GET")
+        .header(HEADER_XAUTH, new_token(&database, 124).await?)
+        .path("/api/tags")
+        .reply(&tag_apis)
+        .await;
+
+    // ASSERT
+    assert_eq!(response.status(), 200, "http status");
+    let body: Value = from_slice(response.body())?;
+    assert_eq!(
+        body["data"],
+        serde_json::json!([{ "name": "work", "count": 1 }])
+    );
+
+    Ok(())
+}
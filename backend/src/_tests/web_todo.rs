@@ -1,24 +1,27 @@
-use crate::web::{handle_rejection, HEADER_XAUTH};
+use crate::web::{
+    filter_utils::{HEADER_XAUTH, JSON_BODY_MAX_SIZE},
+    handle_rejection,
+};
 use std::sync::Arc;
 use warp::hyper;
 use warp::hyper::body;
-use warp::reply;
 // use crate::security::user_from_token;
 // use crate::web::handle_rejection;
 use anyhow::{Context, Result as AnyhowResult};
 use serde::Deserialize;
-use serde_json::{from_str, from_value, json, Value};
+use serde_json::{from_slice, from_str, from_value, Value};
 use std::str::from_utf8;
 
 use warp::Filter;
 
-use crate::model::{initialize_database, ModelAccessController, Status, Todo};
+use crate::model::{initialize_database, DatabaseMode, Status, Todo};
+use crate::security::new_token;
 
 use super::rest_filters;
 #[tokio::test]
 async fn web_todo_list() -> AnyhowResult<()> {
     // ARRANGE
-    let database = initialize_database().await?;
+    let database = initialize_database(DatabaseMode::Dev).await?;
     let database = Arc::new(database);
 
     let todo_apis = rest_filters("api", Arc::clone(&database)).recover(handle_rejection);
@@ -27,8 +30,8 @@ async fn web_todo_list() -> AnyhowResult<()> {
 
     let response = warp::test::request()
         .method("GET")
-        .header(HEADER_XAUTH, "123.user_info_base64.signature_base64")
-        .path("api/todos")
+        .header(HEADER_XAUTH, new_token(&database, 123).await?)
+        .path("/api/todos")
         .reply(&todo_apis)
         .await;
 
@@ -37,20 +40,406 @@ async fn web_todo_list() -> AnyhowResult<()> {
     assert_eq!(response.status(), 200, "https status");
 
     //extract the response data
-    let todos: Vec<Todo> = extract_body_data(response)?;
+    let todos: Vec<Todo> = extract_body_data(&response)?;
 
     assert_eq!(todos.len(), 2, "number of todos");
     assert_eq!(todos[0].id, 101);
-    assert_eq!(todos[0].title, "todos 101");
+    assert_eq!(todos[0].title, "todo 101");
     assert_eq!(todos[0].status, Status::Open);
 
     Ok(())
 }
 
+#[tokio::test]
+async fn web_todo_list_page() -> AnyhowResult<()> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+    let database = Arc::new(database);
+
+    let todo_apis = rest_filters("api", Arc::clone(&database)).recover(handle_rejection);
+    let token = new_token(&database, 123).await?;
+
+    // ACT
+    let first = warp::test::request()
+        .method("GET")
+        .header(HEADER_XAUTH, &token)
+        .path("/api/todos?sort=id:asc&limit=1")
+        .reply(&todo_apis)
+        .await;
+    let first_body: Value = from_slice(first.body())?;
+    let cursor = first_body["next_cursor"].as_str().context("next_cursor")?;
+    let second = warp::test::request()
+        .method("GET")
+        .header(HEADER_XAUTH, &token)
+        .path(&format!("/api/todos?sort=id:asc&limit=1&cursor={cursor}"))
+        .reply(&todo_apis)
+        .await;
+    let wrong_query = warp::test::request()
+        .method("GET")
+        .header(HEADER_XAUTH, &token)
+        .path("/api/todos?order=title")
+        .reply(&todo_apis)
+        .await;
+    let mine = warp::test::request()
+        .method("GET")
+        .header(HEADER_XAUTH, &token)
+        .path("/api/todos?scope=Mine")
+        .reply(&todo_apis)
+        .await;
+    let lowercase_scope = warp::test::request()
+        .method("GET")
+        .header(HEADER_XAUTH, &token)
+        .path("/api/todos?scope=mine")
+        .reply(&todo_apis)
+        .await;
+
+    // ASSERT
+    assert_eq!(first.status(), 200, "http status");
+    assert_eq!(first_body["total"], 2);
+    assert_eq!(first_body["data"][0]["id"], 100);
+    let second_body: Value = from_slice(second.body())?;
+    assert_eq!(second_body["data"][0]["id"], 101);
+    assert!(second_body["next_cursor"].is_null(), "last page");
+    assert_eq!(wrong_query.status(), 400, "unknown query parameter");
+    let mine_body: Value = from_slice(mine.body())?;
+    assert_eq!(mine_body["total"], 2);
+    assert_eq!(lowercase_scope.status(), 400, "capitalized scope");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn web_todo_patch_content_types() -> AnyhowResult<()> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+    let database = Arc::new(database);
+
+    let todo_apis = rest_filters("api", Arc::clone(&database)).recover(handle_rejection);
+    let token = new_token(&database, 123).await?;
+
+    // (content type, body, expected status, expected title)
+    let cases = [
+        (
+            "application/json",
+            r#"{"status": "Closed"}"#,
+            200,
+            "todo 101",
+        ),
+        (
+            "application/merge-patch+json; charset=utf-8",
+            r#"{"title": "merged"}"#,
+            200,
+            "merged",
+        ),
+        (
+            "application/json-patch+json",
+            r#"[{"op": "replace", "path": "/title", "value": "patched"}]"#,
+            200,
+            "patched",
+        ),
+        // the owner is not writable
+        ("application/json", r#"{"cid": 124}"#, 422, ""),
+        (
+            "application/json-patch+json",
+            r#"[{"op": "add", "path": "/cid", "value": 124}]"#,
+            422,
+            "",
+        ),
+        ("text/plain", "title", 415, ""),
+    ];
+
+    for (content_type, body, expected_status, expected_title) in cases {
+        // ACT
+        let response = warp::test::request()
+            .method("PATCH")
+            .header(HEADER_XAUTH, &token)
+            .header("content-type", content_type)
+            .path("/api/todos/101")
+            .body(body)
+            .reply(&todo_apis)
+            .await;
+
+        // ASSERT
+        assert_eq!(response.status(), expected_status, "{content_type} {body}");
+        if expected_status == 200 {
+            let todo: Todo = extract_body_data(&response)?;
+            assert_eq!(todo.title, expected_title, "{content_type} {body}");
+            assert_eq!(todo.cid, 123);
+        }
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn web_todo_etag() -> AnyhowResult<()> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+    let database = Arc::new(database);
+
+    let todo_apis = rest_filters("api", Arc::clone(&database)).recover(handle_rejection);
+    let token = new_token(&database, 123).await?;
+
+    // ACT
+    let get = warp::test::request()
+        .method("GET")
+        .header(HEADER_XAUTH, &token)
+        .path("/api/todos/101")
+        .reply(&todo_apis)
+        .await;
+    let etag = get.headers()["etag"].to_str()?.to_string();
+    let not_modified = warp::test::request()
+        .method("GET")
+        .header(HEADER_XAUTH, &token)
+        .header("if-none-match", format!("W/{etag}"))
+        .path("/api/todos/101")
+        .reply(&todo_apis)
+        .await;
+    let update = warp::test::request()
+        .method("PATCH")
+        .header(HEADER_XAUTH, &token)
+        .header("if-match", &etag)
+        .json(&serde_json::json!({ "title": "first tab" }))
+        .path("/api/todos/101")
+        .reply(&todo_apis)
+        .await;
+    let stale_update = warp::test::request()
+        .method("PATCH")
+        .header(HEADER_XAUTH, &token)
+        .header("if-match", &etag)
+        .json(&serde_json::json!({ "title": "second tab" }))
+        .path("/api/todos/101")
+        .reply(&todo_apis)
+        .await;
+    let stale_delete = warp::test::request()
+        .method("DELETE")
+        .header(HEADER_XAUTH, &token)
+        .header("if-match", &etag)
+        .path("/api/todos/101")
+        .reply(&todo_apis)
+        .await;
+    let weak_delete = warp::test::request()
+        .method("DELETE")
+        .header(HEADER_XAUTH, &token)
+        .header("if-match", "W/\"2\"")
+        .path("/api/todos/101")
+        .reply(&todo_apis)
+        .await;
+    let invalid_if_match = warp::test::request()
+        .method("DELETE")
+        .header(HEADER_XAUTH, &token)
+        .header("if-match", "2")
+        .path("/api/todos/101")
+        .reply(&todo_apis)
+        .await;
+    let listed_delete = warp::test::request()
+        .method("DELETE")
+        .header(HEADER_XAUTH, &token)
+        .header("if-match", "\"1\", W/\"2\", \"2\"")
+        .path("/api/todos/101")
+        .reply(&todo_apis)
+        .await;
+
+    // ASSERT
+    assert_eq!(get.status(), 200);
+    assert_eq!(etag, "\"1\"");
+    assert_eq!(not_modified.status(), 304);
+    assert!(not_modified.body().is_empty());
+    assert_eq!(update.status(), 200);
+    assert_eq!(update.headers()["etag"], "\"2\"");
+    assert_eq!(stale_update.status(), 412);
+    let body: Value = from_slice(stale_update.body())?;
+    assert_eq!(body["code"], "VERSION_CONFLICT");
+    assert_eq!(stale_delete.status(), 412);
+    assert_eq!(
+        weak_delete.status(),
+        412,
+        "If-Match uses the strong comparison"
+    );
+    assert_eq!(invalid_if_match.status(), 400);
+    assert_eq!(
+        listed_delete.status(),
+        200,
+        "one of the ETags is the current one"
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn web_todo_details() -> AnyhowResult<()> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+    let database = Arc::new(database);
+
+    let todo_apis = rest_filters("api", Arc::clone(&database)).recover(handle_rejection);
+    let token = new_token(&database, 123).await?;
+
+    // ACT
+    let created = warp::test::request()
+        .method("POST")
+        .header(HEADER_XAUTH, &token)
+        .json(&serde_json::json!({
+            "title": "with details",
+            "description": "some *markdown*",
+            "due_at": "2030-01-31T12:00:00Z",
+            "priority": "High",
+            "status": "Closed"
+        }))
+        .path("/api/todos")
+        .reply(&todo_apis)
+        .await;
+    let created_body: Value = from_slice(created.body())?;
+    let id = created_body["data"]["id"].as_i64().context("id")?;
+    let merged = warp::test::request()
+        .method("PATCH")
+        .header(HEADER_XAUTH, &token)
+        .header("content-type", "application/merge-patch+json")
+        .body(r#"{"due_at": null, "status": "Open"}"#)
+        .path(&format!("/api/todos/{id}"))
+        .reply(&todo_apis)
+        .await;
+
+    // ASSERT
+    assert_eq!(created.status(), 200, "http status");
+    let todo = &created_body["data"];
+    assert_eq!(todo["description"], "some *markdown*");
+    assert_eq!(todo["due_at"], "2030-01-31T12:00:00Z");
+    assert_eq!(todo["priority"], "High");
+    assert!(todo["completed_at"].is_string());
+    assert!(todo["ctime"].is_string());
+    assert!(todo["mtime"].is_string());
+    let todo: Todo = extract_body_data(&merged)?;
+    assert_eq!(todo.due_at, None);
+    assert_eq!(todo.completed_at, None);
+    assert_eq!(todo.description.as_deref(), Some("some *markdown*"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn web_todo_body_too_large() -> AnyhowResult<()> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+    let database = Arc::new(database);
+
+    let todo_apis = rest_filters("api", Arc::clone(&database)).recover(handle_rejection);
+    let token = new_token(&database, 123).await?;
+    let description = "a".repeat(usize::try_from(JSON_BODY_MAX_SIZE)?);
+
+    // ACT
+    let create = warp::test::request()
+        .method("POST")
+        .header(HEADER_XAUTH, &token)
+        .json(&serde_json::json!({ "title": "too large", "description": description }))
+        .path("/api/todos")
+        .reply(&todo_apis)
+        .await;
+    let patch = warp::test::request()
+        .method("PATCH")
+        .header(HEADER_XAUTH, &token)
+        .header("content-type", "application/merge-patch+json")
+        .json(&serde_json::json!({ "description": description }))
+        .path("/api/todos/101")
+        .reply(&todo_apis)
+        .await;
+
+    // ASSERT
+    assert_eq!(create.status(), 413, "http status");
+    assert_eq!(patch.status(), 413, "http status");
+    let body: Value = from_slice(patch.body())?;
+    assert_eq!(body["code"], "PAYLOAD_TOO_LARGE");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn web_todo_assignee_unassigns_themselves() -> AnyhowResult<()> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+    let database = Arc::new(database);
+
+    let todo_apis = rest_filters("api", Arc::clone(&database)).recover(handle_rejection);
+    let owner_token = new_token(&database, 123).await?;
+    let assignee_token = new_token(&database, 124).await?;
+    let assigned = warp::test::request()
+        .method("PATCH")
+        .header(HEADER_XAUTH, &owner_token)
+        .json(&serde_json::json!({ "assignee_id": 124 }))
+        .path("/api/todos/101")
+        .reply(&todo_apis)
+        .await;
+
+    // ACT
+    let unassigned = warp::test::request()
+        .method("PATCH")
+        .header(HEADER_XAUTH, &assignee_token)
+        .json(&serde_json::json!({ "assignee_id": null }))
+        .path("/api/todos/101")
+        .reply(&todo_apis)
+        .await;
+    let get_by_owner = warp::test::request()
+        .method("GET")
+        .header(HEADER_XAUTH, &owner_token)
+        .path("/api/todos/101")
+        .reply(&todo_apis)
+        .await;
+
+    // ASSERT
+    assert_eq!(assigned.status(), 200, "http status");
+    assert_eq!(unassigned.status(), 200, "http status");
+    let todo: Todo = extract_body_data(&unassigned)?;
+    assert_eq!(todo.assignee_id, None);
+    let todo: Todo = extract_body_data(&get_by_owner)?;
+    assert_eq!(todo.assignee_id, None, "the change is saved");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn web_todo_tree() -> AnyhowResult<()> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+    let database = Arc::new(database);
+
+    let todo_apis = rest_filters("api", Arc::clone(&database)).recover(handle_rejection);
+    let token = new_token(&database, 123).await?;
+    let subtask = warp::test::request()
+        .method("POST")
+        .header(HEADER_XAUTH, &token)
+        .json(&serde_json::json!({"title": "subtask", "parent_id": 101, "status": "Closed"}))
+        .path("/api/todos")
+        .reply(&todo_apis)
+        .await;
+    let subtask: Todo = extract_body_data(&subtask)?;
+
+    // ACT
+    let response = warp::test::request()
+        .method("GET")
+        .header(HEADER_XAUTH, &token)
+        .path("/api/todos/101/tree")
+        .reply(&todo_apis)
+        .await;
+
+    // ASSERT
+    assert_eq!(response.status(), 200, "http status");
+    let tree: Value = extract_body_data(&response)?;
+    assert_eq!(tree["id"], 101);
+    assert_eq!(
+        tree["progress"],
+        serde_json::json!({"closed": 1, "total": 1})
+    );
+    assert_eq!(tree["children"][0]["id"], subtask.id);
+    assert_eq!(tree["children"][0]["parent_id"], 101);
+    assert_eq!(tree["children"][0]["children"], serde_json::json!([]));
+
+    Ok(())
+}
+
 // Web test utils
 
 fn extract_body_data<Deserializable>(
-    response: hyper::Response<body::Bytes>,
+    response: &hyper::Response<body::Bytes>,
 ) -> AnyhowResult<Deserializable>
 where
     for<'de> Deserializable: Deserialize<'de>,
@@ -0,0 +1,102 @@
+use std::sync::Arc;
+
+use anyhow::Result as AnyhowResult;
+use serde_json::{from_slice, json, Value};
+use warp::Filter;
+
+use crate::model::{initialize_database, DatabaseMode};
+use crate::security::new_token;
+use crate::web::{filter_utils::HEADER_XAUTH, handle_rejection};
+
+use super::rest_filters;
+
+#[tokio::test]
+async fn web_webhook_crud_and_test() -> AnyhowResult<()> {
+    // ARRANGE
+    let database = Arc::new(initialize_database(DatabaseMode::Dev).await?);
+    let webhook_apis = rest_filters("api", Arc::clone(&database)).recover(handle_rejection);
+    let token = new_token(&database, 123).await?;
+    let other_token = new_token(&database, 124).await?;
+
+    // ACT
+    let created = warp::test::request()
+        .method("POST")
+        .header(HEADER_XAUTH, &token)
+        .json(&json!({ "url": "https://example.com/hook", "project_id": 1 }))
+        .path("/api/webhooks")
+        .reply(&webhook_apis)
+        .await;
+    let created: Value = from_slice(created.body())?;
+    let id = created["data"]["id"].as_i64().unwrap_or_default();
+    let updated = warp::test::request()
+        .method("PATCH")
+        .header(HEADER_XAUTH, &token)
+        .json(&json!({ "active": false, "project_id": null }))
+        .path(&format!("/api/webhooks/{id}"))
+        .reply(&webhook_apis)
+        .await;
+    let test = warp::test::request()
+        .method("POST")
+        .header(HEADER_XAUTH, &token)
+        .path(&format!("/api/webhooks/{id}/test"))
+        .reply(&webhook_apis)
+        .await;
+    let deliveries = warp::test::request()
+        .method("GET")
+        .header(HEADER_XAUTH, &token)
+        .path(&format!("/api/webhooks/{id}/deliveries"))
+        .reply(&webhook_apis)
+        .await;
+    let other_test = warp::test::request()
+        .method("POST")
+        .header(HEADER_XAUTH, &other_token)
+        .path(&format!("/api/webhooks/{id}/test"))
+        .reply(&webhook_apis)
+        .await;
+    let invalid = warp::test::request()
+        .method("POST")
+        .header(HEADER_XAUTH, &token)
+        .json(&json!({ "url": "not a url" }))
+        .path("/api/webhooks")
+        .reply(&webhook_apis)
+        .await;
+    let deleted = warp::test::request()
+        .method("DELETE")
+        .header(HEADER_XAUTH, &token)
+        .path(&format!("/api/webhooks/{id}"))
+        .reply(&webhook_apis)
+        .await;
+    let listed = warp::test::request()
+        .method("GET")
+        .header(HEADER_XAUTH, &token)
+        .path("/api/webhooks")
+        .reply(&webhook_apis)
+        .await;
+
+    // ASSERT
+    assert_eq!(created["data"]["project_id"], 1);
+    assert!(created["data"]["secret"]
+        .as_str()
+        .is_some_and(|secret| secret.starts_with("whsec_")));
+    assert_eq!(updated.status(), 200, "http status");
+    let updated: Value = from_slice(updated.body())?;
+    assert_eq!(updated["data"]["active"], false);
+    assert_eq!(updated["data"]["project_id"], Value::Null);
+    assert_eq!(test.status(), 200, "http status");
+    let test: Value = from_slice(test.body())?;
+    assert_eq!(test["data"]["type"], "webhook.test");
+    assert_eq!(test["data"]["data"]["webhook_id"], id);
+    let deliveries: Value = from_slice(deliveries.body())?;
+    assert_eq!(
+        deliveries["data"],
+        json!([]),
+        "not delivered until the worker runs"
+    );
+    assert_eq!(other_test.status(), 404, "http status");
+    assert_eq!(invalid.status(), 422, "http status");
+    assert_eq!(deleted.status(), 200, "http status");
+    let listed: Value = from_slice(listed.body())?;
+    assert_eq!(listed["data"], json!([]));
+
+    Ok(())
+}
@@ -0,0 +1,70 @@
+use std::{sync::Arc, time::Duration};
+
+use anyhow::Result as AnyhowResult;
+use serde_json::{from_str, Value};
+use tokio::time::timeout;
+use warp::Filter;
+
+use crate::model::{
+    initialize_database, DatabaseMode, EventHub, ModelAccessController, PartialTodo,
+};
+use crate::security::{new_token, user_context_from_token};
+use crate::web::{filter_utils::HEADER_XAUTH, handle_rejection};
+
+use super::rest_filters;
+
+#[tokio::test]
+async fn web_ws_todo_events_of_visible_todos() -> AnyhowResult<()> {
+    // ARRANGE
+    let database = Arc::new(initialize_database(DatabaseMode::Dev).await?);
+    let hub = Arc::new(EventHub::new(database.as_ref().clone()));
+    let ws_apis = rest_filters("api", Arc::clone(&database), hub).recover(handle_rejection);
+    let token = new_token(&database, 123).await?;
+    let other_token = new_token(&database, 124).await?;
+    let utx = user_context_from_token(&database, &token).await?;
+    let other_utx = user_context_from_token(&database, &other_token).await?;
+    let mut client = warp::test::ws()
+        .path("/api/ws")
+        .header(HEADER_XAUTH, &token)
+        .handshake(ws_apis.clone())
+        .await?;
+    let mut other_client = warp::test::ws()
+        .path(&format!("/api/ws?token={other_token}"))
+        .handshake(ws_apis.clone())
+        .await?;
+
+    // ACT
+    let data = PartialTodo {
+        title: Some(String::from("live")),
+        ..PartialTodo::default()
+    };
+    let todo = ModelAccessController::create(&database, &utx, data).await?;
+    ModelAccessController::delete(&database, &utx, todo.id, None).await?;
+    let data = PartialTodo {
+        title: Some(String::from("todo 200 live")),
+        ..PartialTodo::default()
+    };
+    ModelAccessController::update(&database, &other_utx, 200, data, None).await?;
+    let created = timeout(Duration::from_secs(5), client.recv()).await??;
+    let deleted = timeout(Duration::from_secs(5), client.recv()).await??;
+    let other_first = timeout(Duration::from_secs(5), other_client.recv()).await??;
+    let unauthenticated = warp::test::ws().path("/api/ws").handshake(ws_apis).await;
+
+    // ASSERT
+    let created: Value = from_str(created.to_str().unwrap_or_default())?;
+    assert_eq!(created["type"], "todo.created");
+    assert_eq!(created["data"]["id"], todo.id);
+    assert_eq!(created["data"]["title"], "live");
+    let deleted: Value = from_str(deleted.to_str().unwrap_or_default())?;
+    assert_eq!(deleted["type"], "todo.deleted");
+    assert_eq!(deleted["data"]["id"], todo.id);
+    let other_first: Value = from_str(other_first.to_str().unwrap_or_default())?;
+    assert_eq!(
+        other_first["type"], "todo.updated",
+        "not the todos of demo1"
+    );
+    assert_eq!(other_first["data"]["id"], 200);
+    assert!(unauthenticated.is_err(), "no token, no WebSocket");
+
+    Ok(())
+}
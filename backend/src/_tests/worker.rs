@@ -0,0 +1,215 @@
+use std::sync::{Arc, Mutex};
+
+use chrono::{TimeDelta, Utc};
+use warp::{http::StatusCode, hyper::body::Bytes, Filter};
+
+use super::{run_once, webhook::sign};
+use crate::{
+    config::JobConfig,
+    email::MemoryMailer,
+    model::{
+        initialize_database, DatabaseMode, JobStatus, ModelAccessController, PartialTodo,
+        PartialWebhook, WebhookModelAccessController,
+    },
+    security::{new_token, user_context_from_token},
+};
+
+#[tokio::test]
+async fn worker_run_once_reminds_due_todo() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+    let mailer = MemoryMailer::new("Todo <todo@localhost>".parse()?);
+    let utx = user_context_from_token(&database, &new_token(&database, 123).await?).await?;
+    let data = PartialTodo {
+        due_at: Some(Some(Utc::now() + TimeDelta::minutes(10))),
+        ..PartialTodo::default()
+    };
+    ModelAccessController::update(&database, &utx, 101, data, None).await?;
+
+    // ACT
+    let reminders = run_once(&JobConfig::default(), &database, &mailer).await?;
+    let notifications = run_once(&JobConfig::default(), &database, &mailer).await?;
+    let next_poll = run_once(&JobConfig::default(), &database, &mailer).await?;
+
+    // ASSERT
+    assert_eq!(reminders.len(), 1);
+    assert_eq!(reminders[0].kind, "reminder");
+    assert_eq!(reminders[0].status, JobStatus::Done);
+    assert_eq!(reminders[0].attempts, 1);
+    assert_eq!(notifications.len(), 1);
+    assert_eq!(notifications[0].kind, "notification");
+    assert_eq!(notifications[0].status, JobStatus::Done);
+    assert!(next_poll.is_empty(), "a todo is reminded once");
+    let sent = mailer.sent();
+    assert_eq!(sent.len(), 1);
+    assert_eq!(sent[0].to, "demo1@example.com");
+    assert!(sent[0].subject.starts_with("\"todo 101\" is due at"));
+    assert!(sent[0].text.contains("/#/todos/101"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn worker_run_once_emails_assignee() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+    let mailer = MemoryMailer::new("Todo <todo@localhost>".parse()?);
+    let utx = user_context_from_token(&database, &new_token(&database, 123).await?).await?;
+    let data = PartialTodo {
+        title: Some(String::from("<fix> & ship")),
+        assignee_id: Some(Some(124)),
+        ..PartialTodo::default()
+    };
+    ModelAccessController::update(&database, &utx, 101, data, None).await?;
+
+    // ACT
+    run_once(&JobConfig::default(), &database, &mailer).await?;
+
+    // ASSERT
+    let sent = mailer.sent();
+    assert_eq!(sent.len(), 1);
+    assert_eq!(sent[0].to, "demo2@example.com");
+    assert_eq!(sent[0].subject, "You were assigned \"<fix> & ship\"");
+    assert!(sent[0].text.starts_with("Hello demo2,"));
+    assert!(sent[0].html.contains("&lt;fix&gt; &amp; ship"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn worker_run_once_despite_housekeeping_error() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+    let mailer = MemoryMailer::new("Todo <todo@localhost>".parse()?);
+    let utx = user_context_from_token(&database, &new_token(&database, 123).await?).await?;
+    let data = PartialTodo {
+        assignee_id: Some(Some(124)),
+        ..PartialTodo::default()
+    };
+    ModelAccessController::update(&database, &utx, 101, data, None).await?;
+    // the deliveries purge fails
+    sqlx::query("DROP TABLE webhook_delivery")
+        .execute(&database)
+        .await?;
+
+    // ACT
+    let finished = run_once(&JobConfig::default(), &database, &mailer).await?;
+
+    // ASSERT
+    assert_eq!(finished.len(), 1);
+    assert_eq!(finished[0].status, JobStatus::Done);
+    assert_eq!(mailer.sent().len(), 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn worker_unknown_kind_is_dead() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+    let mailer = MemoryMailer::new("Todo <todo@localhost>".parse()?);
+    let job_config = JobConfig {
+        max_attempts: 1,
+        ..JobConfig::default()
+    };
+    sqlx::query("INSERT INTO job (kind, max_attempts) VALUES ('unknown', 1)")
+        .execute(&database)
+        .await?;
+
+    // ACT
+    let jobs = run_once(&job_config, &database, &mailer).await?;
+
+    // ASSERT
+    assert_eq!(jobs[0].status, JobStatus::Dead);
+    assert!(jobs[0]
+        .last_error
+        .as_deref()
+        .is_some_and(|error| error.contains("unknown job kind 'unknown'")));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn worker_webhook_delivery_signed_and_retried() -> Result<(), Box<dyn std::error::Error>> {
+    // ARRANGE
+    let database = initialize_database(DatabaseMode::Dev).await?;
+    let mailer = MemoryMailer::new("Todo <todo@localhost>".parse()?);
+    let utx = user_context_from_token(&database, &new_token(&database, 123).await?).await?;
+    let job_config = JobConfig {
+        backoff_base_ms: 0,
+        ..JobConfig::default()
+    };
+    // a receiver failing the first request, the requests kept as (signature, event, body)
+    let received = Arc::new(Mutex::new(Vec::<(String, String, Bytes)>::new()));
+    let hook = {
+        let received = Arc::clone(&received);
+        warp::post()
+            .and(warp::path("hook"))
+            .and(warp::header::<String>("X-Webhook-Signature"))
+            .and(warp::header::<String>("X-Webhook-Event"))
+            .and(warp::body::bytes())
+            .map(move |signature, event, body| {
+                let count = {
+                    let mut received = received.lock().unwrap();
+                    received.push((signature, event, body));
+                    received.len()
+                };
+                let status = if count == 1 {
+                    StatusCode::INTERNAL_SERVER_ERROR
+                } else {
+                    StatusCode::NO_CONTENT
+                };
+                warp::reply::with_status(warp::reply(), status)
+            })
+    };
+    let (address, server) = warp::serve(hook).bind_ephemeral(([127, 0, 0, 1], 0));
+    tokio::spawn(server);
+    let data = PartialWebhook {
+        url: Some(format!("http://{address}/hook")),
+        ..PartialWebhook::default()
+    };
+    let webhook = WebhookModelAccessController::create(&database, &utx, data).await?;
+    let data = PartialTodo {
+        title: Some(String::from("posted")),
+        ..PartialTodo::default()
+    };
+    let todo = ModelAccessController::create(&database, &utx, data).await?;
+
+    // ACT
+    let failed = run_once(&job_config, &database, &mailer).await?;
+    let retried = run_once(&job_config, &database, &mailer).await?;
+
+    // ASSERT
+    assert_eq!(failed.len(), 1);
+    assert_eq!(failed[0].status, JobStatus::Queued);
+    assert_eq!(retried.len(), 1);
+    assert_eq!(retried[0].status, JobStatus::Done);
+    assert_eq!(retried[0].attempts, 2);
+
+    let received = received.lock().unwrap().clone();
+    assert_eq!(received.len(), 2);
+    let (signature, event_type, body) = &received[1];
+    assert_eq!(event_type, "todo.created");
+    let (timestamp, expected) = signature
+        .strip_prefix("t=")
+        .and_then(|signature| signature.split_once(",v1="))
+        .ok_or("malformed signature")?;
+    assert_eq!(sign(&webhook.secret, timestamp.parse()?, body), expected);
+    let event: serde_json::Value = serde_json::from_slice(body)?;
+    assert_eq!(event["data"]["id"], todo.id);
+    assert_eq!(
+        event["id"],
+        serde_json::from_slice::<serde_json::Value>(&received[0].2)?["id"]
+    );
+
+    let deliveries = WebhookModelAccessController::deliveries(&database, &utx, webhook.id).await?;
+    assert_eq!(deliveries.len(), 2);
+    assert_eq!(deliveries[0].attempt, 2);
+    assert_eq!(deliveries[0].status_code, Some(204));
+    assert!(deliveries[0].error.is_none());
+    assert_eq!(deliveries[1].attempt, 1);
+    assert_eq!(deliveries[1].status_code, Some(500));
+    assert!(deliveries[1].error.is_some());
+
+    Ok(())
+}
@@ -0,0 +1,178 @@
+use std::{net::IpAddr, sync::Arc};
+
+use clap::{Parser, Subcommand};
+use thiserror::Error as ThisError;
+
+use crate::{
+    config::{self, init_config, Config},
+    email, model, web, worker,
+};
+
+// the doc comments are the help texts, printed as they are
+#[allow(clippy::doc_markdown)]
+#[derive(Parser, Debug)]
+#[command(version, about = "Todo server, warp and postgres")]
+pub struct Cli {
+    /// TOML configuration file (default: $CONFIG_FILE, or config.toml if present)
+    #[arg(short, long, global = true)]
+    pub config: Option<String>,
+
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[allow(clippy::doc_markdown)]
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Apply the pending migrations and start the web server
+    Serve {
+        /// Web port, overrides web.port
+        #[arg(short, long)]
+        port: Option<u16>,
+
+        /// Bind address, overrides web.bind_address
+        #[arg(short, long)]
+        bind_address: Option<IpAddr>,
+
+        /// Folder of the static site, overrides web.folder
+        #[arg(short, long)]
+        web_folder: Option<String>,
+    },
+
+    /// Manage the database migrations
+    Migrate {
+        #[command(subcommand)]
+        action: MigrateAction,
+    },
+
+    /// Insert the development data in an already migrated database
+    Seed,
+
+    /// Validate the configuration and the database connectivity
+    Check,
+}
+
+#[derive(Subcommand, Debug, PartialEq, Eq)]
+pub enum MigrateAction {
+    /// Apply the pending migrations
+    Up,
+    /// List the migrations, applied or pending
+    Status,
+    /// Revert the last applied migration
+    Down,
+}
+
+pub async fn run(cli: Cli) -> Result<(), Error> {
+    let mut config = Config::load(cli.config.as_deref())?;
+
+    if let Command::Serve {
+        port,
+        bind_address,
+        web_folder,
+    } = &cli.command
+    {
+        if let Some(port) = port {
+            config.web.port = *port;
+        }
+        if let Some(bind_address) = bind_address {
+            config.web.bind_address = *bind_address;
+        }
+        if let Some(web_folder) = web_folder {
+            config.web.folder.clone_from(web_folder);
+        }
+        config.validate()?;
+    }
+
+    let web_config = config.web.clone();
+    let job_config = config.jobs.clone();
+    let email_config = config.email.clone();
+    let database_mode = config.database.mode;
+    init_config(config)?;
+
+    match cli.command {
+        Command::Serve { .. } => {
+            // In Production, the database might not be accessible right away, we should loop within a time range until accessible or too long to wait
+            let database = Arc::new(model::initialize_database(database_mode).await?);
+            let worker = if job_config.enabled {
+                let mailer = email::mailer_from_config(&email_config)?;
+                let worker = worker::start_worker(job_config, Arc::clone(&database), mailer);
+                Some(tokio::spawn(worker))
+            } else {
+                None
+            };
+            web::start_web(&web_config, database).await?;
+            if let Some(worker) = worker {
+                worker.abort();
+            }
+            println!("Server ended");
+        }
+        Command::Migrate { action } => {
+            let database = model::connect_database().await?;
+            let directory = model::migrations_directory();
+
+            match action {
+                MigrateAction::Up => {
+                    let applied = model::run_migrations(&database, &directory).await?;
+                    println!("{} migration(s) applied", applied.len());
+                }
+                MigrateAction::Status => {
+                    for status in model::migration_status(&database, &directory).await? {
+                        let state = match (&status.applied_at, status.checksum_mismatch) {
+                            (Some(_), true) => String::from("MODIFIED after being applied"),
+                            (Some(applied_at), false) => format!("applied {applied_at}"),
+                            (None, _) => String::from("pending"),
+                        };
+                        println!("{:<40} {state}", status.version);
+                    }
+                }
+                MigrateAction::Down => {
+                    if model::revert_last_migration(&database, &directory)
+                        .await?
+                        .is_none()
+                    {
+                        println!("No migration to revert");
+                    }
+                }
+            }
+        }
+        Command::Seed => {
+            let database = model::connect_database().await?;
+            model::seed_database(&database).await?;
+            println!("Database seeded");
+        }
+        Command::Check => {
+            let database = model::connect_database().await?;
+            sqlx::query("SELECT 1")
+                .execute(&database)
+                .await
+                .map_err(model::Error::from)?;
+            let pending = model::migration_status(&database, &model::migrations_directory())
+                .await?
+                .iter()
+                .filter(|status| status.applied_at.is_none())
+                .count();
+            println!("Configuration valid, database reachable, {pending} pending migration(s)");
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(ThisError, Debug)]
+pub enum Error {
+    #[error(transparent)]
+    Config(#[from] config::Error),
+
+    #[error(transparent)]
+    Model(#[from] model::Error),
+
+    #[error(transparent)]
+    Web(#[from] web::Error),
+
+    #[error(transparent)]
+    Email(#[from] email::Error),
+}
+
+#[cfg(test)]
+#[path = "../_tests/cli.rs"]
+mod tests;
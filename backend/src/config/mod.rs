@@ -0,0 +1,632 @@
+use std::{env, fs, net::IpAddr, path::Path, str::FromStr, sync::OnceLock, time::Duration};
+
+use lettre::message::Mailbox;
+use serde_derive::Deserialize;
+use sqlx::postgres::PgConnectOptions;
+use thiserror::Error as ThisError;
+
+use crate::email::{EmailTransport, SmtpTls};
+use crate::model::{CloseParentRule, DatabaseMode, JOB_LEASE};
+use crate::storage::StorageBackend;
+
+// Loading order, each one overriding the previous : defaults, TOML file, environment variables, DATABASE_URL
+const CONFIG_FILE_ENV: &str = "CONFIG_FILE";
+const DEFAULT_CONFIG_FILE: &str = "config.toml";
+
+const DEV_TOKEN_SECRET: &str = "dev-only-secret-do-not-use-in-production";
+
+// ten years, the retentions are durations and intervals, which overflow a bit later
+const MAX_RETENTION_HOURS: u64 = 10 * 365 * 24;
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub database: DatabaseConfig,
+    pub web: WebConfig,
+    pub security: SecurityConfig,
+    pub todos: TodoConfig,
+    pub attachments: AttachmentConfig,
+    pub jobs: JobConfig,
+    pub email: EmailConfig,
+    pub webhooks: WebhookConfig,
+    pub events: EventConfig,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseConfig {
+    // when set, takes precedence over host, port, name, user and password
+    pub url: Option<String>,
+    pub host: String,
+    pub port: u16,
+    pub name: String,
+    pub user: String,
+    pub password: String,
+    pub max_connections: u32,
+    pub acquire_timeout_ms: u64,
+    // a session of the app idle in a transaction longer is closed, it would hold back every change log reader
+    pub idle_in_transaction_timeout_ms: u64,
+    pub mode: DatabaseMode,
+    // only used in dev mode, to recreate the database
+    pub root_name: String,
+    pub root_user: String,
+    pub root_password: String,
+    pub sql_directory: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WebConfig {
+    pub bind_address: IpAddr,
+    pub port: u16,
+    pub folder: String,
+    pub api_base_path: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SecurityConfig {
+    pub token_secret: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TodoConfig {
+    // closing a todo with open subtasks is refused (require), or closes them (cascade)
+    pub close_parent: CloseParentRule,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AttachmentConfig {
+    pub backend: StorageBackend,
+    // local backend only
+    pub directory: String,
+    // the largest file accepted, in bytes
+    pub max_size: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct JobConfig {
+    // the worker started with the web server, several servers can share the same jobs
+    pub enabled: bool,
+    pub poll_interval_ms: u64,
+    // the jobs claimed at each poll
+    pub batch_size: u16,
+    // the attempts before a failing job is dead
+    pub max_attempts: u16,
+    // the delay before the second attempt, doubled for each next one
+    pub backoff_base_ms: u64,
+    // how long before its due date a todo is reminded
+    pub reminder_lead_minutes: u32,
+    // how long the done and dead jobs, and the webhook deliveries, are kept
+    pub retention_hours: u64,
+    // how long the todo changes are kept for the event stream clients to catch up
+    pub change_retention_hours: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EmailConfig {
+    pub transport: EmailTransport,
+    // the sender, like "Todo <todo@example.com>"
+    pub from: String,
+    // the address of the web site, for the links of the emails
+    pub app_url: String,
+    // file transport only, where the emails are written
+    pub directory: String,
+    // smtp transport only
+    pub smtp_host: String,
+    pub smtp_port: u16,
+    pub smtp_username: Option<String>,
+    pub smtp_password: Option<String>,
+    pub smtp_tls: SmtpTls,
+    // how long a connection or a command to the relay waits, a slower email is failed and retried
+    pub smtp_timeout_ms: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WebhookConfig {
+    // how long a delivery waits for the response, a slower one is failed and retried
+    pub timeout_ms: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EventConfig {
+    // the interval of the comments keeping an idle event stream open through the proxies
+    pub keep_alive_secs: u64,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self {
+            url: None,
+            host: String::from("localhost"),
+            port: 5432,
+            name: String::from("rust_warp_postgres"),
+            user: String::from("rust_warp_postgres_user"),
+            password: String::from("password"),
+            max_connections: 5,
+            acquire_timeout_ms: 500,
+            idle_in_transaction_timeout_ms: 30_000,
+            mode: DatabaseMode::Prod,
+            root_name: String::from("postgres"),
+            root_user: String::from("postgres"),
+            root_password: String::from("postgres"),
+            sql_directory: String::from("sql/"),
+        }
+    }
+}
+
+impl Default for WebConfig {
+    fn default() -> Self {
+        Self {
+            bind_address: IpAddr::from([127, 0, 0, 1]),
+            port: 8080,
+            folder: String::from("web-folder/"),
+            api_base_path: String::from("api"),
+        }
+    }
+}
+
+impl Default for SecurityConfig {
+    fn default() -> Self {
+        Self {
+            token_secret: String::from(DEV_TOKEN_SECRET),
+        }
+    }
+}
+
+impl Default for AttachmentConfig {
+    fn default() -> Self {
+        Self {
+            backend: StorageBackend::Local,
+            directory: String::from("attachments/"),
+            max_size: 10 * 1024 * 1024,
+        }
+    }
+}
+
+impl Default for JobConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            poll_interval_ms: 5000,
+            batch_size: 10,
+            max_attempts: 5,
+            backoff_base_ms: 10_000,
+            reminder_lead_minutes: 60,
+            retention_hours: 168,
+            change_retention_hours: 168,
+        }
+    }
+}
+
+impl Default for EmailConfig {
+    fn default() -> Self {
+        Self {
+            transport: EmailTransport::File,
+            from: String::from("Todo <todo@localhost>"),
+            app_url: String::from("http://localhost:8080"),
+            directory: String::from("emails/"),
+            smtp_host: String::from("localhost"),
+            smtp_port: 587,
+            smtp_username: None,
+            smtp_password: None,
+            smtp_tls: SmtpTls::StartTls,
+            smtp_timeout_ms: 10_000,
+        }
+    }
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self { timeout_ms: 10_000 }
+    }
+}
+
+impl Default for EventConfig {
+    fn default() -> Self {
+        Self {
+            keep_alive_secs: 15,
+        }
+    }
+}
+
+impl Config {
+    // file: explicit TOML file, which must exist. Otherwise CONFIG_FILE, or config.toml if present
+    pub fn load(file: Option<&str>) -> Result<Self, Error> {
+        let config = Self::read(file)?;
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    // Same as load, without the validation
+    fn read(file: Option<&str>) -> Result<Self, Error> {
+        let file = file.map_or_else(
+            || {
+                env::var(CONFIG_FILE_ENV).ok().or_else(|| {
+                    Path::new(DEFAULT_CONFIG_FILE)
+                        .exists()
+                        .then(|| DEFAULT_CONFIG_FILE.to_string())
+                })
+            },
+            |file| Some(file.to_string()),
+        );
+
+        let mut config = match file {
+            Some(file) => Self::from_toml_file(&file)?,
+            None => Self::default(),
+        };
+
+        config.apply_env()?;
+
+        Ok(config)
+    }
+
+    pub fn from_toml_file(file: &str) -> Result<Self, Error> {
+        let content =
+            fs::read_to_string(file).map_err(|error| Error::FileRead(file.to_string(), error))?;
+
+        toml::from_str(&content).map_err(|error| Error::FileParse(file.to_string(), error))
+    }
+
+    fn apply_env(&mut self) -> Result<(), Error> {
+        let database = &mut self.database;
+        if let Some(url) = env_value("DATABASE_URL")? {
+            database.url = Some(url);
+        }
+        env_override("DATABASE_HOST", &mut database.host)?;
+        env_override("DATABASE_PORT", &mut database.port)?;
+        env_override("DATABASE_NAME", &mut database.name)?;
+        env_override("DATABASE_USER", &mut database.user)?;
+        env_override("DATABASE_PASSWORD", &mut database.password)?;
+        env_override("DATABASE_MAX_CONNECTIONS", &mut database.max_connections)?;
+        env_override(
+            "DATABASE_ACQUIRE_TIMEOUT_MS",
+            &mut database.acquire_timeout_ms,
+        )?;
+        env_override(
+            "DATABASE_IDLE_IN_TRANSACTION_TIMEOUT_MS",
+            &mut database.idle_in_transaction_timeout_ms,
+        )?;
+        env_override("DATABASE_MODE", &mut database.mode)?;
+        env_override("DATABASE_ROOT_NAME", &mut database.root_name)?;
+        env_override("DATABASE_ROOT_USER", &mut database.root_user)?;
+        env_override("DATABASE_ROOT_PASSWORD", &mut database.root_password)?;
+        env_override("SQL_DIRECTORY", &mut database.sql_directory)?;
+
+        let web = &mut self.web;
+        env_override("WEB_BIND_ADDRESS", &mut web.bind_address)?;
+        env_override("WEB_PORT", &mut web.port)?;
+        env_override("WEB_FOLDER", &mut web.folder)?;
+        env_override("API_BASE_PATH", &mut web.api_base_path)?;
+
+        env_override("TOKEN_SECRET", &mut self.security.token_secret)?;
+
+        env_override("TODO_CLOSE_PARENT", &mut self.todos.close_parent)?;
+
+        let attachments = &mut self.attachments;
+        env_override("ATTACHMENTS_BACKEND", &mut attachments.backend)?;
+        env_override("ATTACHMENTS_DIRECTORY", &mut attachments.directory)?;
+        env_override("ATTACHMENTS_MAX_SIZE", &mut attachments.max_size)?;
+
+        let jobs = &mut self.jobs;
+        env_override("JOBS_ENABLED", &mut jobs.enabled)?;
+        env_override("JOBS_POLL_INTERVAL_MS", &mut jobs.poll_interval_ms)?;
+        env_override("JOBS_BATCH_SIZE", &mut jobs.batch_size)?;
+        env_override("JOBS_MAX_ATTEMPTS", &mut jobs.max_attempts)?;
+        env_override("JOBS_BACKOFF_BASE_MS", &mut jobs.backoff_base_ms)?;
+        env_override(
+            "JOBS_REMINDER_LEAD_MINUTES",
+            &mut jobs.reminder_lead_minutes,
+        )?;
+        env_override("JOBS_RETENTION_HOURS", &mut jobs.retention_hours)?;
+        env_override(
+            "JOBS_CHANGE_RETENTION_HOURS",
+            &mut jobs.change_retention_hours,
+        )?;
+
+        let email = &mut self.email;
+        env_override("EMAIL_TRANSPORT", &mut email.transport)?;
+        env_override("EMAIL_FROM", &mut email.from)?;
+        env_override("EMAIL_APP_URL", &mut email.app_url)?;
+        env_override("EMAIL_DIRECTORY", &mut email.directory)?;
+        env_override("EMAIL_SMTP_HOST", &mut email.smtp_host)?;
+        env_override("EMAIL_SMTP_PORT", &mut email.smtp_port)?;
+        if let Some(username) = env_value("EMAIL_SMTP_USERNAME")? {
+            email.smtp_username = Some(username);
+        }
+        if let Some(password) = env_value("EMAIL_SMTP_PASSWORD")? {
+            email.smtp_password = Some(password);
+        }
+        env_override("EMAIL_SMTP_TLS", &mut email.smtp_tls)?;
+        env_override("EMAIL_SMTP_TIMEOUT_MS", &mut email.smtp_timeout_ms)?;
+
+        env_override("WEBHOOKS_TIMEOUT_MS", &mut self.webhooks.timeout_ms)?;
+
+        env_override("EVENTS_KEEP_ALIVE_SECS", &mut self.events.keep_alive_secs)?;
+
+        Ok(())
+    }
+
+    pub fn validate(&self) -> Result<(), Error> {
+        let invalid =
+            |key: &'static str, reason: &str| Err(Error::Invalid(key, reason.to_string()));
+
+        if let Some(url) = &self.database.url {
+            if let Err(error) = PgConnectOptions::from_str(url) {
+                return invalid("database.url", &error.to_string());
+            }
+            // the database is recreated from the name, user and password keys, not from the url
+            if self.database.mode == DatabaseMode::Dev {
+                return invalid("database.url", "can't be set in dev mode");
+            }
+        }
+        if self.database.port == 0 {
+            return invalid("database.port", "must not be 0");
+        }
+        if self.database.max_connections == 0 {
+            return invalid("database.max_connections", "must be at least 1");
+        }
+        if self.database.acquire_timeout_ms == 0 {
+            return invalid("database.acquire_timeout_ms", "must not be 0");
+        }
+        if self.database.idle_in_transaction_timeout_ms == 0 {
+            return invalid("database.idle_in_transaction_timeout_ms", "must not be 0");
+        }
+        if self.web.port == 0 {
+            return invalid("web.port", "must not be 0");
+        }
+        if self.web.folder.trim().is_empty() {
+            return invalid("web.folder", "must not be empty");
+        }
+        let api_base_path = self.web.api_base_path.trim_matches('/');
+        if api_base_path.is_empty() || api_base_path.contains(char::is_whitespace) {
+            return invalid(
+                "web.api_base_path",
+                "must be a non empty path, like 'api/v1'",
+            );
+        }
+        if self.security.token_secret.len() < 32 {
+            return invalid("security.token_secret", "must be at least 32 bytes long");
+        }
+        if self.database.mode == DatabaseMode::Prod
+            && self.security.token_secret == DEV_TOKEN_SECRET
+        {
+            return invalid(
+                "security.token_secret",
+                "must not be the development secret in prod mode, set TOKEN_SECRET",
+            );
+        }
+        if self.attachments.directory.trim().is_empty() {
+            return invalid("attachments.directory", "must not be empty");
+        }
+        if self.attachments.max_size == 0 {
+            return invalid("attachments.max_size", "must not be 0");
+        }
+        if self.jobs.poll_interval_ms == 0 {
+            return invalid("jobs.poll_interval_ms", "must not be 0");
+        }
+        if self.jobs.batch_size == 0 {
+            return invalid("jobs.batch_size", "must be at least 1");
+        }
+        if self.jobs.max_attempts == 0 {
+            return invalid("jobs.max_attempts", "must be at least 1");
+        }
+        let retention_hours_range = 1..=MAX_RETENTION_HOURS;
+        let retention_hours_reason = format!("must be between 1 and {MAX_RETENTION_HOURS}");
+        if !retention_hours_range.contains(&self.jobs.retention_hours) {
+            return invalid("jobs.retention_hours", &retention_hours_reason);
+        }
+        if !retention_hours_range.contains(&self.jobs.change_retention_hours) {
+            return invalid("jobs.change_retention_hours", &retention_hours_reason);
+        }
+        if let Err(error) = self.email.from.parse::<Mailbox>() {
+            return invalid("email.from", &error.to_string());
+        }
+        if self.email.transport == EmailTransport::File && self.email.directory.trim().is_empty() {
+            return invalid("email.directory", "must not be empty");
+        }
+        if self.email.transport == EmailTransport::Smtp && self.email.smtp_host.trim().is_empty() {
+            return invalid("email.smtp_host", "must not be empty");
+        }
+        if self.email.smtp_username.is_some() != self.email.smtp_password.is_some() {
+            return invalid(
+                "email.smtp_username",
+                "must be set with email.smtp_password",
+            );
+        }
+        if self.email.smtp_timeout_ms == 0 {
+            return invalid("email.smtp_timeout_ms", "must not be 0");
+        }
+        if self.webhooks.timeout_ms == 0 {
+            return invalid("webhooks.timeout_ms", "must not be 0");
+        }
+        self.check_batch_timeout("webhooks.timeout_ms", self.webhooks.timeout_ms)?;
+        self.check_batch_timeout("email.smtp_timeout_ms", self.email.smtp_timeout_ms)?;
+        if self.events.keep_alive_secs == 0 {
+            return invalid("events.keep_alive_secs", "must not be 0");
+        }
+
+        Ok(())
+    }
+
+    // the jobs of a batch, the emails and the webhook deliveries, are run one after the other,
+    // all within the lease of the batch
+    fn check_batch_timeout(&self, key: &'static str, timeout_ms: u64) -> Result<(), Error> {
+        let batch_timeout =
+            Duration::from_millis(timeout_ms).saturating_mul(self.jobs.batch_size.into());
+        if batch_timeout >= JOB_LEASE {
+            return Err(Error::Invalid(
+                key,
+                format!(
+                    "times jobs.batch_size must be less than the job lease of {}s",
+                    JOB_LEASE.as_secs()
+                ),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl DatabaseConfig {
+    pub const fn acquire_timeout(&self) -> Duration {
+        Duration::from_millis(self.acquire_timeout_ms)
+    }
+
+    pub fn app_connect_options(&self) -> Result<PgConnectOptions, sqlx::Error> {
+        let connect_options = self.url.as_deref().map_or_else(
+            || {
+                Ok(PgConnectOptions::new()
+                    .host(&self.host)
+                    .port(self.port)
+                    .database(&self.name)
+                    .username(&self.user)
+                    .password(&self.password))
+            },
+            PgConnectOptions::from_str,
+        )?;
+
+        Ok(connect_options.options([(
+            "idle_in_transaction_session_timeout",
+            self.idle_in_transaction_timeout_ms,
+        )]))
+    }
+
+    // same server than the app database, with the root credentials
+    pub fn root_connect_options(&self) -> Result<PgConnectOptions, sqlx::Error> {
+        Ok(self
+            .app_connect_options()?
+            .database(&self.root_name)
+            .username(&self.root_user)
+            .password(&self.root_password))
+    }
+}
+
+// Set the configuration for the whole process, must be called before the first config()
+pub fn init_config(config: Config) -> Result<(), Error> {
+    CONFIG.set(config).map_err(|_| Error::AlreadyInitialized)
+}
+
+// Without init_config, the configuration is loaded on first use
+#[cfg(not(test))]
+pub fn config() -> &'static Config {
+    CONFIG.get_or_init(|| Config::load(None).expect("Invalid configuration"))
+}
+
+// The tests work on the development database, which accepts the development token secret,
+// and store the attachments they upload out of the working tree
+#[cfg(test)]
+pub fn config() -> &'static Config {
+    CONFIG.get_or_init(|| {
+        let mut config = Config::read(None).expect("Invalid configuration");
+        config.database.mode = DatabaseMode::Dev;
+        config.attachments.directory = env::temp_dir()
+            .join(format!(
+                "rust_warp_postgres-attachments-{}",
+                std::process::id()
+            ))
+            .display()
+            .to_string();
+        config.validate().expect("Invalid configuration");
+        config
+    })
+}
+
+// What an environment variable must be, told when it's invalid. Its value is never told, it may be a secret
+trait EnvFormat: FromStr {
+    const FORMAT: &'static str;
+}
+
+impl EnvFormat for String {
+    const FORMAT: &'static str = "a text";
+}
+
+impl EnvFormat for bool {
+    const FORMAT: &'static str = "true or false";
+}
+
+impl EnvFormat for u16 {
+    const FORMAT: &'static str = "a number between 0 and 65535";
+}
+
+impl EnvFormat for u32 {
+    const FORMAT: &'static str = "a number between 0 and 4294967295";
+}
+
+impl EnvFormat for u64 {
+    const FORMAT: &'static str = "a positive number";
+}
+
+impl EnvFormat for IpAddr {
+    const FORMAT: &'static str = "an IP address";
+}
+
+impl EnvFormat for DatabaseMode {
+    const FORMAT: &'static str = "'dev' or 'prod'";
+}
+
+impl EnvFormat for CloseParentRule {
+    const FORMAT: &'static str = "'require' or 'cascade'";
+}
+
+impl EnvFormat for StorageBackend {
+    const FORMAT: &'static str = "'local'";
+}
+
+impl EnvFormat for EmailTransport {
+    const FORMAT: &'static str = "'smtp', 'file' or 'memory'";
+}
+
+impl EnvFormat for SmtpTls {
+    const FORMAT: &'static str = "'none', 'starttls' or 'tls'";
+}
+
+fn env_override<T: EnvFormat>(key: &'static str, field: &mut T) -> Result<(), Error> {
+    if let Some(value) = env_value(key)? {
+        *field = value
+            .parse()
+            .map_err(|_| Error::InvalidEnv(key, T::FORMAT))?;
+    }
+
+    Ok(())
+}
+
+fn env_value(key: &'static str) -> Result<Option<String>, Error> {
+    match env::var(key) {
+        Ok(value) => Ok(Some(value)),
+        Err(env::VarError::NotPresent) => Ok(None),
+        Err(env::VarError::NotUnicode(_)) => Err(Error::InvalidEnv(key, "valid unicode")),
+    }
+}
+
+#[derive(ThisError, Debug)]
+pub enum Error {
+    #[error("Config file '{0}' can't be read _ {1}")]
+    FileRead(String, #[source] std::io::Error),
+
+    #[error("Config file '{0}' is invalid _ {1}")]
+    FileParse(String, #[source] toml::de::Error),
+
+    // no value: it may be a secret, like TOKEN_SECRET
+    #[error("Environment variable {0} is invalid _ expected {1}")]
+    InvalidEnv(&'static str, &'static str),
+
+    #[error("Config {0} is invalid _ {1}")]
+    Invalid(&'static str, String),
+
+    #[error("Config already initialized")]
+    AlreadyInitialized,
+}
+
+#[cfg(test)]
+#[path = "../_tests/config.rs"]
+mod tests;
@@ -0,0 +1,73 @@
+use std::{
+    path::PathBuf,
+    sync::{Mutex, PoisonError},
+};
+
+use futures::future::BoxFuture;
+use lettre::message::Mailbox;
+use tokio::fs;
+
+use super::{message, Email, Error, Mailer};
+
+// Each email is written as a .eml file, like a mail server would receive it
+pub struct FileMailer {
+    directory: PathBuf,
+    from: Mailbox,
+}
+
+impl FileMailer {
+    pub fn new(directory: &str, from: Mailbox) -> Self {
+        Self {
+            directory: PathBuf::from(directory),
+            from,
+        }
+    }
+}
+
+impl Mailer for FileMailer {
+    fn send<'a>(&'a self, email: &'a Email) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            let message = message(&self.from, email)?;
+            fs::create_dir_all(&self.directory).await?;
+            let file = self.directory.join(format!("{}.eml", uuid::Uuid::new_v4()));
+            fs::write(file, message.formatted()).await?;
+            Ok(())
+        })
+    }
+}
+
+// The emails are only kept, in the order they were sent
+pub struct MemoryMailer {
+    from: Mailbox,
+    sent: Mutex<Vec<Email>>,
+}
+
+impl MemoryMailer {
+    pub const fn new(from: Mailbox) -> Self {
+        Self {
+            from,
+            sent: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn sent(&self) -> Vec<Email> {
+        self.sent
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+}
+
+impl Mailer for MemoryMailer {
+    fn send<'a>(&'a self, email: &'a Email) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            // same validation than the other transports
+            message(&self.from, email)?;
+            self.sent
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .push(email.clone());
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,133 @@
+use std::{str::FromStr, sync::Arc};
+
+use futures::future::BoxFuture;
+use lettre::message::{Mailbox, MultiPart};
+use lettre::Message;
+use serde_derive::Deserialize;
+use thiserror::Error as ThisError;
+
+use crate::config::EmailConfig;
+
+mod local;
+mod smtp;
+mod template;
+pub use local::{FileMailer, MemoryMailer};
+pub use smtp::SmtpMailer;
+pub use template::Template;
+
+// One email, its text and its html versions of the same content
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub text: String,
+    pub html: String,
+}
+
+// Sends the emails, the sender is the configured one
+pub trait Mailer: Send + Sync {
+    fn send<'a>(&'a self, email: &'a Email) -> BoxFuture<'a, Result<(), Error>>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EmailTransport {
+    Smtp,
+    // written in a directory, to be read without any mail server
+    #[default]
+    File,
+    // kept in memory, for the tests
+    Memory,
+}
+
+impl FromStr for EmailTransport {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "smtp" => Ok(Self::Smtp),
+            "file" => Ok(Self::File),
+            "memory" => Ok(Self::Memory),
+            other => Err(format!(
+                "unknown email transport '{other}', expected 'smtp', 'file' or 'memory'"
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpTls {
+    // plain text, for a local relay only
+    None,
+    // upgraded with STARTTLS, usually on port 587
+    #[default]
+    StartTls,
+    // implicit TLS, usually on port 465
+    Tls,
+}
+
+impl FromStr for SmtpTls {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "none" => Ok(Self::None),
+            "starttls" => Ok(Self::StartTls),
+            "tls" => Ok(Self::Tls),
+            other => Err(format!(
+                "unknown smtp tls '{other}', expected 'none', 'starttls' or 'tls'"
+            )),
+        }
+    }
+}
+
+pub fn mailer_from_config(email_config: &EmailConfig) -> Result<Arc<dyn Mailer>, Error> {
+    let from: Mailbox = email_config
+        .from
+        .parse()
+        .map_err(|_| Error::InvalidAddress(email_config.from.clone()))?;
+
+    Ok(match email_config.transport {
+        EmailTransport::Smtp => Arc::new(SmtpMailer::new(email_config, from)?),
+        EmailTransport::File => Arc::new(FileMailer::new(&email_config.directory, from)),
+        EmailTransport::Memory => Arc::new(MemoryMailer::new(from)),
+    })
+}
+
+// The MIME message, with both versions of the body
+fn message(from: &Mailbox, email: &Email) -> Result<Message, Error> {
+    let to: Mailbox = email
+        .to
+        .parse()
+        .map_err(|_| Error::InvalidAddress(email.to.clone()))?;
+
+    Ok(Message::builder()
+        .from(from.clone())
+        .to(to)
+        .subject(&email.subject)
+        .multipart(MultiPart::alternative_plain_html(
+            email.text.clone(),
+            email.html.clone(),
+        ))?)
+}
+
+#[allow(clippy::enum_variant_names)]
+#[derive(ThisError, Debug)]
+pub enum Error {
+    #[error("Invalid email address '{0}'")]
+    InvalidAddress(String),
+
+    #[error("Email message failed _ {0}")]
+    MessageFailed(#[from] lettre::error::Error),
+
+    #[error("Smtp failed _ {0}")]
+    SmtpFailed(#[from] lettre::transport::smtp::Error),
+
+    #[error(transparent)]
+    IOError(#[from] std::io::Error),
+}
+
+#[cfg(test)]
+#[path = "../_tests/email.rs"]
+mod tests;
@@ -0,0 +1,51 @@
+use std::time::Duration;
+
+use futures::future::BoxFuture;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Tokio1Executor};
+
+use crate::config::EmailConfig;
+
+use super::{message, Email, Error, Mailer, SmtpTls};
+
+// The emails are sent to the configured relay, the connections are pooled
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl SmtpMailer {
+    pub fn new(email_config: &EmailConfig, from: Mailbox) -> Result<Self, Error> {
+        let host = email_config.smtp_host.as_str();
+        let builder = match email_config.smtp_tls {
+            SmtpTls::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(host),
+            SmtpTls::StartTls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)?,
+            SmtpTls::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(host)?,
+        }
+        .port(email_config.smtp_port)
+        .timeout(Some(Duration::from_millis(email_config.smtp_timeout_ms)));
+
+        let builder = match (&email_config.smtp_username, &email_config.smtp_password) {
+            (Some(username), Some(password)) => {
+                builder.credentials(Credentials::new(username.clone(), password.clone()))
+            }
+            _ => builder,
+        };
+
+        Ok(Self {
+            transport: builder.build(),
+            from,
+        })
+    }
+}
+
+impl Mailer for SmtpMailer {
+    fn send<'a>(&'a self, email: &'a Email) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            let message = message(&self.from, email)?;
+            self.transport.send(message).await?;
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,63 @@
+use super::Email;
+
+// The subject and the bodies of an email, where {{name}} is replaced by the value of name.
+// The values are HTML escaped in the html body, an unknown name is left as is
+pub struct Template {
+    pub subject: &'static str,
+    pub text: &'static str,
+    pub html: &'static str,
+}
+
+impl Template {
+    pub fn render(&self, to: &str, values: &[(&str, &str)]) -> Email {
+        // a header is a single line
+        let subject = substitute(self.subject, values, |value| {
+            value.replace(['\r', '\n'], " ")
+        });
+
+        Email {
+            to: to.to_string(),
+            subject,
+            text: substitute(self.text, values, str::to_string),
+            html: substitute(self.html, values, escape_html),
+        }
+    }
+}
+
+fn substitute(template: &str, values: &[(&str, &str)], encode: impl Fn(&str) -> String) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        rendered.push_str(&rest[..start]);
+        let after_start = &rest[start + 2..];
+        let Some(end) = after_start.find("}}") else {
+            rendered.push_str(&rest[start..]);
+            return rendered;
+        };
+        let name = after_start[..end].trim();
+        match values.iter().find(|(key, _)| *key == name) {
+            Some((_, value)) => rendered.push_str(&encode(value)),
+            None => rendered.push_str(&rest[start..start + 2 + end + 2]),
+        }
+        rest = &after_start[end + 2..];
+    }
+    rendered.push_str(rest);
+
+    rendered
+}
+
+pub fn escape_html(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for character in value.chars() {
+        match character {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            other => escaped.push(other),
+        }
+    }
+
+    escaped
+}
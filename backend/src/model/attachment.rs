@@ -0,0 +1,283 @@
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
+use serde_derive::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::{Postgres, Transaction};
+
+use crate::config::config;
+use crate::model;
+use crate::model::{db::PostgresDatabase, todo::ModelAccessController, FieldError};
+use crate::security::UserContext;
+use crate::storage::{self, storage, ByteStream};
+
+// The metadata of a file attached to a todo, its content is in the storage
+#[derive(sqlx::FromRow, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Attachment {
+    pub id: i64,
+    pub todo_id: i64,
+    // the uploader
+    pub cid: i64,
+    pub ctime: DateTime<Utc>,
+    pub filename: String,
+    pub content_type: String,
+    pub size: i64,
+    pub sha256: String,
+    #[serde(skip)]
+    pub storage_key: String,
+}
+
+const ATTACHMENT_COLUMNS: &str =
+    "id, todo_id, cid, ctime, filename, content_type, size, sha256, storage_key";
+
+// what the client tells about the uploaded file
+#[allow(clippy::module_name_repetitions)]
+#[derive(Debug, Clone, Default)]
+pub struct NewAttachment {
+    pub filename: Option<String>,
+    pub content_type: Option<String>,
+}
+
+// same as the filename and content_type columns, VARCHAR(255)
+const FILENAME_MAX_LEN: usize = 255;
+const DEFAULT_CONTENT_TYPE: &str = "application/octet-stream";
+
+impl NewAttachment {
+    // the file name without its client path, and a content type
+    fn validated(self) -> Result<(String, String), model::Error> {
+        let filename = self
+            .filename
+            .as_deref()
+            .and_then(|filename| filename.rsplit(['/', '\\']).next())
+            .map(str::trim)
+            .unwrap_or_default()
+            .to_string();
+        let content_type = self
+            .content_type
+            .filter(|content_type| !content_type.trim().is_empty())
+            .unwrap_or_else(|| String::from(DEFAULT_CONTENT_TYPE));
+
+        let mut errors = Vec::new();
+        if filename.is_empty() {
+            errors.push(FieldError::new("filename", "must not be empty"));
+        } else if filename.chars().count() > FILENAME_MAX_LEN || filename.contains(char::is_control)
+        {
+            errors.push(FieldError::new(
+                "filename",
+                format!("must be at most {FILENAME_MAX_LEN} printable characters"),
+            ));
+        }
+        if content_type.len() > FILENAME_MAX_LEN || !content_type.is_ascii() {
+            errors.push(FieldError::new(
+                "content_type",
+                "is not a valid content type",
+            ));
+        }
+
+        if errors.is_empty() {
+            Ok((filename, content_type))
+        } else {
+            Err(model::Error::ValidationFailed(errors))
+        }
+    }
+}
+
+#[allow(clippy::module_name_repetitions)]
+pub struct AttachmentModelAccessController;
+impl AttachmentModelAccessController {
+    pub async fn list(
+        database: &PostgresDatabase,
+        utx: &UserContext,
+        todo_id: i64,
+    ) -> Result<Vec<Attachment>, model::Error> {
+        // 404 for a todo the user can't see, rather than an empty list
+        ModelAccessController::get(database, utx, todo_id).await?;
+
+        let sql = format!(
+            "SELECT {ATTACHMENT_COLUMNS} FROM attachment WHERE todo_id = $1 ORDER BY ctime, id"
+        );
+
+        let attachments = sqlx::query_as::<_, Attachment>(&sql)
+            .bind(todo_id)
+            .fetch_all(database)
+            .await?;
+
+        Ok(attachments)
+    }
+
+    // The content is stored while it's hashed and measured, then its metadata is inserted
+    pub async fn create(
+        database: &PostgresDatabase,
+        utx: &UserContext,
+        todo_id: i64,
+        data: NewAttachment,
+        content: ByteStream,
+    ) -> Result<Attachment, model::Error> {
+        let (filename, content_type) = data.validated()?;
+        ModelAccessController::get_for_edit(database, utx, todo_id).await?;
+
+        let storage_key = uuid::Uuid::new_v4().to_string();
+        let max_size = config().attachments.max_size;
+        let digest = Arc::new(Mutex::new(ContentDigest::default()));
+        let measured = {
+            let digest = Arc::clone(&digest);
+            content
+                .map(move |chunk| {
+                    let chunk = chunk?;
+                    lock(&digest).update(&chunk, max_size)?;
+                    Ok(chunk)
+                })
+                .boxed()
+        };
+        storage().put(&storage_key, measured).await?;
+
+        let ContentDigest { hasher, size } = lock(&digest).clone();
+
+        let sql = format!(
+            "INSERT INTO attachment (todo_id, cid, filename, content_type, size, sha256, storage_key) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING {ATTACHMENT_COLUMNS}"
+        );
+
+        let attachment = sqlx::query_as::<_, Attachment>(&sql)
+            .bind(todo_id)
+            .bind(utx.user_id)
+            .bind(filename)
+            .bind(content_type)
+            .bind(i64::try_from(size).unwrap_or(i64::MAX))
+            .bind(format!("{:x}", hasher.finalize()))
+            .bind(&storage_key)
+            .fetch_one(database)
+            .await;
+
+        // without its metadata, the content would never be found again
+        if attachment.is_err() {
+            delete_contents(&[storage_key]).await;
+        }
+
+        Ok(attachment?)
+    }
+
+    pub async fn get(
+        database: &PostgresDatabase,
+        utx: &UserContext,
+        todo_id: i64,
+        id: i64,
+    ) -> Result<Attachment, model::Error> {
+        ModelAccessController::get(database, utx, todo_id).await?;
+
+        let sql =
+            format!("SELECT {ATTACHMENT_COLUMNS} FROM attachment WHERE id = $1 AND todo_id = $2");
+
+        let attachment = sqlx::query_as::<_, Attachment>(&sql)
+            .bind(id)
+            .bind(todo_id)
+            .fetch_one(database)
+            .await;
+
+        handle_fetch_one_result(attachment, id)
+    }
+
+    // the metadata and the content stream, to download it
+    pub async fn get_content(
+        database: &PostgresDatabase,
+        utx: &UserContext,
+        todo_id: i64,
+        id: i64,
+    ) -> Result<(Attachment, ByteStream), model::Error> {
+        let attachment = Self::get(database, utx, todo_id, id).await?;
+        let content = storage().get(&attachment.storage_key).await?;
+
+        Ok((attachment, content))
+    }
+
+    pub async fn delete(
+        database: &PostgresDatabase,
+        utx: &UserContext,
+        todo_id: i64,
+        id: i64,
+    ) -> Result<Attachment, model::Error> {
+        ModelAccessController::get_for_edit(database, utx, todo_id).await?;
+
+        let sql = format!(
+            "DELETE FROM attachment WHERE id = $1 AND todo_id = $2 RETURNING {ATTACHMENT_COLUMNS}"
+        );
+
+        let attachment = sqlx::query_as::<_, Attachment>(&sql)
+            .bind(id)
+            .bind(todo_id)
+            .fetch_one(database)
+            .await;
+        let attachment = handle_fetch_one_result(attachment, id)?;
+
+        delete_contents(std::slice::from_ref(&attachment.storage_key)).await;
+
+        Ok(attachment)
+    }
+}
+
+// Utils
+
+// the hash and the size of the content, chunk after chunk
+#[derive(Clone, Default)]
+struct ContentDigest {
+    hasher: Sha256,
+    size: u64,
+}
+
+impl ContentDigest {
+    fn update(&mut self, chunk: &[u8], max_size: u64) -> Result<(), storage::Error> {
+        self.size += chunk.len() as u64;
+        if self.size > max_size {
+            return Err(storage::Error::TooLarge(max_size));
+        }
+        self.hasher.update(chunk);
+        Ok(())
+    }
+}
+
+// a panic while hashing leaves nothing inconsistent
+fn lock(digest: &Mutex<ContentDigest>) -> MutexGuard<'_, ContentDigest> {
+    digest.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+// The storage keys of the attachments of a todo and of its subtasks, all deleted with it
+pub async fn subtree_storage_keys(
+    transaction: &mut Transaction<'_, Postgres>,
+    todo_id: i64,
+) -> Result<Vec<String>, model::Error> {
+    let keys = sqlx::query_scalar(
+        "WITH RECURSIVE subtree AS ( \
+           SELECT id FROM todo WHERE id = $1 \
+           UNION SELECT todo.id FROM todo JOIN subtree ON todo.parent_id = subtree.id \
+         ) SELECT storage_key FROM attachment WHERE todo_id IN (SELECT id FROM subtree)",
+    )
+    .bind(todo_id)
+    .fetch_all(&mut **transaction)
+    .await?;
+
+    Ok(keys)
+}
+
+// Once their metadata is gone. A failure only leaves an orphan file, so it's logged, not returned
+pub async fn delete_contents(storage_keys: &[String]) {
+    for storage_key in storage_keys {
+        if let Err(error) = storage().delete(storage_key).await {
+            println!("ERROR  - attachment content {storage_key} not deleted - {error}");
+        }
+    }
+}
+
+fn handle_fetch_one_result(
+    result: Result<Attachment, sqlx::Error>,
+    id: i64,
+) -> Result<Attachment, model::Error> {
+    result.map_err(|sqlx_error| match sqlx_error {
+        sqlx::Error::RowNotFound => model::Error::EntityNotFound("attachment", id.to_string()),
+        other => model::Error::SqlxError(other),
+    })
+}
+
+#[cfg(test)]
+#[path = "../_tests/model_attachment.rs"]
+mod tests;
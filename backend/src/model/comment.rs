@@ -0,0 +1,213 @@
+use chrono::{DateTime, Utc};
+use serde_derive::{Deserialize, Serialize};
+
+use crate::model;
+use crate::model::notification::{self, Notification, NotificationEvent};
+use crate::model::{
+    db::PostgresDatabase,
+    todo::{self, ModelAccessController},
+    FieldError,
+};
+use crate::security::UserContext;
+
+#[derive(sqlx::FromRow, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Comment {
+    pub id: i64,
+    pub todo_id: i64,
+    // the author, the only one who can edit or delete it
+    pub cid: i64,
+    pub body: String,
+    pub ctime: DateTime<Utc>,
+    pub edited_at: Option<DateTime<Utc>>,
+}
+
+const COMMENT_COLUMNS: &str = "id, todo_id, cid, body, ctime, edited_at";
+
+// the client writable fields
+#[allow(clippy::module_name_repetitions)]
+#[derive(Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PartialComment {
+    pub body: Option<String>,
+}
+
+const BODY_MAX_LEN: usize = 10_000;
+
+impl PartialComment {
+    // the body, required on create as on update since it's the only field
+    fn validated_body(self) -> Result<String, model::Error> {
+        let invalid = |message: String| {
+            model::Error::ValidationFailed(vec![FieldError::new("body", message)])
+        };
+
+        match self.body {
+            None => Err(invalid(String::from("is required"))),
+            Some(body) if body.trim().is_empty() => Err(invalid(String::from("must not be empty"))),
+            Some(body) if body.chars().count() > BODY_MAX_LEN => Err(invalid(format!(
+                "must be at most {BODY_MAX_LEN} characters"
+            ))),
+            Some(body) => Ok(body),
+        }
+    }
+}
+
+#[allow(clippy::module_name_repetitions)]
+pub struct CommentModelAccessController;
+impl CommentModelAccessController {
+    // oldest first, like a conversation
+    pub async fn list(
+        database: &PostgresDatabase,
+        utx: &UserContext,
+        todo_id: i64,
+    ) -> Result<Vec<Comment>, model::Error> {
+        // 404 for a todo the user can't see, rather than an empty list
+        ModelAccessController::get(database, utx, todo_id).await?;
+
+        let sql =
+            format!("SELECT {COMMENT_COLUMNS} FROM comment WHERE todo_id = $1 ORDER BY ctime, id");
+
+        let comments = sqlx::query_as::<_, Comment>(&sql)
+            .bind(todo_id)
+            .fetch_all(database)
+            .await?;
+
+        Ok(comments)
+    }
+
+    pub async fn create(
+        database: &PostgresDatabase,
+        utx: &UserContext,
+        todo_id: i64,
+        data: PartialComment,
+    ) -> Result<Comment, model::Error> {
+        let body = data.validated_body()?;
+        let todo = ModelAccessController::get(database, utx, todo_id).await?;
+
+        // the comment and its notifications are created together
+        let mut transaction = database.begin().await?;
+
+        let sql = format!(
+            "INSERT INTO comment (todo_id, cid, body) VALUES ($1, $2, $3) RETURNING {COMMENT_COLUMNS}"
+        );
+
+        let comment = sqlx::query_as::<_, Comment>(&sql)
+            .bind(todo_id)
+            .bind(utx.user_id)
+            .bind(body)
+            .fetch_one(&mut *transaction)
+            .await?;
+
+        for user_id in notification::recipients(todo.cid, todo.assignee_id, Some(utx.user_id)) {
+            let notification = Notification {
+                event: NotificationEvent::Commented,
+                user_id,
+                todo_id,
+                comment_id: Some(comment.id),
+            };
+            notification::notify(&mut *transaction, &notification, None).await?;
+        }
+        todo::touch_todo(&mut transaction, todo_id).await?;
+
+        transaction.commit().await?;
+
+        Ok(comment)
+    }
+
+    pub async fn get(
+        database: &PostgresDatabase,
+        utx: &UserContext,
+        todo_id: i64,
+        id: i64,
+    ) -> Result<Comment, model::Error> {
+        ModelAccessController::get(database, utx, todo_id).await?;
+
+        let sql = format!("SELECT {COMMENT_COLUMNS} FROM comment WHERE id = $1 AND todo_id = $2");
+
+        let comment = sqlx::query_as::<_, Comment>(&sql)
+            .bind(id)
+            .bind(todo_id)
+            .fetch_one(database)
+            .await;
+
+        handle_fetch_one_result(comment, id)
+    }
+
+    pub async fn update(
+        database: &PostgresDatabase,
+        utx: &UserContext,
+        todo_id: i64,
+        id: i64,
+        data: PartialComment,
+    ) -> Result<Comment, model::Error> {
+        let body = data.validated_body()?;
+        check_author(database, utx, todo_id, id).await?;
+
+        let sql = format!(
+            "UPDATE comment SET body = $2, edited_at = NOW() WHERE id = $1 RETURNING {COMMENT_COLUMNS}"
+        );
+
+        let comment = sqlx::query_as::<_, Comment>(&sql)
+            .bind(id)
+            .bind(body)
+            .fetch_one(database)
+            .await;
+
+        handle_fetch_one_result(comment, id)
+    }
+
+    pub async fn delete(
+        database: &PostgresDatabase,
+        utx: &UserContext,
+        todo_id: i64,
+        id: i64,
+    ) -> Result<Comment, model::Error> {
+        check_author(database, utx, todo_id, id).await?;
+
+        let mut transaction = database.begin().await?;
+
+        let sql = format!("DELETE FROM comment WHERE id = $1 RETURNING {COMMENT_COLUMNS}");
+
+        let comment = sqlx::query_as::<_, Comment>(&sql)
+            .bind(id)
+            .fetch_one(&mut *transaction)
+            .await;
+        let comment = handle_fetch_one_result(comment, id)?;
+        todo::touch_todo(&mut transaction, todo_id).await?;
+
+        transaction.commit().await?;
+
+        Ok(comment)
+    }
+}
+
+// Utils
+
+// a comment of a todo the user can see, but written by someone else, is denied rather than not found
+async fn check_author(
+    database: &PostgresDatabase,
+    utx: &UserContext,
+    todo_id: i64,
+    id: i64,
+) -> Result<(), model::Error> {
+    let comment = CommentModelAccessController::get(database, utx, todo_id, id).await?;
+
+    if comment.cid == utx.user_id {
+        Ok(())
+    } else {
+        Err(model::Error::AccessDenied("comment", id.to_string()))
+    }
+}
+
+fn handle_fetch_one_result(
+    result: Result<Comment, sqlx::Error>,
+    id: i64,
+) -> Result<Comment, model::Error> {
+    result.map_err(|sqlx_error| match sqlx_error {
+        sqlx::Error::RowNotFound => model::Error::EntityNotFound("comment", id.to_string()),
+        other => model::Error::SqlxError(other),
+    })
+}
+
+#[cfg(test)]
+#[path = "../_tests/model_comment.rs"]
+mod tests;
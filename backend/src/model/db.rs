@@ -1,111 +1,143 @@
-use std::{fs, path::PathBuf, time::Duration};
+use std::{fs, path::Path, path::PathBuf, str::FromStr};
 
-use sqlx::{postgres::PgPoolOptions, Pool, Postgres};
+use serde_derive::Deserialize;
+use sqlx::{
+    postgres::{PgConnectOptions, PgPoolOptions},
+    Pool, Postgres,
+};
+
+use crate::config::{config, DatabaseConfig};
+use crate::model::{self, migration};
 
 pub type PostgresDatabase = Pool<Postgres>;
 
-// Refactor to use .env variables
-const POSTGRES_HOST: &str = "localhost";
-const POSTGRES_ROOT_DATABASE: &str = "postgres";
-const POSTGRES_ROOT_USER: &str = "postgres";
-const POSTGRES_ROOT_PASSWORD: &str = "postgres";
+// relative to the configured sql directory
+const SQL_MIGRATIONS_DIRECTORY: &str = "migrations/";
+const SQL_DEV_SEED: &str = "02-dev-seed.sql";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DatabaseMode {
+    // Drops and recreates the database, then seeds it. Development and tests only
+    Dev,
+    // Only applies the pending migrations
+    Prod,
+}
 
-// Refactor to use .env variables
-const POSTGRES_APP_DATABASE: &str = "app";
-const POSTGRES_APP_USER: &str = "app";
-const POSTGRES_APP_PASSWORD: &str = "app";
-const POSTGRES_APP_MAX_CONNECTIONS: u32 = 5;
+impl FromStr for DatabaseMode {
+    type Err = String;
 
-// Refactor to use .env variables
-const SQL_DIRECTORY: &str = "sql/";
-const SQL_RECREATE: &str = "sql/00-recreate-db.sql";
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "dev" => Ok(Self::Dev),
+            "prod" => Ok(Self::Prod),
+            other => Err(format!(
+                "unknown database mode '{other}', expected 'dev' or 'prod'"
+            )),
+        }
+    }
+}
+
+pub async fn initialize_database(mode: DatabaseMode) -> Result<PostgresDatabase, model::Error> {
+    let db_config = &config().database;
 
-pub async fn initialize_database() -> Result<PostgresDatabase, sqlx::Error> {
     // -- Create the database with POSTGRES_ROOT --> in development only
-    {
-        let root_db = new_database_pool(
-            POSTGRES_HOST,
-            POSTGRES_ROOT_DATABASE,
-            POSTGRES_ROOT_USER,
-            POSTGRES_ROOT_PASSWORD,
-            1,
-        )
-        .await?;
-        execute_sql_file(&root_db, SQL_RECREATE).await?;
+    if mode == DatabaseMode::Dev {
+        let root_db = new_database_pool(db_config.root_connect_options()?, 1, db_config).await?;
+        recreate_database(&root_db, db_config).await?;
     }
 
-    let app_database = new_database_pool(
-        POSTGRES_HOST,
-        POSTGRES_APP_DATABASE,
-        POSTGRES_APP_USER,
-        POSTGRES_APP_PASSWORD,
-        POSTGRES_APP_MAX_CONNECTIONS,
-    )
-    .await?;
-
-    let mut paths: Vec<PathBuf> = fs::read_dir(SQL_DIRECTORY)?
-        .filter_map(|element| element.ok().map(|e| e.path()))
-        .collect::<Vec<PathBuf>>();
+    let app_database = connect_database().await?;
 
-    paths.sort();
+    migration::run_migrations(&app_database, &migrations_directory()).await?;
 
-    for path in paths {
-        if let Some(path) = path.to_str() {
-            if std::path::Path::new(path)
-                .extension()
-                .map_or(false, |ext| ext.eq_ignore_ascii_case("sql")) && path != SQL_RECREATE {
-                execute_sql_file(&app_database, path).await?;
-            }
-        }
+    if mode == DatabaseMode::Dev {
+        seed_database(&app_database).await?;
     }
 
     // returning the app db
-    new_database_pool(
-        POSTGRES_HOST,
-        POSTGRES_APP_DATABASE,
-        POSTGRES_APP_USER,
-        POSTGRES_APP_PASSWORD,
-        POSTGRES_APP_MAX_CONNECTIONS,
+    connect_database().await
+}
+
+// The app database pool, without any migration
+pub async fn connect_database() -> Result<PostgresDatabase, model::Error> {
+    let db_config = &config().database;
+
+    Ok(new_database_pool(
+        db_config.app_connect_options()?,
+        db_config.max_connections,
+        db_config,
     )
-    .await
+    .await?)
+}
+
+// The development data, on an already migrated database
+pub async fn seed_database(database: &PostgresDatabase) -> Result<(), model::Error> {
+    let sql_directory = Path::new(&config().database.sql_directory);
+
+    execute_sql_file(database, &sql_directory.join(SQL_DEV_SEED)).await
+}
+
+pub fn migrations_directory() -> PathBuf {
+    Path::new(&config().database.sql_directory).join(SQL_MIGRATIONS_DIRECTORY)
 }
 
 async fn new_database_pool(
-    host: &str,
-    database: &str,
-    user: &str,
-    password: &str,
+    connect_options: PgConnectOptions,
     max_connections: u32,
+    db_config: &DatabaseConfig,
 ) -> Result<PostgresDatabase, sqlx::Error> {
-    let connection_string = format!("postgres://{user}:{password}@{host}/{database}");
-
     PgPoolOptions::new()
         .max_connections(max_connections)
-        .acquire_timeout(Duration::from_millis(500))
-        .connect(&connection_string)
+        .acquire_timeout(db_config.acquire_timeout())
+        .connect_with(connect_options)
         .await
 }
 
-async fn execute_sql_file(database: &PostgresDatabase, file: &str) -> Result<(), sqlx::Error> {
-    // Read the file
-    let content = fs::read_to_string(file).map_err(|error| {
-        println!("Error reading: {file}, (cause :{error:?}");
-        error
-    })?;
+// Runs the whole file at once (multiple statements allowed), and fails on the first error
+async fn execute_sql_file(database: &PostgresDatabase, file: &Path) -> Result<(), model::Error> {
+    let content = fs::read_to_string(file)?;
 
-    // comments in the sql seed files will break this code
-    let sqls_seed_files_statements: Vec<&str> = content.split(';').collect();
+    sqlx::raw_sql(&content)
+        .execute(database)
+        .await
+        .map_err(|error| model::Error::SqlFileFailed(file.display().to_string(), error))?;
 
-    for sql in sqls_seed_files_statements {
-        match sqlx::query(sql).execute(database).await {
-            Ok(_) => (),
-            Err(error) => println!("Error seeding database, (cause :{error:?}",),
-        }
+    Ok(())
+}
+
+// The configured database and user, dropped and created again.
+// DROP/CREATE DATABASE can't run in a multi statements query (implicit transaction),
+// so they are executed one by one
+async fn recreate_database(
+    root_db: &PostgresDatabase,
+    db_config: &DatabaseConfig,
+) -> Result<(), model::Error> {
+    let name = quote_identifier(&db_config.name);
+    let user = quote_identifier(&db_config.user);
+    let password = quote_literal(&db_config.password);
+
+    let statements = [
+        format!("DROP DATABASE IF EXISTS {name} WITH (FORCE)"),
+        format!("DROP USER IF EXISTS {user}"),
+        format!("CREATE USER {user} WITH PASSWORD {password}"),
+        format!("CREATE DATABASE {name} OWNER {user} ENCODING = 'UTF-8'"),
+    ];
+    for sql in statements {
+        sqlx::raw_sql(&sql).execute(root_db).await?;
     }
 
     Ok(())
 }
 
+fn quote_identifier(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
+
+fn quote_literal(literal: &str) -> String {
+    format!("'{}'", literal.replace('\'', "''"))
+}
+
 #[cfg(test)]
 #[path = "../_tests/model_db.rs"]
 mod tests;
@@ -0,0 +1,350 @@
+use std::{sync::Arc, time::Duration};
+
+use chrono::{DateTime, Utc};
+use serde_derive::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sqlx::{postgres::PgListener, Postgres, Transaction};
+use tokio::sync::{broadcast, OnceCell};
+
+use crate::model;
+use crate::model::{db::PostgresDatabase, todo::Todo, webhook};
+
+// the Postgres channel the changes are notified on, for all the servers
+pub const TODO_EVENTS_CHANNEL: &str = "todo_events";
+// the changes a slow subscriber can be late of, before it misses some
+const HUB_CAPACITY: usize = 1024;
+// before listening again, once the connection is lost
+const LISTEN_RETRY: Duration = Duration::from_secs(1);
+
+// A change of a todo made through the ModelAccessController
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TodoEvent {
+    #[serde(rename = "todo.created")]
+    Created,
+    #[serde(rename = "todo.updated")]
+    Updated,
+    #[serde(rename = "todo.deleted")]
+    Deleted,
+}
+
+impl TodoEvent {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Created => "todo.created",
+            Self::Updated => "todo.updated",
+            Self::Deleted => "todo.deleted",
+        }
+    }
+}
+
+// The NOTIFY payload, small enough for its 8000 bytes limit whatever the todo, the todo is in the change log.
+// The users who can see the todo are the ones of the change, a deleted todo can't be checked later
+#[allow(clippy::module_name_repetitions)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TodoChange {
+    // the id of the change log entry
+    pub id: i64,
+    pub event: TodoEvent,
+    pub todo_id: i64,
+    pub user_ids: Vec<i64>,
+}
+
+// Tells the change to whoever listens to it, in the transaction of the change
+// so a rolled back change is never published. The todo is as it is after the change
+pub async fn publish(
+    transaction: &mut Transaction<'_, Postgres>,
+    event: TodoEvent,
+    todo: &Todo,
+) -> Result<(), model::Error> {
+    let user_ids = visible_to(transaction, todo).await?;
+
+    publish_to(transaction, event, todo, user_ids).await
+}
+
+// publish, to the users given, the ones who could see a deleted todo before its shares went with it
+pub async fn publish_to(
+    transaction: &mut Transaction<'_, Postgres>,
+    event: TodoEvent,
+    todo: &Todo,
+    user_ids: Vec<i64>,
+) -> Result<(), model::Error> {
+    webhook::enqueue_todo_event(transaction, event, todo).await?;
+
+    log_change(transaction, event, todo, user_ids).await
+}
+
+// A Deleted change to the user the todo isn't visible to anymore, while it's still there for the others.
+// No webhook, the todo is not deleted
+pub async fn publish_revoked(
+    transaction: &mut Transaction<'_, Postgres>,
+    todo: &Todo,
+    user_id: i64,
+) -> Result<(), model::Error> {
+    log_change(transaction, TodoEvent::Deleted, todo, vec![user_id]).await
+}
+
+// For the previous assignee, the todo is gone, unless they still see it otherwise
+pub async fn publish_unassigned(
+    transaction: &mut Transaction<'_, Postgres>,
+    todo: &Todo,
+    user_id: i64,
+) -> Result<(), model::Error> {
+    if visible_to(transaction, todo).await?.contains(&user_id) {
+        return Ok(());
+    }
+
+    publish_revoked(transaction, todo, user_id).await
+}
+
+// The changes notified by all the servers, to the subscribers of this one, read once from the change log
+// for all of them. The first subscriber starts listening, on a connection of the pool it keeps
+#[allow(clippy::module_name_repetitions)]
+pub struct EventHub {
+    database: PostgresDatabase,
+    sender: OnceCell<broadcast::Sender<Arc<ChangeLogEntry>>>,
+}
+
+impl EventHub {
+    pub fn new(database: PostgresDatabase) -> Self {
+        Self {
+            database,
+            sender: OnceCell::new(),
+        }
+    }
+
+    // the changes from now on, a receiver lagging HUB_CAPACITY changes behind misses the oldest
+    pub async fn subscribe(
+        &self,
+    ) -> Result<broadcast::Receiver<Arc<ChangeLogEntry>>, model::Error> {
+        let sender = self
+            .sender
+            .get_or_try_init(|| async {
+                let mut listener = PgListener::connect_with(&self.database).await?;
+                listener.listen(TODO_EVENTS_CHANNEL).await?;
+
+                let (sender, _) = broadcast::channel(HUB_CAPACITY);
+                tokio::spawn(forward(listener, self.database.clone(), sender.clone()));
+
+                Ok::<_, model::Error>(sender)
+            })
+            .await?;
+
+        Ok(sender.subscribe())
+    }
+}
+
+// A change, as the event stream clients get it
+#[derive(sqlx::FromRow, Debug, Clone, Serialize, Deserialize)]
+pub struct ChangeLogEntry {
+    pub id: i64,
+    // the transaction of the change, see ChangeLog::after
+    pub xid: i64,
+    // one of the TodoEvent, like "todo.created"
+    pub event: String,
+    pub todo_id: i64,
+    // the users who could see the todo when it changed
+    pub user_ids: Vec<i64>,
+    // the todo after the change, before it for a deletion
+    pub data: Value,
+    pub ctime: DateTime<Utc>,
+}
+
+const ENTRY_COLUMNS: &str = "id, xid, event, todo_id, user_ids, data, ctime";
+
+// the changes of the transactions older than all the running ones, so committed, or never will be.
+// The oldest running transaction is the one of the whole server, not only of this database nor of the app:
+// any session left idle in a transaction stalls every reader until it ends. The app sessions are closed after
+// database.idle_in_transaction_timeout_ms, the other clients of the server need a timeout of their own
+const READABLE: &str = "xid < pg_snapshot_xmin(pg_current_snapshot())::TEXT::BIGINT";
+
+// Where a change is in the change log, ordered by transaction, then by id
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct LogPosition {
+    pub xid: i64,
+    pub id: i64,
+}
+
+impl ChangeLogEntry {
+    pub const fn position(&self) -> LogPosition {
+        LogPosition {
+            xid: self.xid,
+            id: self.id,
+        }
+    }
+}
+
+// The persisted changes, kept for the retention time so a client can resume after the last one it got
+pub struct ChangeLog;
+impl ChangeLog {
+    // The changes after the position, of the todos the user could see when they changed, oldest first.
+    // The ids are given at the insert, not at the commit: the change 11 can be committed before the 10, so reading
+    // after the 11 would skip the 10 for good. Instead, only the changes of the transactions older than all the
+    // running ones are read, in the order of their transactions: a change still to come is of a running
+    // transaction, after every change read. The ones of a long transaction hold the others back until its end.
+    // Like the webhooks, unlike the todo list, the todos of the archived projects are kept: their data tells it
+    pub async fn after(
+        database: &PostgresDatabase,
+        user_id: i64,
+        after: LogPosition,
+        limit: i64,
+    ) -> Result<Vec<ChangeLogEntry>, model::Error> {
+        let sql = format!(
+            "SELECT {ENTRY_COLUMNS} FROM todo_change \
+             WHERE user_ids @> ARRAY[$1]::BIGINT[] AND (xid, id) > ($2, $3) AND {READABLE} \
+             ORDER BY xid, id LIMIT $4"
+        );
+
+        let entries = sqlx::query_as::<_, ChangeLogEntry>(&sql)
+            .bind(user_id)
+            .bind(after.xid)
+            .bind(after.id)
+            .bind(limit)
+            .fetch_all(database)
+            .await?;
+
+        Ok(entries)
+    }
+
+    // None once purged, the todo is the one the change was published with
+    pub async fn get(
+        database: &PostgresDatabase,
+        id: i64,
+    ) -> Result<Option<ChangeLogEntry>, model::Error> {
+        let sql = format!("SELECT {ENTRY_COLUMNS} FROM todo_change WHERE id = $1");
+
+        let entry = sqlx::query_as::<_, ChangeLogEntry>(&sql)
+            .bind(id)
+            .fetch_optional(database)
+            .await?;
+
+        Ok(entry)
+    }
+
+    // the position of the change, where a client resumes. The start of the log once it's purged
+    pub async fn position(
+        database: &PostgresDatabase,
+        id: i64,
+    ) -> Result<LogPosition, model::Error> {
+        let xid: Option<i64> = sqlx::query_scalar("SELECT xid FROM todo_change WHERE id = $1")
+            .bind(id)
+            .fetch_optional(database)
+            .await?;
+
+        Ok(xid.map_or_else(LogPosition::default, |xid| LogPosition { xid, id }))
+    }
+
+    // the position of the last change readable, the start of the log when none, where a new client starts
+    pub async fn last_position(database: &PostgresDatabase) -> Result<LogPosition, model::Error> {
+        let sql = format!(
+            "SELECT xid, id FROM todo_change WHERE {READABLE} ORDER BY xid DESC, id DESC LIMIT 1"
+        );
+
+        let position: Option<(i64, i64)> = sqlx::query_as(&sql).fetch_optional(database).await?;
+
+        Ok(position.map_or_else(LogPosition::default, |(xid, id)| LogPosition { xid, id }))
+    }
+
+    // Returns the number of changes deleted
+    pub async fn purge(
+        database: &PostgresDatabase,
+        retention: Duration,
+    ) -> Result<u64, model::Error> {
+        let result =
+            sqlx::query("DELETE FROM todo_change WHERE ctime < NOW() - $1 * INTERVAL '1 second'")
+                .bind(retention.as_secs_f64())
+                .execute(database)
+                .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+// Utils
+
+// the owner, the assignee and the users the todo is shared with, like the todo list
+pub async fn visible_to(
+    transaction: &mut Transaction<'_, Postgres>,
+    todo: &Todo,
+) -> Result<Vec<i64>, model::Error> {
+    let shared_with: Vec<i64> =
+        sqlx::query_scalar("SELECT user_id FROM todo_share WHERE todo_id = $1")
+            .bind(todo.id)
+            .fetch_all(&mut **transaction)
+            .await?;
+
+    let mut user_ids = vec![todo.cid];
+    user_ids.extend(todo.assignee_id);
+    user_ids.extend(shared_with);
+    user_ids.sort_unstable();
+    user_ids.dedup();
+
+    Ok(user_ids)
+}
+
+// in the change log, and notified
+async fn log_change(
+    transaction: &mut Transaction<'_, Postgres>,
+    event: TodoEvent,
+    todo: &Todo,
+    user_ids: Vec<i64>,
+) -> Result<(), model::Error> {
+    let id: i64 = sqlx::query_scalar(
+        "INSERT INTO todo_change (event, todo_id, user_ids, data) VALUES ($1, $2, $3, $4) RETURNING id",
+    )
+    .bind(event.as_str())
+    .bind(todo.id)
+    .bind(&user_ids)
+    .bind(json!(todo))
+    .fetch_one(&mut **transaction)
+    .await?;
+
+    let change = TodoChange {
+        id,
+        event,
+        todo_id: todo.id,
+        user_ids,
+    };
+    sqlx::query("SELECT pg_notify($1, $2)")
+        .bind(TODO_EVENTS_CHANNEL)
+        .bind(serde_json::to_string(&change).unwrap_or_default())
+        .execute(&mut **transaction)
+        .await?;
+
+    Ok(())
+}
+
+// Until the server stops. The listener reconnects by itself, the changes meanwhile are lost,
+// like a change which can't be read
+async fn forward(
+    mut listener: PgListener,
+    database: PostgresDatabase,
+    sender: broadcast::Sender<Arc<ChangeLogEntry>>,
+) {
+    loop {
+        match listener.recv().await {
+            Ok(notification) => {
+                let change: TodoChange = match serde_json::from_str(notification.payload()) {
+                    Ok(change) => change,
+                    Err(error) => {
+                        println!("ERROR  - todo events - invalid change _ {error}");
+                        continue;
+                    }
+                };
+                match ChangeLog::get(&database, change.id).await {
+                    // no subscriber is not an error
+                    Ok(Some(entry)) => drop(sender.send(Arc::new(entry))),
+                    Ok(None) => {}
+                    Err(error) => println!("ERROR  - todo events - change {} - {error}", change.id),
+                }
+            }
+            Err(error) => {
+                println!("ERROR  - todo events - {error}");
+                tokio::time::sleep(LISTEN_RETRY).await;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "../_tests/model_event.rs"]
+mod tests;
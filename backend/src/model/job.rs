@@ -0,0 +1,289 @@
+use std::{fmt, str::FromStr, time::Duration};
+
+use chrono::{DateTime, Utc};
+use serde_derive::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::PgExecutor;
+
+use crate::model;
+use crate::model::{db::PostgresDatabase, page, user::UserModelAccessController};
+use crate::security::UserContext;
+
+// A background task, run by the first worker to claim it
+#[derive(sqlx::FromRow, Debug, Clone, Serialize, Deserialize)]
+pub struct Job {
+    pub id: i64,
+    // one of the JobKind, as text so that an unknown kind is a failing job, not a broken list
+    pub kind: String,
+    pub payload: Value,
+    pub status: JobStatus,
+    pub attempts: i32,
+    pub max_attempts: i32,
+    pub run_at: DateTime<Utc>,
+    pub last_error: Option<String>,
+    pub ctime: DateTime<Utc>,
+    pub mtime: DateTime<Utc>,
+}
+
+const JOB_COLUMNS: &str =
+    "id, kind, payload, status, attempts, max_attempts, run_at, last_error, ctime, mtime";
+
+#[allow(clippy::module_name_repetitions)]
+#[derive(sqlx::Type, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[sqlx(type_name = "job_status")]
+#[sqlx(rename_all = "lowercase")]
+pub enum JobStatus {
+    Queued,
+    // claimed by a worker, until run_at, its lease
+    Running,
+    Done,
+    // failed max_attempts times, or its lease expired on the last attempt, it's never retried
+    Dead,
+}
+
+#[allow(clippy::module_name_repetitions)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobKind {
+    // payload {"todo_id", "due_at"}, the todo is reminded if it's still due at that date
+    Reminder,
+    // payload a Notification, emailed to its user
+    Notification,
+    // payload a WebhookJob, the event posted to the webhook
+    Webhook,
+}
+
+impl JobKind {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Reminder => "reminder",
+            Self::Notification => "notification",
+            Self::Webhook => "webhook",
+        }
+    }
+}
+
+impl fmt::Display for JobKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for JobKind {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "reminder" => Ok(Self::Reminder),
+            "notification" => Ok(Self::Notification),
+            "webhook" => Ok(Self::Webhook),
+            other => Err(format!("unknown job kind '{other}'")),
+        }
+    }
+}
+
+#[allow(clippy::module_name_repetitions)]
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct JobListOptions {
+    pub status: Option<JobStatus>,
+    pub kind: Option<String>,
+    pub limit: Option<u16>,
+}
+
+// a crashed worker never finishes its jobs, they're claimed again after that
+pub const JOB_LEASE: Duration = Duration::from_mins(5);
+const LEASE_EXPIRED: &str = "lease expired";
+const BACKOFF_MAX: Duration = Duration::from_hours(1);
+
+// The admin view of the jobs, the workers use the functions below
+#[allow(clippy::module_name_repetitions)]
+pub struct JobModelAccessController;
+impl JobModelAccessController {
+    // the most recently changed first
+    pub async fn list(
+        database: &PostgresDatabase,
+        utx: &UserContext,
+        options: JobListOptions,
+    ) -> Result<Vec<Job>, model::Error> {
+        if !UserModelAccessController::is_admin(database, utx.user_id).await? {
+            return Err(model::Error::AccessDenied("jobs", String::from("list")));
+        }
+        let limit = page::validate_limit(options.limit)
+            .map_err(|error| model::Error::ValidationFailed(vec![error]))?;
+
+        let sql = format!(
+            "SELECT {JOB_COLUMNS} FROM job \
+             WHERE ($1::job_status IS NULL OR status = $1) AND ($2::text IS NULL OR kind = $2) \
+             ORDER BY mtime DESC, id DESC LIMIT $3"
+        );
+
+        let jobs = sqlx::query_as::<_, Job>(&sql)
+            .bind(options.status)
+            .bind(options.kind)
+            .bind(i64::from(limit))
+            .fetch_all(database)
+            .await?;
+
+        Ok(jobs)
+    }
+}
+
+// The queue the workers enqueue, claim and finish the jobs with
+#[allow(clippy::module_name_repetitions)]
+pub struct JobQueue;
+impl JobQueue {
+    // None when a job with the same dedup key was already enqueued
+    pub async fn enqueue<'e>(
+        executor: impl PgExecutor<'e>,
+        kind: JobKind,
+        payload: Value,
+        max_attempts: u16,
+        dedup_key: Option<String>,
+    ) -> Result<Option<Job>, model::Error> {
+        let sql = format!(
+            "INSERT INTO job (kind, payload, max_attempts, dedup_key) VALUES ($1, $2, $3, $4) \
+         ON CONFLICT (dedup_key) DO NOTHING RETURNING {JOB_COLUMNS}"
+        );
+
+        let job = sqlx::query_as::<_, Job>(&sql)
+            .bind(kind.as_str())
+            .bind(payload)
+            .bind(i32::from(max_attempts))
+            .bind(dedup_key)
+            .fetch_optional(executor)
+            .await?;
+
+        Ok(job)
+    }
+
+    // One reminder per open todo due within the lead time, and per due date, so a todo
+    // moved to a later date is reminded again. Returns the number of reminders enqueued
+    pub async fn enqueue_reminders(
+        database: &PostgresDatabase,
+        lead_minutes: u32,
+        max_attempts: u16,
+    ) -> Result<u64, model::Error> {
+        let result = sqlx::query(
+            "INSERT INTO job (kind, payload, max_attempts, dedup_key) \
+         SELECT $1, jsonb_build_object('todo_id', id, 'due_at', due_at), $3, \
+                'reminder:' || id || ':' || EXTRACT(EPOCH FROM due_at)::BIGINT \
+         FROM todo \
+         WHERE status = 'open' AND due_at > NOW() AND due_at <= NOW() + $2 * INTERVAL '1 minute' \
+         ON CONFLICT (dedup_key) DO NOTHING",
+        )
+        .bind(JobKind::Reminder.as_str())
+        .bind(f64::from(lead_minutes))
+        .bind(i32::from(max_attempts))
+        .execute(database)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    // The jobs due, marked running for the lease time. SKIP LOCKED lets the workers
+    // of the other servers claim the next ones instead of waiting, no job is claimed twice.
+    // A running job whose lease expired is claimed again, unless it was its last attempt: a job crashing
+    // its worker would never fail, it's dead instead
+    pub async fn claim(database: &PostgresDatabase, limit: u16) -> Result<Vec<Job>, model::Error> {
+        sqlx::query(
+            "UPDATE job SET status = 'dead', last_error = $1, mtime = NOW() \
+             WHERE status = 'running' AND run_at <= NOW() AND attempts >= max_attempts",
+        )
+        .bind(LEASE_EXPIRED)
+        .execute(database)
+        .await?;
+
+        let sql = format!(
+            "UPDATE job SET status = 'running', attempts = attempts + 1, \
+                run_at = NOW() + $2 * INTERVAL '1 second', mtime = NOW() \
+         WHERE id IN ( \
+           SELECT id FROM job WHERE status IN ('queued', 'running') AND run_at <= NOW() \
+             AND attempts < max_attempts \
+           ORDER BY run_at, id LIMIT $1 FOR UPDATE SKIP LOCKED \
+         ) RETURNING {JOB_COLUMNS}"
+        );
+
+        let mut jobs = sqlx::query_as::<_, Job>(&sql)
+            .bind(i64::from(limit))
+            .bind(JOB_LEASE.as_secs_f64())
+            .fetch_all(database)
+            .await?;
+        // RETURNING doesn't keep the order of the sub query
+        jobs.sort_by_key(|job| job.id);
+
+        Ok(jobs)
+    }
+
+    // None when the lease was lost: the job expired and was claimed again, the new attempt has the last word
+    pub async fn complete(
+        database: &PostgresDatabase,
+        job: &Job,
+    ) -> Result<Option<Job>, model::Error> {
+        let sql = format!(
+            "UPDATE job SET status = 'done', mtime = NOW() \
+             WHERE id = $1 AND status = 'running' AND attempts = $2 RETURNING {JOB_COLUMNS}"
+        );
+
+        let done = sqlx::query_as::<_, Job>(&sql)
+            .bind(job.id)
+            .bind(job.attempts)
+            .fetch_optional(database)
+            .await?;
+
+        Ok(done)
+    }
+
+    // Queued again after the backoff, or dead once it has no attempt left. None when the lease was lost
+    pub async fn fail(
+        database: &PostgresDatabase,
+        job: &Job,
+        error: &str,
+        backoff_base: Duration,
+    ) -> Result<Option<Job>, model::Error> {
+        let sql = format!(
+            "UPDATE job SET status = CASE WHEN attempts >= max_attempts THEN 'dead' ELSE 'queued' END::job_status, \
+                run_at = NOW() + $3 * INTERVAL '1 second', last_error = $2, mtime = NOW() \
+             WHERE id = $1 AND status = 'running' AND attempts = $4 RETURNING {JOB_COLUMNS}"
+        );
+
+        let failed = sqlx::query_as::<_, Job>(&sql)
+            .bind(job.id)
+            .bind(error)
+            .bind(backoff(backoff_base, job.attempts).as_secs_f64())
+            .bind(job.attempts)
+            .fetch_optional(database)
+            .await?;
+
+        Ok(failed)
+    }
+
+    // The done and dead jobs last changed before the retention time. Returns the number of jobs deleted
+    pub async fn purge(
+        database: &PostgresDatabase,
+        retention: Duration,
+    ) -> Result<u64, model::Error> {
+        let result = sqlx::query(
+            "DELETE FROM job WHERE status IN ('done', 'dead') AND mtime < NOW() - $1 * INTERVAL '1 second'",
+        )
+        .bind(retention.as_secs_f64())
+        .execute(database)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+// Utils
+
+// The delay after the attempt, base, twice base, four times base... up to an hour
+pub fn backoff(base: Duration, attempts: i32) -> Duration {
+    let exponent = u32::try_from(attempts.saturating_sub(1))
+        .unwrap_or_default()
+        .min(31);
+
+    base.saturating_mul(2_u32.pow(exponent)).min(BACKOFF_MAX)
+}
+
+#[cfg(test)]
+#[path = "../_tests/model_job.rs"]
+mod tests;
@@ -0,0 +1,227 @@
+use std::{fs, path::Path, path::PathBuf};
+
+use sha2::{Digest, Sha256};
+
+use crate::model;
+use crate::model::db::PostgresDatabase;
+
+// any constant works, as long as every instance uses the same one
+const MIGRATION_LOCK_KEY: i64 = 7_343_921;
+
+const SQL_CREATE_SCHEMA_MIGRATIONS: &str = "CREATE TABLE IF NOT EXISTS schema_migrations (
+    version TEXT PRIMARY KEY,
+    checksum TEXT NOT NULL,
+    applied_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+)";
+
+// "01-create-schema.sql" is the migration, and the optional "01-create-schema.down.sql" reverts it
+const DOWN_SUFFIX: &str = ".down";
+
+pub struct Migration {
+    pub version: String, // the file name without the extension, like "01-create-schema"
+    pub checksum: String, // sha256 of the file content, hex encoded
+    pub sql: String,
+    pub down_sql: Option<String>,
+}
+
+pub struct MigrationStatus {
+    pub version: String,
+    pub applied_at: Option<String>,
+    pub checksum_mismatch: bool,
+}
+
+// Every .sql file of the directory, except the .down.sql, ordered by file name
+pub fn load_migrations(directory: &Path) -> Result<Vec<Migration>, model::Error> {
+    let mut paths: Vec<PathBuf> = fs::read_dir(directory)?
+        .filter_map(|element| element.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("sql"))
+        })
+        .filter(|path| {
+            !path
+                .file_stem()
+                .is_some_and(|stem| stem.to_string_lossy().ends_with(DOWN_SUFFIX))
+        })
+        .collect();
+
+    paths.sort();
+
+    paths
+        .into_iter()
+        .map(|path| {
+            let sql = fs::read_to_string(&path)?;
+            let version = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let down_path = directory.join(format!("{version}{DOWN_SUFFIX}.sql"));
+            let down_sql = if down_path.exists() {
+                Some(fs::read_to_string(down_path)?)
+            } else {
+                None
+            };
+
+            Ok(Migration {
+                version,
+                checksum: format!("{:x}", Sha256::digest(sql.as_bytes())),
+                sql,
+                down_sql,
+            })
+        })
+        .collect()
+}
+
+// Apply, in order, the migrations not applied yet, each one in its own transaction.
+// Returns the versions applied by this run.
+pub async fn run_migrations(
+    database: &PostgresDatabase,
+    directory: &Path,
+) -> Result<Vec<String>, model::Error> {
+    let migrations = load_migrations(directory)?;
+
+    create_schema_migrations(database).await?;
+
+    let mut applied_versions = Vec::new();
+
+    for migration in migrations {
+        let mut transaction = database.begin().await?;
+        // another instance might be migrating at the same time, the lock is released with the transaction
+        lock(&mut transaction).await?;
+
+        let applied_checksum: Option<String> =
+            sqlx::query_scalar("SELECT checksum FROM schema_migrations WHERE version = $1")
+                .bind(&migration.version)
+                .fetch_optional(&mut *transaction)
+                .await?;
+
+        match applied_checksum {
+            Some(checksum) if checksum == migration.checksum => continue,
+            Some(_) => return Err(model::Error::MigrationChecksumMismatch(migration.version)),
+            None => (),
+        }
+
+        // raw_sql runs the whole file, so no statement splitting is needed
+        sqlx::raw_sql(&migration.sql)
+            .execute(&mut *transaction)
+            .await
+            .map_err(|error| model::Error::MigrationFailed(migration.version.clone(), error))?;
+
+        sqlx::query("INSERT INTO schema_migrations (version, checksum) VALUES ($1, $2)")
+            .bind(&migration.version)
+            .bind(&migration.checksum)
+            .execute(&mut *transaction)
+            .await?;
+
+        transaction.commit().await?;
+
+        println!("Migration applied: {}", migration.version);
+        applied_versions.push(migration.version);
+    }
+
+    Ok(applied_versions)
+}
+
+// Revert the last applied migration with its .down.sql, in a transaction.
+// Returns the reverted version, None when nothing is applied.
+pub async fn revert_last_migration(
+    database: &PostgresDatabase,
+    directory: &Path,
+) -> Result<Option<String>, model::Error> {
+    let migrations = load_migrations(directory)?;
+
+    create_schema_migrations(database).await?;
+
+    let mut transaction = database.begin().await?;
+    lock(&mut transaction).await?;
+
+    let last_version: Option<String> =
+        sqlx::query_scalar("SELECT version FROM schema_migrations ORDER BY version DESC LIMIT 1")
+            .fetch_optional(&mut *transaction)
+            .await?;
+
+    let Some(version) = last_version else {
+        return Ok(None);
+    };
+
+    let down_sql = migrations
+        .into_iter()
+        .find(|migration| migration.version == version)
+        .and_then(|migration| migration.down_sql)
+        .ok_or_else(|| model::Error::MigrationNotReversible(version.clone()))?;
+
+    sqlx::raw_sql(&down_sql)
+        .execute(&mut *transaction)
+        .await
+        .map_err(|error| model::Error::MigrationFailed(version.clone(), error))?;
+
+    sqlx::query("DELETE FROM schema_migrations WHERE version = $1")
+        .bind(&version)
+        .execute(&mut *transaction)
+        .await?;
+
+    transaction.commit().await?;
+
+    println!("Migration reverted: {version}");
+
+    Ok(Some(version))
+}
+
+// Every migration on disk, applied or pending. Read only, all are pending on a database never migrated
+pub async fn migration_status(
+    database: &PostgresDatabase,
+    directory: &Path,
+) -> Result<Vec<MigrationStatus>, model::Error> {
+    let migrations = load_migrations(directory)?;
+
+    let migrated: bool = sqlx::query_scalar("SELECT to_regclass('schema_migrations') IS NOT NULL")
+        .fetch_one(database)
+        .await?;
+    let applied: Vec<(String, String, String)> = if migrated {
+        sqlx::query_as(
+            "SELECT version, checksum, applied_at::text FROM schema_migrations ORDER BY version",
+        )
+        .fetch_all(database)
+        .await?
+    } else {
+        Vec::new()
+    };
+
+    Ok(migrations
+        .into_iter()
+        .map(|migration| {
+            let applied = applied
+                .iter()
+                .find(|(version, _, _)| *version == migration.version);
+
+            MigrationStatus {
+                applied_at: applied.map(|(_, _, applied_at)| applied_at.clone()),
+                checksum_mismatch: applied
+                    .is_some_and(|(_, checksum, _)| *checksum != migration.checksum),
+                version: migration.version,
+            }
+        })
+        .collect())
+}
+
+async fn create_schema_migrations(database: &PostgresDatabase) -> Result<(), sqlx::Error> {
+    let mut transaction = database.begin().await?;
+    lock(&mut transaction).await?;
+    sqlx::query(SQL_CREATE_SCHEMA_MIGRATIONS)
+        .execute(&mut *transaction)
+        .await?;
+    transaction.commit().await
+}
+
+async fn lock(transaction: &mut sqlx::Transaction<'_, sqlx::Postgres>) -> Result<(), sqlx::Error> {
+    sqlx::query("SELECT pg_advisory_xact_lock($1)")
+        .bind(MIGRATION_LOCK_KEY)
+        .execute(&mut **transaction)
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+#[path = "../_tests/model_migration.rs"]
+mod tests;
@@ -1,11 +1,72 @@
+use serde_derive::Serialize;
 use thiserror::Error as ThisError;
 
+mod attachment;
+mod comment;
 mod db;
+mod event;
+mod job;
+mod migration;
+mod notification;
+mod page;
+mod project;
+mod recurrence;
+mod share;
+mod subtask;
+mod tag;
 mod todo;
-pub use db::initialize_database;
+mod user;
+mod webhook;
+pub use attachment::AttachmentModelAccessController;
+pub use attachment::NewAttachment;
+pub use comment::CommentModelAccessController;
+pub use comment::PartialComment;
+pub use db::DatabaseMode;
 pub use db::PostgresDatabase;
-pub use todo::ModelAccessController;
-pub use todo::{PartialTodo, Status, Todo};
+pub use db::{connect_database, initialize_database, migrations_directory, seed_database};
+pub use event::{ChangeLog, ChangeLogEntry, EventHub, LogPosition, TodoEvent};
+pub use job::{Job, JobKind, JobListOptions};
+// only matched on by the worker tests
+#[cfg(test)]
+pub use job::JobStatus;
+pub use job::{JobModelAccessController, JobQueue, JOB_LEASE};
+pub use migration::{migration_status, revert_last_migration, run_migrations};
+pub use notification::NotificationModelAccessController;
+pub use notification::{
+    notify, preferences_of, recipients, Notification, NotificationEvent,
+    PartialNotificationPreferences,
+};
+pub use page::Page;
+pub use project::PartialProject;
+pub use project::ProjectModelAccessController;
+pub use share::PartialShare;
+pub use share::ShareModelAccessController;
+pub use subtask::CloseParentRule;
+pub use tag::TagModelAccessController;
+pub use todo::{due_todo, ModelAccessController};
+pub use todo::{PartialTodo, Status, Todo, TodoListOptions, TodoPatch};
+pub use user::UserModelAccessController;
+pub use user::{User, UserCredentials};
+pub use webhook::WebhookModelAccessController;
+pub use webhook::{
+    purge_deliveries, record_delivery, webhook_for_delivery, PartialWebhook, WebhookJob,
+};
+
+// A client data error on a given field, sent back as is to the client
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldError {
+    pub field: &'static str,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            field,
+            message: message.into(),
+        }
+    }
+}
 
 #[allow(clippy::enum_variant_names)]
 #[derive(ThisError, Debug)]
@@ -13,9 +74,41 @@ pub enum Error {
     #[error("Entity Not Found _ {0}{1}")]
     EntityNotFound(&'static str, String),
 
+    #[error("Access Denied _ {0}{1}")]
+    AccessDenied(&'static str, String),
+
+    #[error("Version Conflict _ {0}{1} is at version {2}")]
+    VersionConflict(&'static str, String, i64),
+
+    #[error("Validation Failed _ {0:?}")]
+    ValidationFailed(Vec<FieldError>),
+
     #[error(transparent)]
     SqlxError(#[from] sqlx::Error),
 
+    #[error("Sql file {0} failed _ {1}")]
+    SqlFileFailed(String, #[source] sqlx::Error),
+
+    #[error("Migration {0} failed _ {1}")]
+    MigrationFailed(String, #[source] sqlx::Error),
+
+    #[error("Migration {0} was modified after being applied")]
+    MigrationChecksumMismatch(String),
+
+    #[error("Migration {0} has no .down.sql file, it can't be reverted")]
+    MigrationNotReversible(String),
+
     #[error(transparent)]
     IOError(#[from] std::io::Error),
+
+    #[error(transparent)]
+    SecurityError(#[from] crate::security::Error),
+
+    #[error(transparent)]
+    StorageError(#[from] crate::storage::Error),
 }
+
+// the fixtures shared by the model tests
+#[cfg(test)]
+#[path = "../_tests/model_utils.rs"]
+mod test_utils;
@@ -0,0 +1,173 @@
+use serde_derive::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::PgExecutor;
+
+use crate::config::config;
+use crate::model;
+use crate::model::{
+    db::PostgresDatabase,
+    job::{JobKind, JobQueue},
+    todo::deserialize_present,
+    FieldError,
+};
+use crate::security::UserContext;
+
+// Where a user is emailed, and about what. No email, no notification
+#[derive(sqlx::FromRow, Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationPreferences {
+    pub user_id: i64,
+    pub email: Option<String>,
+    pub assigned: bool,
+    pub commented: bool,
+    pub due: bool,
+}
+
+const PREFERENCE_COLUMNS: &str = "user_id, email, assigned, commented, due";
+
+#[allow(clippy::module_name_repetitions, clippy::option_option)]
+#[derive(Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PartialNotificationPreferences {
+    // null to stop all the emails
+    #[serde(default, deserialize_with = "deserialize_present")]
+    pub email: Option<Option<String>>,
+    pub assigned: Option<bool>,
+    pub commented: Option<bool>,
+    pub due: Option<bool>,
+}
+
+// same as the email column, VARCHAR(255)
+const EMAIL_MAX_LEN: usize = 255;
+
+impl PartialNotificationPreferences {
+    fn validate(&self) -> Result<(), model::Error> {
+        if let Some(Some(email)) = &self.email {
+            if email.len() > EMAIL_MAX_LEN || email.parse::<lettre::Address>().is_err() {
+                return Err(model::Error::ValidationFailed(vec![FieldError::new(
+                    "email",
+                    "is not a valid email address",
+                )]));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// What a user is notified of
+#[allow(clippy::module_name_repetitions)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NotificationEvent {
+    Assigned,
+    Commented,
+    Due,
+}
+
+// The payload of a notification job, the email is made when it's sent
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Notification {
+    pub event: NotificationEvent,
+    pub user_id: i64,
+    pub todo_id: i64,
+    #[serde(default)]
+    pub comment_id: Option<i64>,
+}
+
+// Every user has preferences, the defaults until they change them
+#[allow(clippy::module_name_repetitions)]
+pub struct NotificationModelAccessController;
+impl NotificationModelAccessController {
+    pub async fn get(
+        database: &PostgresDatabase,
+        utx: &UserContext,
+    ) -> Result<NotificationPreferences, model::Error> {
+        preferences_of(database, utx.user_id).await
+    }
+
+    pub async fn update(
+        database: &PostgresDatabase,
+        utx: &UserContext,
+        data: PartialNotificationPreferences,
+    ) -> Result<NotificationPreferences, model::Error> {
+        data.validate()?;
+
+        // absent fields keep their current value, or get their default on the first update
+        let sql = format!(
+            "INSERT INTO notification_preference AS preference (user_id, email, assigned, commented, due) \
+             VALUES ($1, $3, COALESCE($4, TRUE), COALESCE($5, TRUE), COALESCE($6, TRUE)) \
+             ON CONFLICT (user_id) DO UPDATE SET \
+               email = CASE WHEN $2 THEN $3 ELSE preference.email END, \
+               assigned = COALESCE($4, preference.assigned), \
+               commented = COALESCE($5, preference.commented), \
+               due = COALESCE($6, preference.due), mtime = NOW() \
+             RETURNING {PREFERENCE_COLUMNS}"
+        );
+
+        let preferences = sqlx::query_as::<_, NotificationPreferences>(&sql)
+            .bind(utx.user_id)
+            .bind(data.email.is_some())
+            .bind(data.email.flatten())
+            .bind(data.assigned)
+            .bind(data.commented)
+            .bind(data.due)
+            .fetch_one(database)
+            .await?;
+
+        Ok(preferences)
+    }
+}
+
+// Utils
+
+pub async fn preferences_of(
+    database: &PostgresDatabase,
+    user_id: i64,
+) -> Result<NotificationPreferences, model::Error> {
+    let sql =
+        format!("SELECT {PREFERENCE_COLUMNS} FROM notification_preference WHERE user_id = $1");
+
+    let preferences = sqlx::query_as::<_, NotificationPreferences>(&sql)
+        .bind(user_id)
+        .fetch_optional(database)
+        .await?;
+
+    Ok(preferences.unwrap_or(NotificationPreferences {
+        user_id,
+        email: None,
+        assigned: true,
+        commented: true,
+        due: true,
+    }))
+}
+
+// A notification job, so the email is sent, and retried, by the worker.
+// In the transaction of the change, a rolled back change notifies nobody
+pub async fn notify<'e>(
+    executor: impl PgExecutor<'e>,
+    notification: &Notification,
+    dedup_key: Option<String>,
+) -> Result<(), model::Error> {
+    JobQueue::enqueue(
+        executor,
+        JobKind::Notification,
+        json!(notification),
+        config().jobs.max_attempts,
+        dedup_key,
+    )
+    .await?;
+
+    Ok(())
+}
+
+// the users of the todo, its owner and its assignee, but the one who made the change
+pub fn recipients(owner_id: i64, assignee_id: Option<i64>, except: Option<i64>) -> Vec<i64> {
+    let mut recipients = vec![owner_id];
+    recipients.extend(assignee_id.filter(|assignee_id| *assignee_id != owner_id));
+    recipients.retain(|user_id| Some(*user_id) != except);
+
+    recipients
+}
+
+#[cfg(test)]
+#[path = "../_tests/model_notification.rs"]
+mod tests;
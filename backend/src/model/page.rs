@@ -0,0 +1,100 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use serde_derive::{Deserialize, Serialize};
+
+use crate::model::FieldError;
+
+pub const DEFAULT_PAGE_LIMIT: u16 = 50;
+pub const MAX_PAGE_LIMIT: u16 = 100;
+
+// One page of a list, and the cursor to give back to get the next one (None on the last page)
+#[derive(Debug, Clone, Serialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>,
+    // number of items matching the filters, over all the pages
+    pub total: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortDirection {
+    Asc,
+    Desc,
+}
+
+impl SortDirection {
+    pub const fn as_sql(self) -> &'static str {
+        match self {
+            Self::Asc => "ASC",
+            Self::Desc => "DESC",
+        }
+    }
+
+    // the row comparison to get the rows after the cursor, in that direction
+    pub const fn after_operator(self) -> &'static str {
+        match self {
+            Self::Asc => ">",
+            Self::Desc => "<",
+        }
+    }
+}
+
+// Keyset pagination : the position of the last item of a page, (sort key, id).
+// Opaque to the client, it's the base64url of its json.
+// The sort is kept in it, a cursor is only valid for the sort it was made with
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cursor {
+    pub sort: String,
+    pub key: String,
+    pub id: i64,
+}
+
+impl Cursor {
+    pub fn encode(&self) -> String {
+        // a struct of strings and an integer always serializes
+        let json = serde_json::to_vec(self).unwrap_or_default();
+        URL_SAFE_NO_PAD.encode(json)
+    }
+
+    // is_key: whether the key is a value of the sort column, cast by the query. A forged one is invalid, not a query error
+    pub fn decode(
+        value: &str,
+        sort: &str,
+        is_key: impl Fn(&str) -> bool,
+    ) -> Result<Self, FieldError> {
+        let cursor: Self = URL_SAFE_NO_PAD
+            .decode(value)
+            .ok()
+            .and_then(|json| serde_json::from_slice::<Self>(&json).ok())
+            .filter(|cursor| cursor.sort != sort || is_key(&cursor.key))
+            .ok_or_else(|| FieldError::new("cursor", "is not a valid cursor"))?;
+
+        if cursor.sort != sort {
+            return Err(FieldError::new(
+                "cursor",
+                format!("was made for the sort '{}', not '{sort}'", cursor.sort),
+            ));
+        }
+
+        Ok(cursor)
+    }
+}
+
+pub fn validate_limit(limit: Option<u16>) -> Result<u16, FieldError> {
+    match limit.unwrap_or(DEFAULT_PAGE_LIMIT) {
+        limit @ 1..=MAX_PAGE_LIMIT => Ok(limit),
+        _ => Err(FieldError::new(
+            "limit",
+            format!("must be between 1 and {MAX_PAGE_LIMIT}"),
+        )),
+    }
+}
+
+// the LIKE pattern matching value anywhere, the LIKE wildcards of value matching themselves
+pub fn contains_pattern(value: &str) -> String {
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+
+    format!("%{escaped}%")
+}
@@ -0,0 +1,260 @@
+use chrono::{DateTime, Utc};
+use serde_derive::{Deserialize, Serialize};
+use sqlx::{PgExecutor, Postgres, Transaction};
+
+use crate::model;
+use crate::model::{db::PostgresDatabase, event::TodoEvent, todo, FieldError};
+use crate::security::UserContext;
+
+#[derive(sqlx::FromRow, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Project {
+    pub id: i64,
+    pub cid: i64,
+    pub name: String,
+    pub color: String,
+    // an archived project hides its todos from the todos list, and gets no new todo
+    pub archived: bool,
+    pub ctime: DateTime<Utc>,
+    pub mtime: DateTime<Utc>,
+}
+
+const PROJECT_COLUMNS: &str = "id, cid, name, color, archived, ctime, mtime";
+
+// the client writable fields, absent ones are left untouched
+#[allow(clippy::module_name_repetitions)]
+#[derive(Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PartialProject {
+    pub name: Option<String>,
+    pub color: Option<String>,
+    pub archived: Option<bool>,
+}
+
+// same as the name column, VARCHAR(63)
+const NAME_MAX_LEN: usize = 63;
+
+impl PartialProject {
+    fn validate(&self) -> Result<(), model::Error> {
+        let mut errors = Vec::new();
+
+        if let Some(name) = &self.name {
+            if name.trim().is_empty() {
+                errors.push(FieldError::new("name", "must not be empty"));
+            } else if name.chars().count() > NAME_MAX_LEN {
+                errors.push(FieldError::new(
+                    "name",
+                    format!("must be at most {NAME_MAX_LEN} characters"),
+                ));
+            }
+        }
+        if let Some(color) = &self.color {
+            let is_hex_color = color.len() == 7
+                && color.starts_with('#')
+                && color[1..].chars().all(|c| c.is_ascii_hexdigit());
+            if !is_hex_color {
+                errors.push(FieldError::new("color", "must be like #rrggbb"));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(model::Error::ValidationFailed(errors))
+        }
+    }
+}
+
+pub struct ProjectModelAccessController;
+impl ProjectModelAccessController {
+    pub async fn create(
+        database: &PostgresDatabase,
+        utx: &UserContext,
+        data: PartialProject,
+    ) -> Result<Project, model::Error> {
+        data.validate()?;
+
+        let sql = format!(
+            "INSERT INTO project (cid, name, color, archived) \
+             VALUES ($1, $2, COALESCE($3, '#808080'), COALESCE($4, FALSE)) RETURNING {PROJECT_COLUMNS}"
+        );
+
+        let project = sqlx::query_as::<_, Project>(&sql)
+            .bind(utx.user_id)
+            .bind(data.name.unwrap_or_else(|| "untitled".into()))
+            .bind(data.color)
+            .bind(data.archived)
+            .fetch_one(database)
+            .await?;
+
+        Ok(project)
+    }
+
+    pub async fn list(
+        database: &PostgresDatabase,
+        utx: &UserContext,
+    ) -> Result<Vec<Project>, model::Error> {
+        let sql = format!("SELECT {PROJECT_COLUMNS} FROM project WHERE cid = $1 ORDER BY name, id");
+
+        let projects = sqlx::query_as::<_, Project>(&sql)
+            .bind(utx.user_id)
+            .fetch_all(database)
+            .await?;
+
+        Ok(projects)
+    }
+
+    pub async fn get(
+        database: &PostgresDatabase,
+        utx: &UserContext,
+        id: i64,
+    ) -> Result<Project, model::Error> {
+        select_project(database, utx, id, false).await
+    }
+
+    pub async fn update(
+        database: &PostgresDatabase,
+        utx: &UserContext,
+        id: i64,
+        data: PartialProject,
+    ) -> Result<Project, model::Error> {
+        data.validate()?;
+
+        let mut transaction = database.begin().await?;
+
+        let sql = format!(
+            "UPDATE project SET name = COALESCE($3, name), color = COALESCE($4, color), \
+             archived = COALESCE($5, archived), mtime = NOW() \
+             WHERE id = $1 AND cid = $2 RETURNING {PROJECT_COLUMNS}"
+        );
+
+        let project = sqlx::query_as::<_, Project>(&sql)
+            .bind(id)
+            .bind(utx.user_id)
+            .bind(data.name)
+            .bind(data.color)
+            .bind(data.archived)
+            .fetch_one(&mut *transaction)
+            .await;
+        let project = handle_fetch_one_result(project, id)?;
+
+        // archiving cascades to the todos of the project, and so does unarchiving
+        if data.archived.is_some() {
+            let todo_ids = set_todos_archived(&mut transaction, id, project.archived).await?;
+            todo::publish_changed(&mut transaction, TodoEvent::Updated, &todo_ids).await?;
+        }
+
+        transaction.commit().await?;
+
+        Ok(project)
+    }
+
+    // its todos are kept, without project
+    pub async fn delete(
+        database: &PostgresDatabase,
+        utx: &UserContext,
+        id: i64,
+    ) -> Result<Project, model::Error> {
+        let mut transaction = database.begin().await?;
+
+        select_project(&mut *transaction, utx, id, true).await?;
+        let todo_ids = detach_todos(&mut transaction, id).await?;
+
+        let sql =
+            format!("DELETE FROM project WHERE id = $1 AND cid = $2 RETURNING {PROJECT_COLUMNS}");
+
+        let project = sqlx::query_as::<_, Project>(&sql)
+            .bind(id)
+            .bind(utx.user_id)
+            .fetch_one(&mut *transaction)
+            .await;
+        let project = handle_fetch_one_result(project, id)?;
+        todo::publish_changed(&mut transaction, TodoEvent::Updated, &todo_ids).await?;
+
+        transaction.commit().await?;
+
+        Ok(project)
+    }
+
+    // The project a todo of the user can be put in, or the field error telling why not
+    pub async fn check_for_todo<'e>(
+        executor: impl PgExecutor<'e>,
+        utx: &UserContext,
+        id: i64,
+    ) -> Result<(), model::Error> {
+        let invalid = |message: &str| {
+            model::Error::ValidationFailed(vec![FieldError::new("project_id", message)])
+        };
+
+        match select_project(executor, utx, id, false).await {
+            Ok(project) if project.archived => Err(invalid("is an archived project")),
+            Ok(_) => Ok(()),
+            Err(model::Error::EntityNotFound(_, _)) => Err(invalid("is not one of your projects")),
+            Err(other) => Err(other),
+        }
+    }
+}
+
+// Utils
+
+// scoped to the owner, a project of another user is just not found
+async fn select_project<'e>(
+    executor: impl PgExecutor<'e>,
+    utx: &UserContext,
+    id: i64,
+    for_update: bool,
+) -> Result<Project, model::Error> {
+    let lock = if for_update { " FOR UPDATE" } else { "" };
+    let sql = format!("SELECT {PROJECT_COLUMNS} FROM project WHERE id = $1 AND cid = $2{lock}");
+
+    let project = sqlx::query_as::<_, Project>(&sql)
+        .bind(id)
+        .bind(utx.user_id)
+        .fetch_one(executor)
+        .await;
+
+    handle_fetch_one_result(project, id)
+}
+
+// Returns the ids of the todos changed
+async fn set_todos_archived(
+    transaction: &mut Transaction<'_, Postgres>,
+    project_id: i64,
+    archived: bool,
+) -> Result<Vec<i64>, model::Error> {
+    Ok(sqlx::query_scalar(
+        "UPDATE todo SET archived = $2, mtime = NOW(), version = version + 1 \
+         WHERE project_id = $1 AND archived <> $2 RETURNING id",
+    )
+    .bind(project_id)
+    .bind(archived)
+    .fetch_all(&mut **transaction)
+    .await?)
+}
+
+// done before the delete, so the todos get a new version, unlike with the ON DELETE SET NULL
+async fn detach_todos(
+    transaction: &mut Transaction<'_, Postgres>,
+    project_id: i64,
+) -> Result<Vec<i64>, model::Error> {
+    Ok(sqlx::query_scalar(
+        "UPDATE todo SET project_id = NULL, archived = FALSE, mtime = NOW(), version = version + 1 \
+         WHERE project_id = $1 RETURNING id",
+    )
+    .bind(project_id)
+    .fetch_all(&mut **transaction)
+    .await?)
+}
+
+fn handle_fetch_one_result(
+    result: Result<Project, sqlx::Error>,
+    id: i64,
+) -> Result<Project, model::Error> {
+    result.map_err(|sqlx_error| match sqlx_error {
+        sqlx::Error::RowNotFound => model::Error::EntityNotFound("project", id.to_string()),
+        other => model::Error::SqlxError(other),
+    })
+}
+
+#[cfg(test)]
+#[path = "../_tests/model_project.rs"]
+mod tests;
@@ -0,0 +1,262 @@
+use std::{collections::BTreeSet, fmt, str::FromStr};
+
+use chrono::{DateTime, Datelike, Days, Months, NaiveDate, Utc, Weekday};
+use sqlx::{Postgres, Transaction};
+
+use crate::model;
+use crate::model::FieldError;
+
+// The RRULE subset of the recurring todos, like "FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,TH"
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recurrence {
+    pub frequency: Frequency,
+    // every interval days, weeks or months
+    pub interval: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Frequency {
+    Daily,
+    // BYDAY, the weekday of the due date when empty
+    Weekly(BTreeSet<WeekdayKey>),
+    // BYMONTHDAY, the day of the due date when None. The last day of the shorter months
+    Monthly(Option<u32>),
+}
+
+// Weekday in the RRULE order, monday first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct WeekdayKey(u32);
+
+const WEEKDAYS: [&str; 7] = ["MO", "TU", "WE", "TH", "FR", "SA", "SU"];
+const INTERVAL_MAX: u32 = 366;
+
+impl WeekdayKey {
+    const fn of(weekday: Weekday) -> Self {
+        Self(weekday.num_days_from_monday())
+    }
+}
+
+impl Recurrence {
+    // The first occurrence strictly after the given one, at the same time of day
+    pub fn next_after(&self, after: DateTime<Utc>) -> DateTime<Utc> {
+        let date = after.date_naive();
+        let next_date = match &self.frequency {
+            Frequency::Daily => date.checked_add_days(Days::new(u64::from(self.interval))),
+            Frequency::Weekly(weekdays) => self.next_weekly(date, weekdays),
+            Frequency::Monthly(day) => self.next_monthly(date, day.unwrap_or_else(|| date.day())),
+        }
+        .unwrap_or(NaiveDate::MAX);
+
+        next_date.and_time(after.time()).and_utc()
+    }
+
+    // A monthly rule without BYMONTHDAY gets the day of the occurrence it recurs from. Otherwise, the 31st clamped
+    // to the 29th in February would stay the 29th
+    pub fn anchored(&self, occurrence: DateTime<Utc>) -> Self {
+        match self.frequency {
+            Frequency::Monthly(None) => Self {
+                frequency: Frequency::Monthly(Some(occurrence.day())),
+                interval: self.interval,
+            },
+            _ => self.clone(),
+        }
+    }
+
+    // the next listed weekday, in a week that is a multiple of interval from the week of date
+    fn next_weekly(&self, date: NaiveDate, weekdays: &BTreeSet<WeekdayKey>) -> Option<NaiveDate> {
+        let week_start = date.week(Weekday::Mon).first_day();
+        let weekdays = if weekdays.is_empty() {
+            BTreeSet::from([WeekdayKey::of(date.weekday())])
+        } else {
+            weekdays.clone()
+        };
+
+        (1..=u64::from(7 * self.interval + 7))
+            .filter_map(|days| date.checked_add_days(Days::new(days)))
+            .find(|candidate| {
+                let weeks = (candidate.week(Weekday::Mon).first_day() - week_start).num_weeks();
+                weeks % i64::from(self.interval) == 0
+                    && weekdays.contains(&WeekdayKey::of(candidate.weekday()))
+            })
+    }
+
+    fn next_monthly(&self, date: NaiveDate, day: u32) -> Option<NaiveDate> {
+        let first_of_month = date.with_day(1)?;
+        // the day may still come this month
+        let this_month = day_of_month(first_of_month, day);
+        if this_month.is_some_and(|this_month| this_month > date) {
+            return this_month;
+        }
+
+        day_of_month(
+            first_of_month.checked_add_months(Months::new(self.interval))?,
+            day,
+        )
+    }
+}
+
+// the day, or the last day of the month when it is shorter
+fn day_of_month(first_of_month: NaiveDate, day: u32) -> Option<NaiveDate> {
+    let last_day = first_of_month
+        .checked_add_months(Months::new(1))?
+        .pred_opt()?
+        .day();
+
+    first_of_month.with_day(day.min(last_day))
+}
+
+impl FromStr for Recurrence {
+    type Err = FieldError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = |message: String| FieldError::new("recurrence", message);
+
+        let mut frequency = None;
+        let mut interval = 1;
+        let mut by_day = None;
+        let mut by_month_day = None;
+        for part in value.trim().split(';').filter(|part| !part.is_empty()) {
+            let (key, value) = part
+                .split_once('=')
+                .ok_or_else(|| invalid(format!("'{part}' is not like KEY=VALUE")))?;
+            match key.trim().to_ascii_uppercase().as_str() {
+                "FREQ" => frequency = Some(value.trim().to_ascii_uppercase()),
+                "INTERVAL" => {
+                    interval = value
+                        .trim()
+                        .parse()
+                        .ok()
+                        .filter(|interval| (1..=INTERVAL_MAX).contains(interval))
+                        .ok_or_else(|| {
+                            invalid(format!("INTERVAL must be between 1 and {INTERVAL_MAX}"))
+                        })?;
+                }
+                "BYDAY" => {
+                    let weekdays = value
+                        .split(',')
+                        .map(|weekday| {
+                            let weekday = weekday.trim().to_ascii_uppercase();
+                            WEEKDAYS
+                                .iter()
+                                .position(|key| *key == weekday)
+                                .and_then(|index| u32::try_from(index).ok())
+                                .map(WeekdayKey)
+                                .ok_or_else(|| {
+                                    invalid(format!("BYDAY '{weekday}' is not one of MO to SU"))
+                                })
+                        })
+                        .collect::<Result<BTreeSet<_>, _>>()?;
+                    by_day = Some(weekdays);
+                }
+                "BYMONTHDAY" => {
+                    let day = value
+                        .trim()
+                        .parse()
+                        .ok()
+                        .filter(|day| (1..=31).contains(day))
+                        .ok_or_else(|| {
+                            invalid(String::from("BYMONTHDAY must be between 1 and 31"))
+                        })?;
+                    by_month_day = Some(day);
+                }
+                other => return Err(invalid(format!("'{other}' is not supported"))),
+            }
+        }
+
+        let frequency = match frequency.as_deref() {
+            Some("DAILY") if by_day.is_none() && by_month_day.is_none() => Frequency::Daily,
+            Some("WEEKLY") if by_month_day.is_none() => {
+                Frequency::Weekly(by_day.unwrap_or_default())
+            }
+            Some("MONTHLY") if by_day.is_none() => Frequency::Monthly(by_month_day),
+            Some("DAILY" | "WEEKLY" | "MONTHLY") => {
+                return Err(invalid(String::from(
+                    "BYDAY is for WEEKLY only, BYMONTHDAY for MONTHLY only",
+                )))
+            }
+            Some(other) => {
+                return Err(invalid(format!(
+                    "FREQ '{other}' is not one of DAILY, WEEKLY, MONTHLY"
+                )))
+            }
+            None => return Err(invalid(String::from("FREQ is required"))),
+        };
+
+        Ok(Self {
+            frequency,
+            interval,
+        })
+    }
+}
+
+// The normalized rule, as stored
+impl fmt::Display for Recurrence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let frequency = match self.frequency {
+            Frequency::Daily => "DAILY",
+            Frequency::Weekly(_) => "WEEKLY",
+            Frequency::Monthly(_) => "MONTHLY",
+        };
+        write!(f, "FREQ={frequency}")?;
+        if self.interval != 1 {
+            write!(f, ";INTERVAL={}", self.interval)?;
+        }
+        match &self.frequency {
+            Frequency::Weekly(weekdays) if !weekdays.is_empty() => {
+                let weekdays: Vec<&str> = weekdays
+                    .iter()
+                    .filter_map(|weekday| WEEKDAYS.get(weekday.0 as usize).copied())
+                    .collect();
+                write!(f, ";BYDAY={}", weekdays.join(","))
+            }
+            Frequency::Monthly(Some(day)) => write!(f, ";BYMONTHDAY={day}"),
+            _ => Ok(()),
+        }
+    }
+}
+
+// The next occurrence of a recurring todo being closed, with its tags and shares, due at the next date.
+// The rule moves to it, the closed todo doesn't recur anymore
+pub async fn create_next_occurrence(
+    transaction: &mut Transaction<'_, Postgres>,
+    todo_id: i64,
+    recurrence: &Recurrence,
+    due_at: DateTime<Utc>,
+) -> Result<i64, model::Error> {
+    let recurrence = recurrence.anchored(due_at);
+
+    let next_id: i64 = sqlx::query_scalar(
+        "INSERT INTO todo (cid, title, priority, description, due_at, project_id, archived, parent_id, assignee_id, recurrence) \
+         SELECT cid, title, priority, description, $2, project_id, archived, parent_id, assignee_id, $3 \
+         FROM todo WHERE id = $1 RETURNING id",
+    )
+    .bind(todo_id)
+    .bind(recurrence.next_after(due_at))
+    .bind(recurrence.to_string())
+    .fetch_one(&mut **transaction)
+    .await?;
+
+    sqlx::query(
+        "INSERT INTO todo_tag (todo_id, tag_id) SELECT $2, tag_id FROM todo_tag WHERE todo_id = $1",
+    )
+    .bind(todo_id)
+    .bind(next_id)
+    .execute(&mut **transaction)
+    .await?;
+
+    // the users it's shared with keep seeing the series
+    sqlx::query(
+        "INSERT INTO todo_share (todo_id, user_id, permission) \
+         SELECT $2, user_id, permission FROM todo_share WHERE todo_id = $1",
+    )
+    .bind(todo_id)
+    .bind(next_id)
+    .execute(&mut **transaction)
+    .await?;
+
+    Ok(next_id)
+}
+
+#[cfg(test)]
+#[path = "../_tests/model_recurrence.rs"]
+mod tests;
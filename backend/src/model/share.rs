@@ -0,0 +1,200 @@
+use chrono::{DateTime, Utc};
+use serde_derive::{Deserialize, Serialize};
+use sqlx::PgExecutor;
+
+use crate::model;
+use crate::model::{
+    db::PostgresDatabase,
+    event,
+    todo::{self, ModelAccessController, Todo},
+    FieldError,
+};
+use crate::security::UserContext;
+
+// What a user, other than the owner, can do on a todo
+#[derive(sqlx::Type, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[sqlx(type_name = "todo_permission")]
+#[sqlx(rename_all = "lowercase")]
+pub enum Permission {
+    // see it, and comment it
+    View,
+    // update and delete it too
+    Edit,
+}
+
+#[derive(sqlx::FromRow, Debug, Clone, Serialize, Deserialize)]
+pub struct TodoShare {
+    pub todo_id: i64,
+    pub user_id: i64,
+    pub permission: Permission,
+    pub ctime: DateTime<Utc>,
+}
+
+const SHARE_COLUMNS: &str = "todo_id, user_id, permission, ctime";
+
+#[allow(clippy::module_name_repetitions)]
+#[derive(Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PartialShare {
+    pub permission: Option<Permission>,
+}
+
+// Only the owner of a todo shares it, everyone it's visible to sees with whom
+#[allow(clippy::module_name_repetitions)]
+pub struct ShareModelAccessController;
+impl ShareModelAccessController {
+    pub async fn list(
+        database: &PostgresDatabase,
+        utx: &UserContext,
+        todo_id: i64,
+    ) -> Result<Vec<TodoShare>, model::Error> {
+        ModelAccessController::get(database, utx, todo_id).await?;
+
+        let sql =
+            format!("SELECT {SHARE_COLUMNS} FROM todo_share WHERE todo_id = $1 ORDER BY user_id");
+
+        let shares = sqlx::query_as::<_, TodoShare>(&sql)
+            .bind(todo_id)
+            .fetch_all(database)
+            .await?;
+
+        Ok(shares)
+    }
+
+    // shares the todo with the user, or changes the permission it's shared with
+    pub async fn set(
+        database: &PostgresDatabase,
+        utx: &UserContext,
+        todo_id: i64,
+        user_id: i64,
+        data: PartialShare,
+    ) -> Result<TodoShare, model::Error> {
+        let todo = check_owner(database, utx, todo_id).await?;
+        let Some(permission) = data.permission else {
+            return Err(model::Error::ValidationFailed(vec![FieldError::new(
+                "permission",
+                "is required",
+            )]));
+        };
+        if user_id == todo.cid {
+            return Err(model::Error::ValidationFailed(vec![FieldError::new(
+                "user_id",
+                "is the owner of the todo",
+            )]));
+        }
+        check_user(database, "user_id", user_id).await?;
+
+        let sql = format!(
+            "INSERT INTO todo_share (todo_id, user_id, permission) VALUES ($1, $2, $3) \
+             ON CONFLICT (todo_id, user_id) DO UPDATE SET permission = $3 RETURNING {SHARE_COLUMNS}"
+        );
+
+        let mut transaction = database.begin().await?;
+        let share = sqlx::query_as::<_, TodoShare>(&sql)
+            .bind(todo_id)
+            .bind(user_id)
+            .bind(permission)
+            .fetch_one(&mut *transaction)
+            .await?;
+        todo::touch_todo(&mut transaction, todo_id).await?;
+        transaction.commit().await?;
+
+        Ok(share)
+    }
+
+    pub async fn delete(
+        database: &PostgresDatabase,
+        utx: &UserContext,
+        todo_id: i64,
+        user_id: i64,
+    ) -> Result<TodoShare, model::Error> {
+        let todo = check_owner(database, utx, todo_id).await?;
+
+        let sql = format!(
+            "DELETE FROM todo_share WHERE todo_id = $1 AND user_id = $2 RETURNING {SHARE_COLUMNS}"
+        );
+
+        let mut transaction = database.begin().await?;
+        let share = sqlx::query_as::<_, TodoShare>(&sql)
+            .bind(todo_id)
+            .bind(user_id)
+            .fetch_one(&mut *transaction)
+            .await
+            .map_err(|sqlx_error| match sqlx_error {
+                sqlx::Error::RowNotFound => {
+                    model::Error::EntityNotFound("todo share", format!("{todo_id}/{user_id}"))
+                }
+                other => model::Error::SqlxError(other),
+            })?;
+        todo::touch_todo(&mut transaction, todo_id).await?;
+        // for the user, the todo is gone, unless it's still assigned to them
+        if todo.assignee_id != Some(user_id) {
+            event::publish_revoked(&mut transaction, &todo, user_id).await?;
+        }
+        transaction.commit().await?;
+
+        Ok(share)
+    }
+}
+
+// Utils
+
+// The permission of the user on a todo visible to them, the owner and the assignee can edit it
+pub async fn permission_of<'e>(
+    executor: impl PgExecutor<'e>,
+    utx: &UserContext,
+    todo: &Todo,
+) -> Result<Permission, model::Error> {
+    if todo.cid == utx.user_id || todo.assignee_id == Some(utx.user_id) {
+        return Ok(Permission::Edit);
+    }
+
+    let permission =
+        sqlx::query_scalar("SELECT permission FROM todo_share WHERE todo_id = $1 AND user_id = $2")
+            .bind(todo.id)
+            .bind(utx.user_id)
+            .fetch_optional(executor)
+            .await?;
+
+    Ok(permission.unwrap_or(Permission::View))
+}
+
+// An existing user, or the field error
+pub async fn check_user<'e>(
+    executor: impl PgExecutor<'e>,
+    field: &'static str,
+    user_id: i64,
+) -> Result<(), model::Error> {
+    let exists: bool = sqlx::query_scalar(r#"SELECT EXISTS (SELECT 1 FROM "user" WHERE id = $1)"#)
+        .bind(user_id)
+        .fetch_one(executor)
+        .await?;
+
+    if exists {
+        Ok(())
+    } else {
+        Err(model::Error::ValidationFailed(vec![FieldError::new(
+            field,
+            "is not a user",
+        )]))
+    }
+}
+
+// a todo the user sees without owning it is denied rather than not found
+async fn check_owner(
+    database: &PostgresDatabase,
+    utx: &UserContext,
+    todo_id: i64,
+) -> Result<Todo, model::Error> {
+    let todo = ModelAccessController::get(database, utx, todo_id).await?;
+
+    if todo.cid == utx.user_id {
+        Ok(todo)
+    } else {
+        Err(model::Error::AccessDenied("todo", todo_id.to_string()))
+    }
+}
+
+#[cfg(test)]
+#[path = "../_tests/model_share.rs"]
+mod tests;
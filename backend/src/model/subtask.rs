@@ -0,0 +1,183 @@
+use std::{collections::HashMap, str::FromStr};
+
+use serde_derive::{Deserialize, Serialize};
+use sqlx::{Postgres, Transaction};
+
+use crate::model;
+use crate::model::{todo::Todo, FieldError};
+use crate::security::UserContext;
+
+// with the owner id, the lock of the subtask trees of the owner
+const PARENT_LOCK_KEY: i32 = 7_343_922;
+
+// What closing a todo does to its open subtasks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CloseParentRule {
+    // a todo can only be closed once all its subtasks are closed
+    #[default]
+    Require,
+    // closing a todo closes all its subtasks
+    Cascade,
+}
+
+impl FromStr for CloseParentRule {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "require" => Ok(Self::Require),
+            "cascade" => Ok(Self::Cascade),
+            other => Err(format!(
+                "unknown close parent rule '{other}', expected 'require' or 'cascade'"
+            )),
+        }
+    }
+}
+
+// closed children / total children
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Progress {
+    pub closed: usize,
+    pub total: usize,
+}
+
+// A todo with its subtasks, recursively
+#[derive(Debug, Clone, Serialize)]
+pub struct TodoTree {
+    #[serde(flatten)]
+    pub todo: Todo,
+    pub progress: Progress,
+    pub children: Vec<Self>,
+}
+
+impl TodoTree {
+    // todos: the root and all its descendants
+    pub fn build(root_id: i64, todos: Vec<Todo>) -> Option<Self> {
+        let mut root = None;
+        let mut children_of: HashMap<i64, Vec<Todo>> = HashMap::new();
+        for todo in todos {
+            match todo.parent_id {
+                _ if todo.id == root_id => root = Some(todo),
+                Some(parent_id) => children_of.entry(parent_id).or_default().push(todo),
+                None => {}
+            }
+        }
+
+        root.map(|root| Self::with_children(root, &mut children_of))
+    }
+
+    fn with_children(todo: Todo, children_of: &mut HashMap<i64, Vec<Todo>>) -> Self {
+        let children: Vec<Self> = children_of
+            .remove(&todo.id)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|child| Self::with_children(child, children_of))
+            .collect();
+        let progress = Progress {
+            closed: children
+                .iter()
+                .filter(|child| child.todo.status == model::Status::Closed)
+                .count(),
+            total: children.len(),
+        };
+
+        Self {
+            todo,
+            progress,
+            children,
+        }
+    }
+}
+
+// The parent must be a todo of the user, and not the todo itself or one of its subtasks.
+// The parents of the user are changed one transaction at a time, until its end: two concurrent moves,
+// A under B and B under A, would both see no cycle
+pub async fn check_parent(
+    transaction: &mut Transaction<'_, Postgres>,
+    utx: &UserContext,
+    todo_id: Option<i64>,
+    parent_id: i64,
+) -> Result<(), model::Error> {
+    let invalid =
+        |message: &str| model::Error::ValidationFailed(vec![FieldError::new("parent_id", message)]);
+
+    sqlx::query("SELECT pg_advisory_xact_lock($1, ($2 % 2147483647)::INT)")
+        .bind(PARENT_LOCK_KEY)
+        .bind(utx.user_id)
+        .execute(&mut **transaction)
+        .await?;
+
+    // the parent and its ancestors, the todo must not be one of them
+    let sql = "WITH RECURSIVE ancestor AS ( \
+                 SELECT id, parent_id FROM todo WHERE id = $1 AND cid = $2 \
+                 UNION SELECT todo.id, todo.parent_id FROM todo JOIN ancestor ON todo.id = ancestor.parent_id \
+               ) SELECT id FROM ancestor";
+    let ancestors: Vec<i64> = sqlx::query_scalar(sql)
+        .bind(parent_id)
+        .bind(utx.user_id)
+        .fetch_all(&mut **transaction)
+        .await?;
+
+    if ancestors.is_empty() {
+        Err(invalid("is not one of your todos"))
+    } else if todo_id.is_some_and(|todo_id| ancestors.contains(&todo_id)) {
+        Err(invalid("would make the todo a subtask of itself"))
+    } else {
+        Ok(())
+    }
+}
+
+pub async fn has_open_descendants(
+    transaction: &mut Transaction<'_, Postgres>,
+    todo_id: i64,
+) -> Result<bool, model::Error> {
+    let sql = format!(
+        "{DESCENDANTS} SELECT EXISTS (SELECT 1 FROM todo JOIN descendant ON todo.id = descendant.id \
+         WHERE todo.status = 'open')"
+    );
+
+    Ok(sqlx::query_scalar(&sql)
+        .bind(todo_id)
+        .fetch_one(&mut **transaction)
+        .await?)
+}
+
+// Returns the ids of the subtasks closed
+pub async fn close_descendants(
+    transaction: &mut Transaction<'_, Postgres>,
+    todo_id: i64,
+) -> Result<Vec<i64>, model::Error> {
+    let sql = format!(
+        "{DESCENDANTS} UPDATE todo SET status = 'closed', completed_at = NOW(), mtime = NOW(), \
+         version = version + 1 WHERE status = 'open' AND id IN (SELECT id FROM descendant) RETURNING id"
+    );
+
+    Ok(sqlx::query_scalar(&sql)
+        .bind(todo_id)
+        .fetch_all(&mut **transaction)
+        .await?)
+}
+
+// the subtasks, recursively, that go with the todo when it's deleted
+pub async fn descendant_ids(
+    transaction: &mut Transaction<'_, Postgres>,
+    todo_id: i64,
+) -> Result<Vec<i64>, model::Error> {
+    let sql = format!("{DESCENDANTS} SELECT id FROM descendant ORDER BY id");
+
+    Ok(sqlx::query_scalar(&sql)
+        .bind(todo_id)
+        .fetch_all(&mut **transaction)
+        .await?)
+}
+
+// the subtasks of $1, recursively
+const DESCENDANTS: &str = "WITH RECURSIVE descendant AS ( \
+     SELECT id FROM todo WHERE parent_id = $1 \
+     UNION SELECT todo.id FROM todo JOIN descendant ON todo.parent_id = descendant.id \
+   )";
+
+#[cfg(test)]
+#[path = "../_tests/model_subtask.rs"]
+mod tests;
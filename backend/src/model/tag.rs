@@ -0,0 +1,97 @@
+use serde_derive::{Deserialize, Serialize};
+use sqlx::{Postgres, Transaction};
+
+use crate::model;
+use crate::model::{db::PostgresDatabase, FieldError};
+use crate::security::UserContext;
+
+// same as the name column, VARCHAR(64)
+const NAME_MAX_LEN: usize = 64;
+
+// A tag of the user, with the number of its todos labeled by it
+#[derive(sqlx::FromRow, Debug, Clone, Serialize, Deserialize)]
+pub struct TagUsage {
+    pub name: String,
+    pub count: i64,
+}
+
+pub struct TagModelAccessController;
+impl TagModelAccessController {
+    pub async fn list(
+        database: &PostgresDatabase,
+        utx: &UserContext,
+    ) -> Result<Vec<TagUsage>, model::Error> {
+        let sql_statement = "SELECT tag.name, COUNT(todo_tag.todo_id) AS count FROM tag \
+             LEFT JOIN todo_tag ON todo_tag.tag_id = tag.id \
+             WHERE tag.cid = $1 GROUP BY tag.id ORDER BY tag.name";
+
+        let tags = sqlx::query_as::<_, TagUsage>(sql_statement)
+            .bind(utx.user_id)
+            .fetch_all(database)
+            .await?;
+
+        Ok(tags)
+    }
+
+    // Replaces the tags of a todo of the user, the unknown tags are created.
+    // In the transaction of the todo change, so the todo and its tags change together
+    pub async fn set_todo_tags(
+        transaction: &mut Transaction<'_, Postgres>,
+        utx: &UserContext,
+        todo_id: i64,
+        names: &[String],
+    ) -> Result<(), model::Error> {
+        let names =
+            normalize_tags(names).map_err(|error| model::Error::ValidationFailed(vec![error]))?;
+
+        sqlx::query(
+            "INSERT INTO tag (cid, name) SELECT $1, UNNEST($2::TEXT[]) ON CONFLICT (cid, name) DO NOTHING",
+        )
+        .bind(utx.user_id)
+        .bind(&names)
+        .execute(&mut **transaction)
+        .await?;
+
+        sqlx::query("DELETE FROM todo_tag WHERE todo_id = $1")
+            .bind(todo_id)
+            .execute(&mut **transaction)
+            .await?;
+
+        sqlx::query(
+            "INSERT INTO todo_tag (todo_id, tag_id) SELECT $1, id FROM tag WHERE cid = $2 AND name = ANY($3)",
+        )
+        .bind(todo_id)
+        .bind(utx.user_id)
+        .bind(&names)
+        .execute(&mut **transaction)
+        .await?;
+
+        Ok(())
+    }
+}
+
+// The tags as stored : trimmed, without duplicates, in their first order
+pub fn normalize_tags(names: &[String]) -> Result<Vec<String>, FieldError> {
+    let mut tags: Vec<String> = Vec::with_capacity(names.len());
+
+    for name in names.iter().map(|name| name.trim()) {
+        if name.is_empty() {
+            return Err(FieldError::new("tags", "a tag must not be empty"));
+        }
+        if name.chars().count() > NAME_MAX_LEN {
+            return Err(FieldError::new(
+                "tags",
+                format!("a tag must be at most {NAME_MAX_LEN} characters"),
+            ));
+        }
+        if !tags.iter().any(|tag| tag == name) {
+            tags.push(name.to_string());
+        }
+    }
+
+    Ok(tags)
+}
+
+#[cfg(test)]
+#[path = "../_tests/model_tag.rs"]
+mod tests;
@@ -1,7 +1,23 @@
+use std::{fmt, str::FromStr};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize as DeserializeTrait, Deserializer};
 use serde_derive::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{PgExecutor, Postgres, QueryBuilder, Transaction};
 
+use crate::config::config;
 use crate::model;
-use crate::model::db::PostgresDatabase;
+use crate::model::attachment;
+use crate::model::event::{self, TodoEvent};
+use crate::model::notification::{self, Notification, NotificationEvent};
+use crate::model::page::{self, Cursor, Page, SortDirection};
+use crate::model::project::ProjectModelAccessController;
+use crate::model::recurrence::{self, Recurrence};
+use crate::model::share::{self, Permission};
+use crate::model::subtask::{self, CloseParentRule, TodoTree};
+use crate::model::tag::{normalize_tags, TagModelAccessController};
+use crate::model::{db::PostgresDatabase, FieldError};
 use crate::security::UserContext;
 
 #[derive(sqlx::FromRow, Debug, Clone, Default, Serialize, Deserialize)]
@@ -10,8 +26,41 @@ pub struct Todo {
     pub cid: i64,
     pub title: String,
     pub status: Status,
+    // Markdown
+    pub description: Option<String>,
+    pub due_at: Option<DateTime<Utc>>,
+    pub priority: Priority,
+    // set by the server when the status becomes closed
+    pub completed_at: Option<DateTime<Utc>>,
+    pub ctime: DateTime<Utc>,
+    pub mtime: DateTime<Utc>,
+    // incremented by every update, the ETag of the todo
+    pub version: i64,
+    // the tag names, sorted
+    pub tags: Vec<String>,
+    pub project_id: Option<i64>,
+    // the todos of an archived project are archived
+    pub archived: bool,
+    // the todo this one is a subtask of
+    pub parent_id: Option<i64>,
+    pub comment_count: i64,
+    // the user the todo is delegated to, who can edit it
+    pub assignee_id: Option<i64>,
+    // RRULE subset, closing the todo creates its next occurrence
+    pub recurrence: Option<String>,
 }
 
+// the columns of Todo, in the SELECT and RETURNING clauses
+const TODO_COLUMNS: &str =
+    "id, cid, title, status, description, due_at, priority, completed_at, ctime, mtime, version, \
+     ARRAY(SELECT tag.name::TEXT FROM todo_tag JOIN tag ON tag.id = todo_tag.tag_id \
+     WHERE todo_tag.todo_id = todo.id ORDER BY tag.name) AS tags, project_id, archived, parent_id, \
+     (SELECT COUNT(*) FROM comment WHERE comment.todo_id = todo.id) AS comment_count, assignee_id, recurrence";
+
+// the todos the user $2 can see : owned, assigned to them, or shared with them
+const VISIBLE_TO_USER: &str = "(cid = $2 OR assignee_id = $2 OR EXISTS \
+     (SELECT 1 FROM todo_share WHERE todo_share.todo_id = todo.id AND todo_share.user_id = $2))";
+
 // we need the sqlx macro to map the database enum type to that struct
 // it needs to be the same name than in the sql file
 // The Rust's side of enum must be Uppercase
@@ -25,12 +74,366 @@ pub enum Status {
     Closed,
 }
 
-#[allow(clippy::module_name_repetitions)]
+#[derive(sqlx::Type, Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[sqlx(type_name = "todo_priority")]
+#[sqlx(rename_all = "lowercase")]
+pub enum Priority {
+    Low,
+    #[default]
+    Medium,
+    High,
+    Urgent,
+}
+
+// the client writable fields, absent ones are left untouched.
+// The owner (cid) is not one of them, sending it is an error
+#[allow(clippy::module_name_repetitions, clippy::option_option)]
 #[derive(Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct PartialTodo {
-    pub cid: Option<i64>,
     pub title: Option<String>,
     pub status: Option<Status>,
+    pub priority: Option<Priority>,
+    // the nullable fields: None is absent, Some(None) is null and clears the field
+    #[serde(default, deserialize_with = "deserialize_present")]
+    pub description: Option<Option<String>>,
+    #[serde(default, deserialize_with = "deserialize_present")]
+    pub due_at: Option<Option<DateTime<Utc>>>,
+    // replaces all the tags of the todo
+    pub tags: Option<Vec<String>>,
+    // one of the not archived projects of the user, null to take it out of its project
+    #[serde(default, deserialize_with = "deserialize_present")]
+    pub project_id: Option<Option<i64>>,
+    // another todo of the user, null to make it a top level todo
+    #[serde(default, deserialize_with = "deserialize_present")]
+    pub parent_id: Option<Option<i64>>,
+    // any user, null to unassign it
+    #[serde(default, deserialize_with = "deserialize_present")]
+    pub assignee_id: Option<Option<i64>>,
+    // like "FREQ=WEEKLY;BYDAY=MO,FR", null to stop the recurrence
+    #[serde(default, deserialize_with = "deserialize_present")]
+    pub recurrence: Option<Option<String>>,
+}
+
+// A present field, even null, is Some. An absent one stays None thanks to serde(default)
+pub(super) fn deserialize_present<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    T: DeserializeTrait<'de>,
+    D: Deserializer<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+// The three PATCH flavors of the update route
+#[allow(clippy::module_name_repetitions)]
+pub enum TodoPatch {
+    // application/json, the present fields are set
+    Partial(PartialTodo),
+    // application/merge-patch+json (RFC 7396)
+    Merge(Value),
+    // application/json-patch+json (RFC 6902)
+    Json(json_patch::Patch),
+}
+
+// The writable fields as a json document, what the merge and json patches are applied to
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TodoDocument {
+    title: String,
+    status: Status,
+    priority: Priority,
+    description: Option<String>,
+    due_at: Option<DateTime<Utc>>,
+    tags: Vec<String>,
+    project_id: Option<i64>,
+    parent_id: Option<i64>,
+    assignee_id: Option<i64>,
+    recurrence: Option<String>,
+}
+
+// The query parameters of the todos list, all optional
+#[allow(clippy::module_name_repetitions)]
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TodoListOptions {
+    // all the todos the user can see by default
+    pub scope: Option<TodoScope>,
+    pub status: Option<Status>,
+    // case insensitive substring of the title
+    pub title: Option<String>,
+    // the name of one of its tags
+    pub tag: Option<String>,
+    pub project_id: Option<i64>,
+    // the subtasks of that todo
+    pub parent_id: Option<i64>,
+    // false by default, the todos of the archived projects are hidden
+    pub archived: Option<bool>,
+    pub created_before: Option<DateTime<Utc>>,
+    pub created_after: Option<DateTime<Utc>>,
+    // "field" or "field:asc" or "field:desc", see TodoSort
+    pub sort: Option<String>,
+    // the next_cursor of the previous page
+    pub cursor: Option<String>,
+    pub limit: Option<u16>,
+}
+
+// Which of the todos the user can see are listed
+#[allow(clippy::module_name_repetitions)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum TodoScope {
+    // owned by the user
+    Mine,
+    // assigned to the user
+    Assigned,
+    // shared with the user
+    Shared,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TodoSortField {
+    Id,
+    Ctime,
+    Mtime,
+    Title,
+    Status,
+    Priority,
+}
+
+impl TodoSortField {
+    // also the name in the sort parameter
+    const fn column(self) -> &'static str {
+        match self {
+            Self::Id => "id",
+            Self::Ctime => "ctime",
+            Self::Mtime => "mtime",
+            Self::Title => "title",
+            Self::Status => "status",
+            Self::Priority => "priority",
+        }
+    }
+
+    // the cursor keeps the sort key as text, cast back to the column type
+    const fn sql_type(self) -> &'static str {
+        match self {
+            Self::Id => "BIGINT",
+            Self::Ctime | Self::Mtime => "TIMESTAMPTZ",
+            Self::Title => "TEXT",
+            Self::Status => "todo_status",
+            Self::Priority => "todo_priority",
+        }
+    }
+
+    // the sort key as the column's text, which sql_type casts back without error
+    fn is_key(self, key: &str) -> bool {
+        match self {
+            Self::Id => key.parse::<i64>().is_ok(),
+            Self::Ctime | Self::Mtime => {
+                DateTime::parse_from_str(key, "%Y-%m-%d %H:%M:%S%.f%#z").is_ok()
+            }
+            Self::Title => true,
+            Self::Status => matches!(key, "open" | "closed"),
+            Self::Priority => matches!(key, "low" | "medium" | "high" | "urgent"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TodoSort {
+    pub field: TodoSortField,
+    pub direction: SortDirection,
+}
+
+// the newest first
+impl Default for TodoSort {
+    fn default() -> Self {
+        Self {
+            field: TodoSortField::Id,
+            direction: SortDirection::Desc,
+        }
+    }
+}
+
+impl FromStr for TodoSort {
+    type Err = FieldError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (field, direction) = value.split_once(':').unwrap_or((value, "asc"));
+
+        let field = match field {
+            "id" => TodoSortField::Id,
+            "ctime" => TodoSortField::Ctime,
+            "mtime" => TodoSortField::Mtime,
+            "title" => TodoSortField::Title,
+            "status" => TodoSortField::Status,
+            "priority" => TodoSortField::Priority,
+            _ => {
+                return Err(FieldError::new(
+                    "sort",
+                    "must be one of id, ctime, mtime, title, status, priority",
+                ))
+            }
+        };
+        let direction = match direction {
+            "asc" => SortDirection::Asc,
+            "desc" => SortDirection::Desc,
+            _ => return Err(FieldError::new("sort", "direction must be asc or desc")),
+        };
+
+        Ok(Self { field, direction })
+    }
+}
+
+impl fmt::Display for TodoSort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let direction = match self.direction {
+            SortDirection::Asc => "asc",
+            SortDirection::Desc => "desc",
+        };
+        write!(f, "{}:{direction}", self.field.column())
+    }
+}
+
+impl TodoListOptions {
+    fn validate(&self) -> Result<(TodoSort, u16, Option<Cursor>), model::Error> {
+        let mut errors = Vec::new();
+
+        let sort = self
+            .sort
+            .as_deref()
+            .map_or_else(|| Ok(TodoSort::default()), str::parse)
+            .unwrap_or_else(|error| {
+                errors.push(error);
+                TodoSort::default()
+            });
+        let limit = page::validate_limit(self.limit).unwrap_or_else(|error| {
+            errors.push(error);
+            page::DEFAULT_PAGE_LIMIT
+        });
+        let cursor = self.cursor.as_deref().and_then(|cursor| {
+            Cursor::decode(cursor, &sort.to_string(), |key| sort.field.is_key(key))
+                .map_err(|error| errors.push(error))
+                .ok()
+        });
+
+        if errors.is_empty() {
+            Ok((sort, limit, cursor))
+        } else {
+            Err(model::Error::ValidationFailed(errors))
+        }
+    }
+
+    // the WHERE clause, shared by the page and the total count
+    fn push_filters(&self, query: &mut QueryBuilder<'_, Postgres>, utx: &UserContext) {
+        match self.scope {
+            Some(TodoScope::Mine) => {
+                query.push(" WHERE cid = ").push_bind(utx.user_id);
+            }
+            Some(TodoScope::Assigned) => {
+                query.push(" WHERE assignee_id = ").push_bind(utx.user_id);
+            }
+            Some(TodoScope::Shared) => {
+                query
+                    .push(" WHERE EXISTS (SELECT 1 FROM todo_share WHERE todo_share.todo_id = todo.id AND todo_share.user_id = ")
+                    .push_bind(utx.user_id)
+                    .push(")");
+            }
+            None => {
+                query
+                    .push(" WHERE (cid = ")
+                    .push_bind(utx.user_id)
+                    .push(" OR assignee_id = ")
+                    .push_bind(utx.user_id)
+                    .push(" OR EXISTS (SELECT 1 FROM todo_share WHERE todo_share.todo_id = todo.id AND todo_share.user_id = ")
+                    .push_bind(utx.user_id)
+                    .push("))");
+            }
+        }
+
+        if let Some(status) = &self.status {
+            query.push(" AND status = ").push_bind(status.clone());
+        }
+        if let Some(title) = &self.title {
+            query
+                .push(" AND title ILIKE ")
+                .push_bind(page::contains_pattern(title));
+        }
+        query
+            .push(" AND archived = ")
+            .push_bind(self.archived.unwrap_or(false));
+        if let Some(project_id) = self.project_id {
+            query.push(" AND project_id = ").push_bind(project_id);
+        }
+        if let Some(parent_id) = self.parent_id {
+            query.push(" AND parent_id = ").push_bind(parent_id);
+        }
+        if let Some(tag) = &self.tag {
+            query
+                .push(
+                    " AND EXISTS (SELECT 1 FROM todo_tag JOIN tag ON tag.id = todo_tag.tag_id \
+                     WHERE todo_tag.todo_id = todo.id AND tag.name = ",
+                )
+                .push_bind(tag.trim().to_string())
+                .push(")");
+        }
+        if let Some(created_before) = self.created_before {
+            query.push(" AND ctime < ").push_bind(created_before);
+        }
+        if let Some(created_after) = self.created_after {
+            query.push(" AND ctime > ").push_bind(created_after);
+        }
+    }
+}
+
+// A listed todo, with its sort key to build the cursor
+#[derive(sqlx::FromRow)]
+struct TodoListRow {
+    #[sqlx(flatten)]
+    todo: Todo,
+    sort_key: String,
+}
+
+// same as the title column, VARCHAR(63)
+const TITLE_MAX_LEN: usize = 63;
+const DESCRIPTION_MAX_LEN: usize = 65_535;
+
+impl PartialTodo {
+    // Checks every present field, so the client gets all its errors at once
+    fn validate(&self) -> Result<(), model::Error> {
+        let mut errors = Vec::new();
+
+        if let Some(title) = &self.title {
+            if title.trim().is_empty() {
+                errors.push(FieldError::new("title", "must not be empty"));
+            } else if title.chars().count() > TITLE_MAX_LEN {
+                errors.push(FieldError::new(
+                    "title",
+                    format!("must be at most {TITLE_MAX_LEN} characters"),
+                ));
+            }
+        }
+        if let Some(Err(error)) = self.tags.as_deref().map(normalize_tags) {
+            errors.push(error);
+        }
+        if let Some(Some(recurrence)) = &self.recurrence {
+            if let Err(error) = recurrence.parse::<Recurrence>() {
+                errors.push(error);
+            }
+        }
+        if let Some(Some(description)) = &self.description {
+            if description.chars().count() > DESCRIPTION_MAX_LEN {
+                errors.push(FieldError::new(
+                    "description",
+                    format!("must be at most {DESCRIPTION_MAX_LEN} characters"),
+                ));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(model::Error::ValidationFailed(errors))
+        }
+    }
 }
 
 pub struct ModelAccessController;
@@ -40,84 +443,610 @@ impl ModelAccessController {
         utx: &UserContext,
         data: PartialTodo,
     ) -> Result<Todo, model::Error> {
-        let sql = "INSERT INTO todo (cid, title) VALUES ($1, $2) RETURNING id, cid, title, status";
+        data.validate()?;
+
+        // the todo and its tags are created together
+        let mut transaction = database.begin().await?;
+
+        let project_id = data.project_id.flatten();
+        if let Some(project_id) = project_id {
+            ProjectModelAccessController::check_for_todo(&mut *transaction, utx, project_id)
+                .await?;
+        }
+
+        let parent_id = data.parent_id.flatten();
+        if let Some(parent_id) = parent_id {
+            subtask::check_parent(&mut transaction, utx, None, parent_id).await?;
+        }
+        let assignee_id = data.assignee_id.flatten();
+        if let Some(assignee_id) = assignee_id {
+            share::check_user(&mut *transaction, "assignee_id", assignee_id).await?;
+        }
+
+        let recurrence = parse_recurrence(data.recurrence.flatten().as_deref())?;
+
+        let sql = "INSERT INTO todo (cid, title, status, priority, description, due_at, completed_at, project_id, parent_id, assignee_id, recurrence) \
+             VALUES ($1, $2, COALESCE($3, 'open'::todo_status), COALESCE($4, 'medium'::todo_priority), $5, $6, \
+             CASE WHEN $3 = 'closed'::todo_status THEN NOW() END, $7, $8, $9, $10) RETURNING id";
+
+        let query = sqlx::query_scalar::<_, i64>(sql)
+            .bind(utx.user_id) // the owner is always the user of the context, never the client data
+            .bind(data.title.unwrap_or_else(|| "untitled".into()))
+            .bind(data.status)
+            .bind(data.priority)
+            .bind(data.description.flatten())
+            .bind(data.due_at.flatten())
+            .bind(project_id)
+            .bind(parent_id)
+            .bind(assignee_id)
+            .bind(recurrence.map(|recurrence| recurrence.to_string()));
+
+        let id = query.fetch_one(&mut *transaction).await?;
+
+        if let Some(tags) = &data.tags {
+            TagModelAccessController::set_todo_tags(&mut transaction, utx, id, tags).await?;
+        }
+        if let Some(assignee_id) = assignee_id {
+            notify_assigned(&mut transaction, utx, id, assignee_id).await?;
+        }
 
-        let query = sqlx::query_as::<_, Todo>(sql)
-            .bind(utx.user_id) // FIXME : should come from user context
-            .bind(data.title.unwrap_or_else(|| "untitled".into()));
+        let todo = select_todo(&mut *transaction, utx, id).await?;
+        event::publish(&mut transaction, TodoEvent::Created, &todo).await?;
 
-        let todo = query.fetch_one(database).await?;
+        transaction.commit().await?;
 
         Ok(todo)
     }
 
     pub async fn list(
         database: &PostgresDatabase,
-        _utx: &UserContext,
-    ) -> Result<Vec<Todo>, model::Error> {
-        let sql_statement = "SELECT id, cid, title, status FROM todo ORDER BY id DESC";
+        utx: &UserContext,
+        options: &TodoListOptions,
+    ) -> Result<Page<Todo>, model::Error> {
+        let (sort, limit, cursor) = options.validate()?;
+        let column = sort.field.column();
+        let direction = sort.direction.as_sql();
+
+        let mut count_query = QueryBuilder::new("SELECT COUNT(*) FROM todo");
+        options.push_filters(&mut count_query, utx);
+        let total: i64 = count_query.build_query_scalar().fetch_one(database).await?;
+
+        // the columns come from TodoSortField, never from the client data
+        let mut query = QueryBuilder::new(format!(
+            "SELECT {TODO_COLUMNS}, {column}::TEXT AS sort_key FROM todo"
+        ));
+        options.push_filters(&mut query, utx);
+        if let Some(cursor) = cursor {
+            // the id breaks the ties of the sort key, so no todo is skipped or repeated
+            query
+                .push(format!(
+                    " AND ({column}, id) {} (",
+                    sort.direction.after_operator()
+                ))
+                .push_bind(cursor.key)
+                .push(format!("::{}, ", sort.field.sql_type()))
+                .push_bind(cursor.id)
+                .push(")");
+        }
+        // one more than the limit, to know if there's a next page
+        query
+            .push(format!(
+                " ORDER BY {column} {direction}, id {direction} LIMIT "
+            ))
+            .push_bind(i64::from(limit) + 1);
 
-        let query = sqlx::query_as::<_, Todo>(sql_statement);
+        let mut rows: Vec<TodoListRow> = query.build_query_as().fetch_all(database).await?;
 
-        let todos = query.fetch_all(database).await?;
+        let next_cursor = if rows.len() > usize::from(limit) {
+            rows.truncate(usize::from(limit));
+            rows.last().map(|row| {
+                Cursor {
+                    sort: sort.to_string(),
+                    key: row.sort_key.clone(),
+                    id: row.todo.id,
+                }
+                .encode()
+            })
+        } else {
+            None
+        };
 
-        Ok(todos)
+        Ok(Page {
+            items: rows.into_iter().map(|row| row.todo).collect(),
+            next_cursor,
+            total,
+        })
     }
 
     pub async fn get(
         database: &PostgresDatabase,
-        _utx: &UserContext,
+        utx: &UserContext,
         id: i64,
     ) -> Result<Todo, model::Error> {
-        let sql_statement = "SELECT id, cid, title, status FROM todo WHERE id = $1";
-
-        let sql_query = sqlx::query_as::<_, Todo>(sql_statement).bind(id);
+        select_todo(database, utx, id).await
+    }
 
-        let todo = sql_query.fetch_one(database).await;
+    // The todo, if the user can edit it, not only see it
+    pub async fn get_for_edit(
+        database: &PostgresDatabase,
+        utx: &UserContext,
+        id: i64,
+    ) -> Result<Todo, model::Error> {
+        let todo = select_todo(database, utx, id).await?;
 
-        handle_fetch_one_result(todo, id)
+        if share::permission_of(database, utx, &todo).await? == Permission::Edit {
+            Ok(todo)
+        } else {
+            Err(model::Error::AccessDenied("todo", id.to_string()))
+        }
     }
 
+    // expected_versions are the If-Match of the client, None to update whatever the current version
     pub async fn update(
         database: &PostgresDatabase,
-        _utx: &UserContext,
+        utx: &UserContext,
         id: i64,
         data: PartialTodo,
+        expected_versions: Option<Vec<i64>>,
     ) -> Result<Todo, model::Error> {
-        let sql_statement =
-            "UPDATE todo SET (title, status) = ($2, $3) WHERE id = $1 RETURNING id, title, status";
+        Self::patch(
+            database,
+            utx,
+            id,
+            TodoPatch::Partial(data),
+            expected_versions,
+        )
+        .await
+    }
 
-        let sql_query = sqlx::query_as::<_, Todo>(sql_statement)
-            .bind(id)
-            .bind(data.title)
-            .bind(data.status);
+    pub async fn patch(
+        database: &PostgresDatabase,
+        utx: &UserContext,
+        id: i64,
+        patch: TodoPatch,
+        expected_versions: Option<Vec<i64>>,
+    ) -> Result<Todo, model::Error> {
+        let close_parent_rule = config().todos.close_parent;
+
+        patch_todo(
+            database,
+            utx,
+            id,
+            patch,
+            expected_versions,
+            close_parent_rule,
+        )
+        .await
+    }
+
+    // The todo with all its subtasks, recursively
+    pub async fn get_tree(
+        database: &PostgresDatabase,
+        utx: &UserContext,
+        id: i64,
+    ) -> Result<TodoTree, model::Error> {
+        let sql_statement = format!(
+            "WITH RECURSIVE subtree AS ( \
+               SELECT id FROM todo WHERE id = $1 AND {VISIBLE_TO_USER} \
+               UNION SELECT todo.id FROM todo JOIN subtree ON todo.parent_id = subtree.id \
+             ) SELECT {TODO_COLUMNS} FROM todo WHERE id IN (SELECT id FROM subtree) ORDER BY id"
+        );
 
-        let todo = sql_query.fetch_one(database).await;
+        let todos = sqlx::query_as::<_, Todo>(&sql_statement)
+            .bind(id)
+            .bind(utx.user_id)
+            .fetch_all(database)
+            .await?;
 
-        handle_fetch_one_result(todo, id)
+        TodoTree::build(id, todos)
+            .ok_or_else(|| model::Error::EntityNotFound("todo", id.to_string()))
     }
 
     pub async fn delete(
         database: &PostgresDatabase,
-        _utx: &UserContext,
+        utx: &UserContext,
         id: i64,
+        expected_versions: Option<Vec<i64>>,
     ) -> Result<Todo, model::Error> {
-        let sql_statement = "DELETE FROM todo WHERE id = $1 RETURNING *";
+        let mut transaction = database.begin().await?;
+
+        let todo = lock_todo(&mut transaction, utx, id, expected_versions).await?;
+        // its subtasks and all their attachments go with it
+        let storage_keys = attachment::subtree_storage_keys(&mut transaction, id).await?;
+        let subtask_ids = subtask::descendant_ids(&mut transaction, id).await?;
+        let subtasks = select_todos(&mut *transaction, &subtask_ids).await?;
+        // while their shares still tell who can see them
+        let user_ids = event::visible_to(&mut transaction, &todo).await?;
+        let mut subtask_user_ids = Vec::with_capacity(subtasks.len());
+        for subtask in &subtasks {
+            subtask_user_ids.push(event::visible_to(&mut transaction, subtask).await?);
+        }
+
+        let sql_statement =
+            format!("DELETE FROM todo WHERE id = $1 AND cid = $2 RETURNING {TODO_COLUMNS}");
 
-        let sql_query = sqlx::query_as::<_, Todo>(sql_statement).bind(id);
+        let sql_query = sqlx::query_as::<_, Todo>(&sql_statement)
+            .bind(id)
+            .bind(todo.cid);
+
+        let todo = sql_query.fetch_one(&mut *transaction).await;
+        let todo = handle_fetch_one_result(todo, id)?;
+        event::publish_to(&mut transaction, TodoEvent::Deleted, &todo, user_ids).await?;
+        for (subtask, user_ids) in subtasks.iter().zip(subtask_user_ids) {
+            event::publish_to(&mut transaction, TodoEvent::Deleted, subtask, user_ids).await?;
+        }
+
+        transaction.commit().await?;
 
-        let todo = sql_query.fetch_one(database).await;
+        attachment::delete_contents(&storage_keys).await;
 
-        handle_fetch_one_result(todo, id)
+        Ok(todo)
     }
 }
 
 // Utils
 
-fn handle_fetch_one_result(
-    result: Result<Todo, sqlx::Error>,
+// patch with the close parent rule as a parameter, rather than the configured one
+pub(super) async fn patch_todo(
+    database: &PostgresDatabase,
+    utx: &UserContext,
     id: i64,
+    patch: TodoPatch,
+    expected_versions: Option<Vec<i64>>,
+    close_parent_rule: CloseParentRule,
 ) -> Result<Todo, model::Error> {
+    // the patch applies to the current todo, locked until the update
+    let mut transaction = database.begin().await?;
+
+    let todo = lock_todo(&mut transaction, utx, id, expected_versions).await?;
+
+    let mut data = match patch {
+        TodoPatch::Partial(data) => data,
+        TodoPatch::Merge(merge_patch) => patch_document(&todo, |document| {
+            json_patch::merge(document, &merge_patch);
+            Ok(())
+        })?,
+        TodoPatch::Json(json_patch) => patch_document(&todo, |document| {
+            json_patch::patch(document, &json_patch)
+                .map_err(|error| validation_error("patch", error.to_string()))
+        })?,
+    };
+    data.validate()?;
+
+    // the project, the parent and the tags are the owner's ones, whoever edits the todo
+    let owner = UserContext { user_id: todo.cid };
+
+    // an unchanged project is not checked, the todos of an archived project stay editable
+    if data.project_id == Some(todo.project_id) {
+        data.project_id = None;
+    }
+    if let Some(Some(project_id)) = data.project_id {
+        ProjectModelAccessController::check_for_todo(&mut *transaction, &owner, project_id).await?;
+    }
+    if data.parent_id == Some(todo.parent_id) {
+        data.parent_id = None;
+    }
+    if let Some(Some(parent_id)) = data.parent_id {
+        subtask::check_parent(&mut transaction, &owner, Some(id), parent_id).await?;
+    }
+    if data.assignee_id == Some(todo.assignee_id) {
+        data.assignee_id = None;
+    }
+    let previous_assignee_id = data.assignee_id.and(todo.assignee_id);
+    if let Some(Some(assignee_id)) = data.assignee_id {
+        share::check_user(&mut *transaction, "assignee_id", assignee_id).await?;
+    }
+
+    let closing = todo.status == Status::Open && data.status == Some(Status::Closed);
+    if closing
+        && close_parent_rule == CloseParentRule::Require
+        && subtask::has_open_descendants(&mut transaction, id).await?
+    {
+        return Err(validation_error(
+            "status",
+            String::from("can't be closed while a subtask is open"),
+        ));
+    }
+
+    let (recurrence, next_occurrence) = recurrence_change(&todo, &data, closing)?;
+    let due_at = data.due_at.unwrap_or(todo.due_at);
+
+    // absent fields of the PartialTodo keep their current value,
+    // completed_at is kept while closed, set when it becomes closed, cleared when open
+    let sql_statement = "UPDATE todo SET title = COALESCE($3, title), status = COALESCE($4, status), \
+         priority = COALESCE($5, priority), \
+         description = CASE WHEN $6 THEN $7 ELSE description END, \
+         due_at = CASE WHEN $8 THEN $9 ELSE due_at END, \
+         project_id = CASE WHEN $10 THEN $11 ELSE project_id END, \
+         parent_id = CASE WHEN $12 THEN $13 ELSE parent_id END, \
+         assignee_id = CASE WHEN $14 THEN $15 ELSE assignee_id END, \
+         recurrence = CASE WHEN $16 THEN $17 ELSE recurrence END, \
+         archived = CASE WHEN $10 THEN FALSE ELSE archived END, \
+         completed_at = CASE WHEN COALESCE($4, status) = 'closed' THEN COALESCE(completed_at, NOW()) END, \
+         mtime = NOW(), version = version + 1 \
+         WHERE id = $1 AND cid = $2 RETURNING id";
+
+    let updated = sqlx::query_scalar::<_, i64>(sql_statement)
+        .bind(id)
+        .bind(todo.cid)
+        .bind(data.title)
+        .bind(data.status)
+        .bind(data.priority)
+        .bind(data.description.is_some())
+        .bind(data.description.flatten())
+        .bind(data.due_at.is_some())
+        .bind(data.due_at.flatten())
+        .bind(data.project_id.is_some())
+        .bind(data.project_id.flatten())
+        .bind(data.parent_id.is_some())
+        .bind(data.parent_id.flatten())
+        .bind(data.assignee_id.is_some())
+        .bind(data.assignee_id.flatten())
+        .bind(recurrence.is_some())
+        .bind(recurrence.flatten())
+        .fetch_one(&mut *transaction)
+        .await;
+    handle_fetch_one_result(updated, id)?;
+
+    let cascade = closing && close_parent_rule == CloseParentRule::Cascade;
+    let (created_ids, closed_ids) =
+        close_cascades(&mut transaction, id, next_occurrence, due_at, cascade).await?;
+
+    if let Some(tags) = &data.tags {
+        TagModelAccessController::set_todo_tags(&mut transaction, &owner, id, tags).await?;
+    }
+    if let Some(Some(assignee_id)) = data.assignee_id {
+        notify_assigned(&mut transaction, utx, id, assignee_id).await?;
+    }
+
+    // as the owner, the user may not see it anymore, like an assignee who unassigned themselves
+    let todo = select_todo(&mut *transaction, &owner, id).await?;
+    publish_changed(&mut transaction, TodoEvent::Created, &created_ids).await?;
+    publish_changed(&mut transaction, TodoEvent::Updated, &closed_ids).await?;
+    event::publish(&mut transaction, TodoEvent::Updated, &todo).await?;
+    if let Some(user_id) = previous_assignee_id {
+        event::publish_unassigned(&mut transaction, &todo, user_id).await?;
+    }
+
+    transaction.commit().await?;
+
+    Ok(todo)
+}
+
+// Publishes the todos changed along with another one, like by a cascade, once changed
+pub(super) async fn publish_changed(
+    transaction: &mut Transaction<'_, Postgres>,
+    event: TodoEvent,
+    ids: &[i64],
+) -> Result<(), model::Error> {
+    for todo in select_todos(&mut **transaction, ids).await? {
+        event::publish(transaction, event, &todo).await?;
+    }
+
+    Ok(())
+}
+
+// A new version of the todo, when what it shows changes without a write of its own, like its comment_count
+// or who it's shared with
+pub(super) async fn touch_todo(
+    transaction: &mut Transaction<'_, Postgres>,
+    todo_id: i64,
+) -> Result<(), model::Error> {
+    sqlx::query("UPDATE todo SET mtime = NOW(), version = version + 1 WHERE id = $1")
+        .bind(todo_id)
+        .execute(&mut **transaction)
+        .await?;
+
+    publish_changed(transaction, TodoEvent::Updated, &[todo_id]).await
+}
+
+// For a reminder, not scoped to a user : the todo if it's still open and due at that date
+pub async fn due_todo(
+    database: &PostgresDatabase,
+    id: i64,
+    due_at: DateTime<Utc>,
+) -> Result<Option<Todo>, model::Error> {
+    let sql_statement = format!(
+        "SELECT {TODO_COLUMNS} FROM todo WHERE id = $1 AND status = 'open' AND due_at = $2"
+    );
+
+    let todo = sqlx::query_as::<_, Todo>(&sql_statement)
+        .bind(id)
+        .bind(due_at)
+        .fetch_optional(database)
+        .await?;
+
+    Ok(todo)
+}
+
+// scoped to the user, a todo they can't see is just not found, so its existence doesn't leak
+async fn select_todo<'e>(
+    executor: impl PgExecutor<'e>,
+    utx: &UserContext,
+    id: i64,
+) -> Result<Todo, model::Error> {
+    let sql_statement =
+        format!("SELECT {TODO_COLUMNS} FROM todo WHERE id = $1 AND {VISIBLE_TO_USER}");
+
+    let todo = sqlx::query_as::<_, Todo>(&sql_statement)
+        .bind(id)
+        .bind(utx.user_id)
+        .fetch_one(executor)
+        .await;
+
+    handle_fetch_one_result(todo, id)
+}
+
+// not scoped to a user, for the todos changed along with one the user can edit
+async fn select_todos<'e>(
+    executor: impl PgExecutor<'e>,
+    ids: &[i64],
+) -> Result<Vec<Todo>, model::Error> {
+    if ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let sql_statement = format!("SELECT {TODO_COLUMNS} FROM todo WHERE id = ANY($1) ORDER BY id");
+
+    Ok(sqlx::query_as::<_, Todo>(&sql_statement)
+        .bind(ids)
+        .fetch_all(executor)
+        .await?)
+}
+
+// The todo, locked until the end of the transaction, if the user can edit it
+// and it's still at the version the client has
+async fn lock_todo(
+    transaction: &mut Transaction<'_, Postgres>,
+    utx: &UserContext,
+    id: i64,
+    expected_versions: Option<Vec<i64>>,
+) -> Result<Todo, model::Error> {
+    let sql_statement =
+        format!("SELECT {TODO_COLUMNS} FROM todo WHERE id = $1 AND {VISIBLE_TO_USER} FOR UPDATE");
+
+    let todo = sqlx::query_as::<_, Todo>(&sql_statement)
+        .bind(id)
+        .bind(utx.user_id)
+        .fetch_one(&mut **transaction)
+        .await;
+    let todo = handle_fetch_one_result(todo, id)?;
+
+    if share::permission_of(&mut **transaction, utx, &todo).await? != Permission::Edit {
+        return Err(model::Error::AccessDenied("todo", id.to_string()));
+    }
+
+    match expected_versions {
+        Some(versions) if !versions.contains(&todo.version) => Err(model::Error::VersionConflict(
+            "todo",
+            id.to_string(),
+            todo.version,
+        )),
+        _ => Ok(todo),
+    }
+}
+
+// the new assignee, unless they assigned it to themselves
+async fn notify_assigned(
+    transaction: &mut Transaction<'_, Postgres>,
+    utx: &UserContext,
+    id: i64,
+    assignee_id: i64,
+) -> Result<(), model::Error> {
+    if assignee_id == utx.user_id {
+        return Ok(());
+    }
+    let notification = Notification {
+        event: NotificationEvent::Assigned,
+        user_id: assignee_id,
+        todo_id: id,
+        comment_id: None,
+    };
+
+    notification::notify(&mut **transaction, &notification, None).await
+}
+
+// the stored rules are always valid, only the client ones can fail
+fn parse_recurrence(recurrence: Option<&str>) -> Result<Option<Recurrence>, model::Error> {
+    recurrence
+        .map(str::parse)
+        .transpose()
+        .map_err(|error| model::Error::ValidationFailed(vec![error]))
+}
+
+// The todos changed by a close: its next occurrence is created, and its open subtasks closed.
+// Returns the ids of both, for their changes to be published
+async fn close_cascades(
+    transaction: &mut Transaction<'_, Postgres>,
+    id: i64,
+    next_occurrence: Option<Recurrence>,
+    due_at: Option<DateTime<Utc>>,
+    close_subtasks: bool,
+) -> Result<(Vec<i64>, Vec<i64>), model::Error> {
+    let mut created_ids = Vec::new();
+    if let Some(rule) = next_occurrence {
+        // after its due date, or after now without one
+        let after = due_at.unwrap_or_else(Utc::now);
+        created_ids.push(recurrence::create_next_occurrence(transaction, id, &rule, after).await?);
+    }
+
+    let closed_ids = if close_subtasks {
+        subtask::close_descendants(transaction, id).await?
+    } else {
+        Vec::new()
+    };
+
+    Ok((created_ids, closed_ids))
+}
+
+// The recurrence to set, if any, and the rule of the next occurrence to create.
+// Closing a recurring todo creates its next occurrence, the rule moves to it
+#[allow(clippy::option_option)]
+fn recurrence_change(
+    todo: &Todo,
+    data: &PartialTodo,
+    closing: bool,
+) -> Result<(Option<Option<String>>, Option<Recurrence>), model::Error> {
+    let recurrence = match &data.recurrence {
+        Some(recurrence) => parse_recurrence(recurrence.as_deref())?,
+        None => parse_recurrence(todo.recurrence.as_deref())?,
+    };
+
+    Ok(match recurrence {
+        Some(recurrence) if closing => (Some(None), Some(recurrence)),
+        recurrence => (
+            data.recurrence
+                .is_some()
+                .then(|| recurrence.map(|recurrence| recurrence.to_string())),
+            None,
+        ),
+    })
+}
+
+// The writable fields of todo, patched as a json document
+fn patch_document(
+    todo: &Todo,
+    apply: impl FnOnce(&mut Value) -> Result<(), model::Error>,
+) -> Result<PartialTodo, model::Error> {
+    let mut document = serde_json::to_value(TodoDocument {
+        title: todo.title.clone(),
+        status: todo.status.clone(),
+        priority: todo.priority,
+        description: todo.description.clone(),
+        due_at: todo.due_at,
+        tags: todo.tags.clone(),
+        project_id: todo.project_id,
+        parent_id: todo.parent_id,
+        assignee_id: todo.assignee_id,
+        recurrence: todo.recurrence.clone(),
+    })
+    .map_err(|error| validation_error("patch", error.to_string()))?;
+
+    apply(&mut document)?;
+
+    // a removed field, or an unknown or mistyped one, makes the document invalid
+    let document: TodoDocument = serde_json::from_value(document)
+        .map_err(|error| validation_error("patch", error.to_string()))?;
+
+    Ok(PartialTodo {
+        title: Some(document.title),
+        status: Some(document.status),
+        priority: Some(document.priority),
+        description: Some(document.description),
+        due_at: Some(document.due_at),
+        tags: Some(document.tags),
+        project_id: Some(document.project_id),
+        parent_id: Some(document.parent_id),
+        assignee_id: Some(document.assignee_id),
+        recurrence: Some(document.recurrence),
+    })
+}
+
+fn validation_error(field: &'static str, message: String) -> model::Error {
+    model::Error::ValidationFailed(vec![FieldError::new(field, message)])
+}
+
+fn handle_fetch_one_result<T>(result: Result<T, sqlx::Error>, id: i64) -> Result<T, model::Error> {
     result.map_err(|sqlx_error| match sqlx_error {
         sqlx::Error::RowNotFound => model::Error::EntityNotFound("todo", id.to_string()),
         other => model::Error::SqlxError(other),
@@ -0,0 +1,123 @@
+use serde_derive::{Deserialize, Serialize};
+
+use crate::model;
+use crate::model::db::PostgresDatabase;
+use crate::security;
+
+// the password hash and token salt are never part of the User, so they can't leak in a response
+#[derive(sqlx::FromRow, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct User {
+    pub id: i64,
+    pub username: String,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct UserCredentials {
+    pub username: String,
+    pub password: String,
+}
+
+#[derive(sqlx::FromRow)]
+pub struct UserForAuth {
+    pub id: i64,
+    pub username: String,
+    pub pwd: String,
+    pub token_salt: String,
+}
+
+pub struct UserModelAccessController;
+impl UserModelAccessController {
+    pub async fn create(
+        database: &PostgresDatabase,
+        data: UserCredentials,
+    ) -> Result<User, model::Error> {
+        let sql = r#"INSERT INTO "user" (username, pwd) VALUES ($1, $2) RETURNING id, username"#;
+
+        let pwd = security::hash_password(&data.password).await?;
+
+        let query = sqlx::query_as::<_, User>(sql).bind(data.username).bind(pwd);
+
+        let user = query.fetch_one(database).await?;
+
+        Ok(user)
+    }
+
+    pub async fn get(database: &PostgresDatabase, id: i64) -> Result<User, model::Error> {
+        let sql_statement = r#"SELECT id, username FROM "user" WHERE id = $1"#;
+
+        let sql_query = sqlx::query_as::<_, User>(sql_statement).bind(id);
+
+        let user = sql_query.fetch_one(database).await;
+
+        handle_fetch_one_result(user, id.to_string())
+    }
+
+    pub async fn get_for_auth(
+        database: &PostgresDatabase,
+        id: i64,
+    ) -> Result<UserForAuth, model::Error> {
+        let sql_statement = r#"SELECT id, username, pwd, token_salt FROM "user" WHERE id = $1"#;
+
+        let sql_query = sqlx::query_as::<_, UserForAuth>(sql_statement).bind(id);
+
+        let user = sql_query.fetch_one(database).await;
+
+        handle_fetch_one_result(user, id.to_string())
+    }
+
+    pub async fn get_for_auth_by_username(
+        database: &PostgresDatabase,
+        username: &str,
+    ) -> Result<UserForAuth, model::Error> {
+        let sql_statement =
+            r#"SELECT id, username, pwd, token_salt FROM "user" WHERE username = $1"#;
+
+        let sql_query = sqlx::query_as::<_, UserForAuth>(sql_statement).bind(username);
+
+        let user = sql_query.fetch_one(database).await;
+
+        handle_fetch_one_result(user, username.to_string())
+    }
+
+    // A new token salt invalidates every token issued so far for that user
+    pub async fn renew_token_salt(
+        database: &PostgresDatabase,
+        id: i64,
+    ) -> Result<(), model::Error> {
+        let sql_statement =
+            r#"UPDATE "user" SET token_salt = gen_random_uuid()::text WHERE id = $1 RETURNING id"#;
+
+        let sql_query = sqlx::query_scalar::<_, i64>(sql_statement).bind(id);
+
+        let result = sql_query.fetch_one(database).await;
+
+        handle_fetch_one_result(result, id.to_string()).map(|_| ())
+    }
+
+    // The admins see what belongs to the whole server, like the background jobs
+    pub async fn is_admin(database: &PostgresDatabase, id: i64) -> Result<bool, model::Error> {
+        let sql_statement = r#"SELECT admin FROM "user" WHERE id = $1"#;
+
+        let sql_query = sqlx::query_scalar::<_, bool>(sql_statement).bind(id);
+
+        let admin = sql_query.fetch_one(database).await;
+
+        handle_fetch_one_result(admin, id.to_string())
+    }
+}
+
+// Utils
+
+fn handle_fetch_one_result<T>(
+    result: Result<T, sqlx::Error>,
+    key: String,
+) -> Result<T, model::Error> {
+    result.map_err(|sqlx_error| match sqlx_error {
+        sqlx::Error::RowNotFound => model::Error::EntityNotFound("user", key),
+        other => model::Error::SqlxError(other),
+    })
+}
+
+#[cfg(test)]
+#[path = "../_tests/model_user.rs"]
+mod tests;
@@ -0,0 +1,430 @@
+use std::{fmt::Write, time::Duration};
+
+use chrono::{DateTime, Utc};
+use rand::{rngs::OsRng, RngCore};
+use serde_derive::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sqlx::{PgExecutor, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::config::config;
+use crate::model;
+use crate::model::{
+    db::PostgresDatabase,
+    event::TodoEvent,
+    job::{JobKind, JobQueue},
+    project::ProjectModelAccessController,
+    todo::{deserialize_present, Todo},
+    FieldError,
+};
+use crate::security::UserContext;
+
+// An endpoint the todo events of its owner are posted to
+#[derive(sqlx::FromRow, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Webhook {
+    pub id: i64,
+    pub cid: i64,
+    // only the todos of that project, all the todos of the owner when None
+    pub project_id: Option<i64>,
+    pub url: String,
+    // the HMAC key of the X-Webhook-Signature header, for the owner to check it
+    pub secret: String,
+    pub active: bool,
+    pub ctime: DateTime<Utc>,
+    pub mtime: DateTime<Utc>,
+}
+
+const WEBHOOK_COLUMNS: &str = "id, cid, project_id, url, secret, active, ctime, mtime";
+
+#[allow(clippy::module_name_repetitions, clippy::option_option)]
+#[derive(Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PartialWebhook {
+    pub url: Option<String>,
+    // one of the projects of the user, null for all their todos
+    #[serde(default, deserialize_with = "deserialize_present")]
+    pub project_id: Option<Option<i64>>,
+    pub active: Option<bool>,
+}
+
+// One attempt to post an event
+#[allow(clippy::module_name_repetitions)]
+#[derive(sqlx::FromRow, Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookDelivery {
+    pub id: i64,
+    pub webhook_id: i64,
+    pub event_id: Uuid,
+    pub event_type: String,
+    pub attempt: i32,
+    // None when no response was received
+    pub status_code: Option<i32>,
+    pub error: Option<String>,
+    pub duration_ms: i64,
+    pub ctime: DateTime<Utc>,
+}
+
+const DELIVERY_COLUMNS: &str =
+    "id, webhook_id, event_id, event_type, attempt, status_code, error, duration_ms, ctime";
+
+// The body posted to the webhook
+#[allow(clippy::module_name_repetitions)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WebhookEvent {
+    pub id: Uuid,
+    #[serde(rename = "type")]
+    pub event_type: String,
+    pub created_at: DateTime<Utc>,
+    pub data: Value,
+}
+
+// The payload of a webhook job
+#[allow(clippy::module_name_repetitions)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookJob {
+    pub webhook_id: i64,
+    pub event: WebhookEvent,
+}
+
+pub const TEST_EVENT_TYPE: &str = "webhook.test";
+
+// same as the url column, VARCHAR(2048)
+const URL_MAX_LEN: usize = 2048;
+// the most recent deliveries listed
+const DELIVERIES_LIMIT: i64 = 100;
+
+impl PartialWebhook {
+    fn validate(&self) -> Result<(), model::Error> {
+        if let Some(url) = &self.url {
+            let is_http = reqwest::Url::parse(url)
+                .is_ok_and(|url| matches!(url.scheme(), "http" | "https") && url.has_host());
+            if url.len() > URL_MAX_LEN || !is_http {
+                return Err(model::Error::ValidationFailed(vec![FieldError::new(
+                    "url",
+                    "must be an http or https URL",
+                )]));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// A user only sees and manages their own webhooks
+#[allow(clippy::module_name_repetitions)]
+pub struct WebhookModelAccessController;
+impl WebhookModelAccessController {
+    pub async fn list(
+        database: &PostgresDatabase,
+        utx: &UserContext,
+    ) -> Result<Vec<Webhook>, model::Error> {
+        let sql = format!("SELECT {WEBHOOK_COLUMNS} FROM webhook WHERE cid = $1 ORDER BY id");
+
+        let webhooks = sqlx::query_as::<_, Webhook>(&sql)
+            .bind(utx.user_id)
+            .fetch_all(database)
+            .await?;
+
+        Ok(webhooks)
+    }
+
+    pub async fn create(
+        database: &PostgresDatabase,
+        utx: &UserContext,
+        data: PartialWebhook,
+    ) -> Result<Webhook, model::Error> {
+        data.validate()?;
+        let Some(url) = data.url else {
+            return Err(model::Error::ValidationFailed(vec![FieldError::new(
+                "url",
+                "is required",
+            )]));
+        };
+        let project_id = data.project_id.flatten();
+        check_project(database, utx, project_id).await?;
+
+        let sql = format!(
+            "INSERT INTO webhook (cid, project_id, url, secret, active) \
+             VALUES ($1, $2, $3, $4, COALESCE($5, TRUE)) RETURNING {WEBHOOK_COLUMNS}"
+        );
+
+        let webhook = sqlx::query_as::<_, Webhook>(&sql)
+            .bind(utx.user_id)
+            .bind(project_id)
+            .bind(url)
+            .bind(new_secret())
+            .bind(data.active)
+            .fetch_one(database)
+            .await?;
+
+        Ok(webhook)
+    }
+
+    pub async fn update(
+        database: &PostgresDatabase,
+        utx: &UserContext,
+        id: i64,
+        data: PartialWebhook,
+    ) -> Result<Webhook, model::Error> {
+        data.validate()?;
+        if let Some(project_id) = data.project_id {
+            check_project(database, utx, project_id).await?;
+        }
+
+        let sql = format!(
+            "UPDATE webhook SET url = COALESCE($3, url), \
+             project_id = CASE WHEN $4 THEN $5 ELSE project_id END, \
+             active = COALESCE($6, active), mtime = NOW() \
+             WHERE id = $1 AND cid = $2 RETURNING {WEBHOOK_COLUMNS}"
+        );
+
+        let webhook = sqlx::query_as::<_, Webhook>(&sql)
+            .bind(id)
+            .bind(utx.user_id)
+            .bind(data.url)
+            .bind(data.project_id.is_some())
+            .bind(data.project_id.flatten())
+            .bind(data.active)
+            .fetch_one(database)
+            .await;
+
+        handle_fetch_one_result(webhook, id)
+    }
+
+    // its deliveries go with it
+    pub async fn delete(
+        database: &PostgresDatabase,
+        utx: &UserContext,
+        id: i64,
+    ) -> Result<Webhook, model::Error> {
+        let sql =
+            format!("DELETE FROM webhook WHERE id = $1 AND cid = $2 RETURNING {WEBHOOK_COLUMNS}");
+
+        let webhook = sqlx::query_as::<_, Webhook>(&sql)
+            .bind(id)
+            .bind(utx.user_id)
+            .fetch_one(database)
+            .await;
+
+        handle_fetch_one_result(webhook, id)
+    }
+
+    // the delivery log, the most recent attempts first
+    pub async fn deliveries(
+        database: &PostgresDatabase,
+        utx: &UserContext,
+        id: i64,
+    ) -> Result<Vec<WebhookDelivery>, model::Error> {
+        select_webhook(database, utx, id).await?;
+
+        let sql = format!(
+            "SELECT {DELIVERY_COLUMNS} FROM webhook_delivery WHERE webhook_id = $1 \
+             ORDER BY id DESC LIMIT $2"
+        );
+
+        let deliveries = sqlx::query_as::<_, WebhookDelivery>(&sql)
+            .bind(id)
+            .bind(DELIVERIES_LIMIT)
+            .fetch_all(database)
+            .await?;
+
+        Ok(deliveries)
+    }
+
+    // A test event, delivered like the todo ones, even to an inactive webhook
+    pub async fn send_test(
+        database: &PostgresDatabase,
+        utx: &UserContext,
+        id: i64,
+    ) -> Result<WebhookEvent, model::Error> {
+        let webhook = select_webhook(database, utx, id).await?;
+
+        let event = new_event(TEST_EVENT_TYPE, json!({ "webhook_id": webhook.id }));
+        enqueue(database, webhook.id, &event).await?;
+
+        Ok(event)
+    }
+}
+
+// Utils
+
+// One delivery job per active webhook of the todo owner, for all their todos or for its project
+pub async fn enqueue_todo_event(
+    transaction: &mut Transaction<'_, Postgres>,
+    event: TodoEvent,
+    todo: &Todo,
+) -> Result<(), model::Error> {
+    let webhook_ids: Vec<i64> = sqlx::query_scalar(
+        "SELECT id FROM webhook WHERE cid = $1 AND active \
+         AND (project_id IS NULL OR project_id = $2) ORDER BY id",
+    )
+    .bind(todo.cid)
+    .bind(todo.project_id)
+    .fetch_all(&mut **transaction)
+    .await?;
+    if webhook_ids.is_empty() {
+        return Ok(());
+    }
+
+    let event = new_event(event.as_str(), json!(todo));
+    for webhook_id in webhook_ids {
+        enqueue(&mut **transaction, webhook_id, &event).await?;
+    }
+
+    Ok(())
+}
+
+// The webhook an event is delivered to, None once deleted, or inactive for a todo event
+pub async fn webhook_for_delivery(
+    database: &PostgresDatabase,
+    id: i64,
+    event_type: &str,
+) -> Result<Option<Webhook>, model::Error> {
+    let sql = format!("SELECT {WEBHOOK_COLUMNS} FROM webhook WHERE id = $1 AND (active OR $2)");
+
+    let webhook = sqlx::query_as::<_, Webhook>(&sql)
+        .bind(id)
+        .bind(event_type == TEST_EVENT_TYPE)
+        .fetch_optional(database)
+        .await?;
+
+    Ok(webhook)
+}
+
+pub async fn record_delivery(
+    database: &PostgresDatabase,
+    webhook_id: i64,
+    event: &WebhookEvent,
+    attempt: i32,
+    status_code: Option<u16>,
+    error: Option<&str>,
+    duration_ms: i64,
+) -> Result<WebhookDelivery, model::Error> {
+    let sql = format!(
+        "INSERT INTO webhook_delivery (webhook_id, event_id, event_type, attempt, status_code, error, duration_ms) \
+         VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING {DELIVERY_COLUMNS}"
+    );
+
+    let delivery = sqlx::query_as::<_, WebhookDelivery>(&sql)
+        .bind(webhook_id)
+        .bind(event.id)
+        .bind(&event.event_type)
+        .bind(attempt)
+        .bind(status_code.map(i32::from))
+        .bind(error)
+        .bind(duration_ms)
+        .fetch_one(database)
+        .await?;
+
+    Ok(delivery)
+}
+
+// Returns the number of deliveries deleted
+pub async fn purge_deliveries(
+    database: &PostgresDatabase,
+    retention: Duration,
+) -> Result<u64, model::Error> {
+    let result =
+        sqlx::query("DELETE FROM webhook_delivery WHERE ctime < NOW() - $1 * INTERVAL '1 second'")
+            .bind(retention.as_secs_f64())
+            .execute(database)
+            .await?;
+
+    Ok(result.rows_affected())
+}
+
+async fn enqueue<'e>(
+    executor: impl PgExecutor<'e>,
+    webhook_id: i64,
+    event: &WebhookEvent,
+) -> Result<(), model::Error> {
+    let payload = WebhookJob {
+        webhook_id,
+        event: event.clone(),
+    };
+    JobQueue::enqueue(
+        executor,
+        JobKind::Webhook,
+        json!(payload),
+        config().jobs.max_attempts,
+        None,
+    )
+    .await?;
+
+    Ok(())
+}
+
+fn new_event(event_type: &str, data: Value) -> WebhookEvent {
+    WebhookEvent {
+        id: Uuid::new_v4(),
+        event_type: event_type.to_string(),
+        created_at: Utc::now(),
+        data,
+    }
+}
+
+// 256 random bits, hex encoded
+fn new_secret() -> String {
+    let mut bytes = [0_u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+
+    bytes
+        .iter()
+        .fold(String::from("whsec_"), |mut secret, byte| {
+            // writing to a String never fails
+            let _ = write!(secret, "{byte:02x}");
+            secret
+        })
+}
+
+// a project of another user is a validation error, like for the todos
+async fn check_project(
+    database: &PostgresDatabase,
+    utx: &UserContext,
+    project_id: Option<i64>,
+) -> Result<(), model::Error> {
+    let Some(project_id) = project_id else {
+        return Ok(());
+    };
+
+    match ProjectModelAccessController::get(database, utx, project_id).await {
+        Ok(_) => Ok(()),
+        Err(model::Error::EntityNotFound(_, _)) => {
+            Err(model::Error::ValidationFailed(vec![FieldError::new(
+                "project_id",
+                "is not one of your projects",
+            )]))
+        }
+        Err(other) => Err(other),
+    }
+}
+
+// scoped to the owner, a webhook of another user is just not found
+async fn select_webhook(
+    database: &PostgresDatabase,
+    utx: &UserContext,
+    id: i64,
+) -> Result<Webhook, model::Error> {
+    let sql = format!("SELECT {WEBHOOK_COLUMNS} FROM webhook WHERE id = $1 AND cid = $2");
+
+    let webhook = sqlx::query_as::<_, Webhook>(&sql)
+        .bind(id)
+        .bind(utx.user_id)
+        .fetch_one(database)
+        .await;
+
+    handle_fetch_one_result(webhook, id)
+}
+
+fn handle_fetch_one_result(
+    result: Result<Webhook, sqlx::Error>,
+    id: i64,
+) -> Result<Webhook, model::Error> {
+    result.map_err(|sqlx_error| match sqlx_error {
+        sqlx::Error::RowNotFound => model::Error::EntityNotFound("webhook", id.to_string()),
+        other => model::Error::SqlxError(other),
+    })
+}
+
+#[cfg(test)]
+#[path = "../_tests/model_webhook.rs"]
+mod tests;
@@ -1,25 +1,138 @@
+use std::time::Duration;
+
 use thiserror::Error as ThisError;
 
-use crate::model::PostgresDatabase;
+use crate::config::config;
+use crate::model::{self, PostgresDatabase, User, UserModelAccessController};
+
+mod password;
+mod token;
+pub use password::{hash_password, verify_password};
+pub use token::Token;
+
+const TOKEN_DURATION: Duration = Duration::from_hours(24);
+
+// Hash of a random password nobody knows, verified for unknown usernames so they take as long as a wrong password
+const DUMMY_PASSWORD_HASH: &str =
+    "$argon2id$v=19$m=19456,t=2,p=1$NQpL+vjPH5On02L8sn2GWw$p5iNrO3OSBAK3kdKY9T5ifImC7SvSeaEyk2ldVvC2bI";
 
 pub struct UserContext {
     pub user_id: i64,
 }
 
 pub async fn user_context_from_token(
-    _database: &PostgresDatabase,
+    database: &PostgresDatabase,
     user_token: &str,
 ) -> Result<UserContext, Error> {
-    // TODO : real validation needed
-    // fetch user informations from database
-    match user_token.parse::<i64>() {
-        Ok(value) => Ok(UserContext { user_id: value }),
-        Err(_) => Err(Error::InvalidToken(String::from(user_token))),
-    }
+    let token = Token::parse(user_token)?;
+
+    // the key depends on the user token salt, so an unknown user is just a token we didn't sign
+    let token_salt = match UserModelAccessController::get_for_auth(database, token.user_id).await {
+        Ok(user) => user.token_salt,
+        Err(model::Error::EntityNotFound(_, _)) => return Err(Error::InvalidTokenSignature),
+        Err(other) => return Err(other.into()),
+    };
+
+    token.validate(&signing_key(&token_salt))?;
+
+    Ok(UserContext {
+        user_id: token.user_id,
+    })
+}
+
+// Check the credentials, and issue a signed token, as expected by user_context_from_token
+pub async fn login(
+    database: &PostgresDatabase,
+    username: &str,
+    password: &str,
+) -> Result<(User, String), Error> {
+    let user = match UserModelAccessController::get_for_auth_by_username(database, username).await {
+        Ok(user) => user,
+        Err(model::Error::EntityNotFound(_, _)) => {
+            let _ = verify_password(password, DUMMY_PASSWORD_HASH).await;
+            return Err(Error::LoginFail);
+        }
+        Err(other) => return Err(other.into()),
+    };
+
+    // a wrong password fails the login, a hash that can't be verified is an internal error
+    verify_password(password, &user.pwd)
+        .await
+        .map_err(|error| match error {
+            Error::WrongPassword => Error::LoginFail,
+            other => other,
+        })?;
+
+    let token = Token::sign(user.id, TOKEN_DURATION, &signing_key(&user.token_salt));
+
+    Ok((
+        User {
+            id: user.id,
+            username: user.username,
+        },
+        token,
+    ))
+}
+
+// Every token of the user is invalidated, not only the one used for the logout
+pub async fn logout(database: &PostgresDatabase, utx: &UserContext) -> Result<(), Error> {
+    UserModelAccessController::renew_token_salt(database, utx.user_id).await?;
+
+    Ok(())
 }
 
+// Issue a signed token for a user, without the credentials check
+pub async fn new_token(database: &PostgresDatabase, user_id: i64) -> Result<String, Error> {
+    let user = UserModelAccessController::get_for_auth(database, user_id).await?;
+
+    Ok(Token::sign(
+        user.id,
+        TOKEN_DURATION,
+        &signing_key(&user.token_salt),
+    ))
+}
+
+fn signing_key(token_salt: &str) -> Vec<u8> {
+    [
+        config().security.token_secret.as_bytes(),
+        token_salt.as_bytes(),
+    ]
+    .concat()
+}
+
+#[allow(clippy::enum_variant_names)]
 #[derive(ThisError, Debug)]
 pub enum Error {
-    #[error("Invalid Token {0}")]
-    InvalidToken(String),
+    // no payload: the client token must not end up in responses or logs
+    #[error("Malformed Token")]
+    MalformedToken,
+
+    #[error("Invalid Token Signature")]
+    InvalidTokenSignature,
+
+    #[error("Expired Token")]
+    ExpiredToken,
+
+    #[error("Login failed, wrong username or password")]
+    LoginFail,
+
+    #[error("Wrong password")]
+    WrongPassword,
+
+    #[error("Password hash error {0}")]
+    PasswordHash(String),
+
+    // boxed because model::Error already contains a security::Error
+    #[error(transparent)]
+    ModelError(Box<model::Error>),
 }
+
+impl From<model::Error> for Error {
+    fn from(other: model::Error) -> Self {
+        Self::ModelError(Box::new(other))
+    }
+}
+
+#[cfg(test)]
+#[path = "../_tests/security.rs"]
+mod tests;
@@ -0,0 +1,41 @@
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+
+use tokio::task::spawn_blocking;
+
+use crate::security::Error;
+
+// Argon2id with the default parameters, stored as a PHC string (algorithm, parameters and salt included).
+// Hashing takes tens of milliseconds, so it runs on the blocking threads, not on the async workers
+pub async fn hash_password(password: &str) -> Result<String, Error> {
+    let password = password.to_string();
+
+    spawn_blocking(move || {
+        let salt = SaltString::generate(&mut OsRng);
+
+        Argon2::default()
+            .hash_password(password.as_bytes(), &salt)
+            .map(|hash| hash.to_string())
+            .map_err(|error| Error::PasswordHash(error.to_string()))
+    })
+    .await
+    .map_err(|error| Error::PasswordHash(error.to_string()))?
+}
+
+pub async fn verify_password(password: &str, password_hash: &str) -> Result<(), Error> {
+    let password = password.to_string();
+    let password_hash = password_hash.to_string();
+
+    spawn_blocking(move || {
+        let password_hash = PasswordHash::new(&password_hash)
+            .map_err(|error| Error::PasswordHash(error.to_string()))?;
+
+        Argon2::default()
+            .verify_password(password.as_bytes(), &password_hash)
+            .map_err(|_| Error::WrongPassword)
+    })
+    .await
+    .map_err(|error| Error::PasswordHash(error.to_string()))?
+}
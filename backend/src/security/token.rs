@@ -0,0 +1,82 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD as B64, Engine};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::security::Error;
+
+type HmacSha256 = Hmac<Sha256>;
+
+// token format : "{user_id}.{expiration_base64}.{signature_base64}"
+// with the signature being the HMAC-SHA256 of "{user_id}.{expiration_base64}"
+pub struct Token {
+    pub user_id: i64,
+    pub expiration: u64, // unix timestamp in seconds
+    content: String,
+    signature: Vec<u8>,
+}
+
+impl Token {
+    pub fn sign(user_id: i64, duration: Duration, key: &[u8]) -> String {
+        let expiration = now_unix_seconds().saturating_add(duration.as_secs());
+        let content = format!("{user_id}.{}", B64.encode(expiration.to_string()));
+        let signature = B64.encode(signature(key, &content).finalize().into_bytes());
+
+        format!("{content}.{signature}")
+    }
+
+    // Only checks the format, the signature still needs to be validated
+    pub fn parse(token: &str) -> Result<Self, Error> {
+        let malformed = || Error::MalformedToken;
+
+        let mut parts = token.splitn(3, '.');
+        let (Some(user_id), Some(expiration), Some(signature)) =
+            (parts.next(), parts.next(), parts.next())
+        else {
+            return Err(malformed());
+        };
+
+        Ok(Self {
+            user_id: user_id.parse().map_err(|_| malformed())?,
+            expiration: B64
+                .decode(expiration)
+                .ok()
+                .and_then(|bytes| String::from_utf8(bytes).ok())
+                .and_then(|expiration| expiration.parse().ok())
+                .ok_or_else(malformed)?,
+            content: format!("{user_id}.{expiration}"),
+            signature: B64.decode(signature).map_err(|_| malformed())?,
+        })
+    }
+
+    // Check the signature (constant time) and then the expiration
+    pub fn validate(&self, key: &[u8]) -> Result<(), Error> {
+        signature(key, &self.content)
+            .verify_slice(&self.signature)
+            .map_err(|_| Error::InvalidTokenSignature)?;
+
+        if self.expiration <= now_unix_seconds() {
+            return Err(Error::ExpiredToken);
+        }
+
+        Ok(())
+    }
+}
+
+fn signature(secret: &[u8], content: &str) -> HmacSha256 {
+    // HMAC accepts keys of any size, so this can't fail
+    let mut mac = HmacSha256::new_from_slice(secret).expect("HMAC can take a key of any size");
+    mac.update(content.as_bytes());
+    mac
+}
+
+fn now_unix_seconds() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+#[cfg(test)]
+#[path = "../_tests/security_token.rs"]
+mod tests;
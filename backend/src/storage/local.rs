@@ -0,0 +1,106 @@
+use std::{
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use bytes::Bytes;
+use futures::{future::BoxFuture, stream, FutureExt, StreamExt, TryStreamExt};
+use tokio::{
+    fs::{self, File},
+    io::{AsyncReadExt, AsyncWriteExt},
+};
+
+use super::{ByteStream, Error, Storage};
+
+const READ_CHUNK_SIZE: usize = 64 * 1024;
+
+// One file per key, in a directory created on the first put
+pub struct LocalStorage {
+    directory: PathBuf,
+}
+
+impl LocalStorage {
+    pub fn new(directory: impl AsRef<Path>) -> Self {
+        Self {
+            directory: directory.as_ref().to_path_buf(),
+        }
+    }
+
+    // the key never leaves the directory, it can't be a path
+    fn path(&self, key: &str) -> Result<PathBuf, Error> {
+        let is_valid =
+            !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+        if is_valid {
+            Ok(self.directory.join(key))
+        } else {
+            Err(Error::InvalidKey(key.to_string()))
+        }
+    }
+
+    async fn write(&self, key: &str, mut content: ByteStream) -> Result<(), Error> {
+        let path = self.path(key)?;
+        fs::create_dir_all(&self.directory).await?;
+
+        // written aside, then renamed, so a key is either complete or absent
+        let partial_path = path.with_extension("part");
+        let written = async {
+            let mut file = File::create(&partial_path).await?;
+            while let Some(chunk) = content.try_next().await? {
+                file.write_all(&chunk).await?;
+            }
+            file.sync_all().await?;
+            fs::rename(&partial_path, &path).await?;
+            Ok(())
+        }
+        .await;
+
+        if written.is_err() {
+            let _ = fs::remove_file(&partial_path).await;
+        }
+        written
+    }
+
+    async fn read(&self, key: &str) -> Result<ByteStream, Error> {
+        let path = self.path(key)?;
+        let file = match File::open(&path).await {
+            Ok(file) => file,
+            Err(error) if error.kind() == ErrorKind::NotFound => {
+                return Err(Error::NotFound(key.to_string()))
+            }
+            Err(error) => return Err(error.into()),
+        };
+
+        let chunks = stream::try_unfold(file, |mut file| async move {
+            let mut buffer = vec![0; READ_CHUNK_SIZE];
+            let read = file.read(&mut buffer).await?;
+            if read == 0 {
+                return Ok(None);
+            }
+            buffer.truncate(read);
+            Ok(Some((Bytes::from(buffer), file)))
+        });
+
+        Ok(chunks.boxed())
+    }
+
+    async fn remove(&self, key: &str) -> Result<(), Error> {
+        match fs::remove_file(self.path(key)?).await {
+            Err(error) if error.kind() != ErrorKind::NotFound => Err(error.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Storage for LocalStorage {
+    fn put<'a>(&'a self, key: &'a str, content: ByteStream) -> BoxFuture<'a, Result<(), Error>> {
+        self.write(key, content).boxed()
+    }
+
+    fn get<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<ByteStream, Error>> {
+        self.read(key).boxed()
+    }
+
+    fn delete<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<(), Error>> {
+        self.remove(key).boxed()
+    }
+}
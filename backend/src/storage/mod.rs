@@ -0,0 +1,84 @@
+use std::{str::FromStr, sync::OnceLock};
+
+use bytes::Bytes;
+use futures::{future::BoxFuture, stream::BoxStream};
+use serde_derive::Deserialize;
+use thiserror::Error as ThisError;
+
+use crate::config::{config, AttachmentConfig};
+
+mod local;
+pub use local::LocalStorage;
+
+static STORAGE: OnceLock<Box<dyn Storage>> = OnceLock::new();
+
+// The content of a stored object, chunk by chunk
+pub type ByteStream = BoxStream<'static, Result<Bytes, Error>>;
+
+// Where the attachment contents are kept, the database only has their metadata.
+// A key is chosen by the caller, unique, and made of [a-zA-Z0-9-] only
+pub trait Storage: Send + Sync {
+    // nothing is kept under the key when the content stream fails
+    fn put<'a>(&'a self, key: &'a str, content: ByteStream) -> BoxFuture<'a, Result<(), Error>>;
+
+    fn get<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<ByteStream, Error>>;
+
+    // deleting a missing key is not an error
+    fn delete<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Result<(), Error>>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    #[default]
+    Local,
+}
+
+impl FromStr for StorageBackend {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "local" => Ok(Self::Local),
+            other => Err(format!(
+                "unknown storage backend '{other}', expected 'local'"
+            )),
+        }
+    }
+}
+
+pub fn storage_from_config(attachment_config: &AttachmentConfig) -> Box<dyn Storage> {
+    match attachment_config.backend {
+        StorageBackend::Local => Box::new(LocalStorage::new(&attachment_config.directory)),
+    }
+}
+
+// The storage of the configured backend, for the whole process
+pub fn storage() -> &'static dyn Storage {
+    STORAGE
+        .get_or_init(|| storage_from_config(&config().attachments))
+        .as_ref()
+}
+
+#[allow(clippy::enum_variant_names)]
+#[derive(ThisError, Debug)]
+pub enum Error {
+    #[error("Storage key '{0}' is invalid")]
+    InvalidKey(String),
+
+    #[error("Storage object '{0}' not found")]
+    NotFound(String),
+
+    #[error("Content is larger than the {0} bytes limit")]
+    TooLarge(u64),
+
+    #[error("Content stream failed _ {0}")]
+    StreamFailed(String),
+
+    #[error(transparent)]
+    IOError(#[from] std::io::Error),
+}
+
+#[cfg(test)]
+#[path = "../_tests/storage.rs"]
+mod tests;
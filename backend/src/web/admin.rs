@@ -0,0 +1,46 @@
+use std::sync::Arc;
+
+use warp::{reject::Rejection as WarpRejection, reply::Json as WarpJSON, Filter};
+
+use crate::{
+    model::{self, JobListOptions, JobModelAccessController, PostgresDatabase},
+    security::UserContext,
+};
+
+use super::{
+    filter_utils::{do_auth, path_prefix, with_db},
+    serialize_to_warpjson,
+};
+
+pub fn rest_filters(
+    base_path: &str,
+    database: Arc<model::PostgresDatabase>,
+) -> impl Filter<Extract = impl warp::Reply, Error = WarpRejection> + Clone {
+    // base_path = api/v1 -> api/v1/admin/jobs
+    let jobs_path = path_prefix(base_path)
+        .and(warp::path("admin"))
+        .and(warp::path("jobs"));
+
+    let common = with_db(Arc::clone(&database)).and(do_auth(database));
+
+    // LIST the background jobs 'GET /admin/jobs?status=Dead&kind=reminder&limit=20, admins only
+    jobs_path
+        .and(warp::get())
+        .and(warp::path::end())
+        .and(common)
+        .and(warp::query::<JobListOptions>())
+        .and_then(job_list)
+}
+
+async fn job_list(
+    database: Arc<PostgresDatabase>,
+    utx: UserContext,
+    options: JobListOptions,
+) -> Result<WarpJSON, WarpRejection> {
+    let jobs = JobModelAccessController::list(&database, &utx, options).await?;
+    Ok(serialize_to_warpjson(jobs))
+}
+
+#[cfg(test)]
+#[path = "../_tests/web_admin.rs"]
+mod tests;
@@ -0,0 +1,202 @@
+use std::sync::Arc;
+
+use bytes::Buf;
+use futures::{StreamExt, TryStreamExt};
+use warp::http::{header, HeaderValue};
+use warp::hyper::Body;
+use warp::multipart::{FormData, Part};
+use warp::{
+    reject::Rejection as WarpRejection,
+    reply::{Json as WarpJSON, Response},
+    Filter,
+};
+
+use crate::{
+    config::config,
+    model::{self, AttachmentModelAccessController, NewAttachment, PostgresDatabase},
+    security::UserContext,
+    storage,
+};
+
+use super::{
+    filter_utils::{do_auth, path_prefix, with_db},
+    serialize_to_warpjson, Error as WebError,
+};
+
+// the form field of the file
+const FILE_FIELD: &str = "file";
+// room for the multipart boundaries and part headers, over the file size limit
+const MULTIPART_OVERHEAD: u64 = 16 * 1024;
+
+pub fn rest_filters(
+    base_path: &str,
+    database: Arc<model::PostgresDatabase>,
+) -> impl Filter<Extract = impl warp::Reply, Error = WarpRejection> + Clone {
+    // base_path = api/v1 -> api/v1/todos/101/attachments
+    let attachments_path = path_prefix(base_path)
+        .and(warp::path("todos"))
+        .and(warp::path::param())
+        .and(warp::path("attachments"));
+
+    let common = with_db(Arc::clone(&database)).and(do_auth(database));
+
+    // LIST the todo attachments 'GET /todos/101/attachments, their metadata only
+    let list = attachments_path
+        .clone()
+        .and(warp::get())
+        .and(warp::path::end())
+        .and(common.clone())
+        .and_then(attachment_list);
+
+    // UPLOAD attachment 'POST /todos/101/attachments with a multipart/form-data body, the file in its "file" field
+    let max_length = config().attachments.max_size + MULTIPART_OVERHEAD;
+    let create = attachments_path
+        .clone()
+        .and(warp::post())
+        .and(warp::path::end())
+        .and(common.clone())
+        .and(warp::multipart::form().max_length(max_length))
+        .and_then(attachment_create);
+
+    // DOWNLOAD attachment 'GET /todos/101/attachments/1000, streamed with its content type
+    let download = attachments_path
+        .clone()
+        .and(warp::get())
+        .and(warp::path::param())
+        .and(warp::path::end())
+        .and(common.clone())
+        .and_then(attachment_download);
+
+    // DELETE attachment 'DELETE /todos/101/attachments/1000, its content too
+    let delete = attachments_path
+        .and(warp::delete())
+        .and(warp::path::param())
+        .and(warp::path::end())
+        .and(common)
+        .and_then(attachment_delete);
+
+    list.or(create).or(download).or(delete)
+}
+
+// the path params come first, then the database and the utx of common
+async fn attachment_list(
+    todo_id: i64,
+    database: Arc<PostgresDatabase>,
+    utx: UserContext,
+) -> Result<WarpJSON, WarpRejection> {
+    let attachments = AttachmentModelAccessController::list(&database, &utx, todo_id).await?;
+    Ok(serialize_to_warpjson(attachments))
+}
+
+async fn attachment_create(
+    todo_id: i64,
+    database: Arc<PostgresDatabase>,
+    utx: UserContext,
+    form: FormData,
+) -> Result<WarpJSON, WarpRejection> {
+    let part = file_part(form).await?;
+
+    let data = NewAttachment {
+        filename: part.filename().map(ToString::to_string),
+        content_type: part.content_type().map(ToString::to_string),
+    };
+    // streamed to the storage, never fully in memory
+    let content = part
+        .stream()
+        .map_ok(|mut chunk| chunk.copy_to_bytes(chunk.remaining()))
+        .map_err(|error| storage::Error::StreamFailed(error.to_string()))
+        .boxed();
+
+    let attachment =
+        AttachmentModelAccessController::create(&database, &utx, todo_id, data, content).await?;
+
+    Ok(serialize_to_warpjson(attachment))
+}
+
+async fn attachment_download(
+    todo_id: i64,
+    id: i64,
+    database: Arc<PostgresDatabase>,
+    utx: UserContext,
+) -> Result<Response, WarpRejection> {
+    let (attachment, content) =
+        AttachmentModelAccessController::get_content(&database, &utx, todo_id, id).await?;
+
+    let mut response = Response::new(Body::wrap_stream(content));
+    let headers = response.headers_mut();
+    headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_str(&attachment.content_type)
+            .unwrap_or_else(|_| HeaderValue::from_static("application/octet-stream")),
+    );
+    headers.insert(header::CONTENT_LENGTH, HeaderValue::from(attachment.size));
+    headers.insert(
+        header::CONTENT_DISPOSITION,
+        content_disposition(&attachment.filename),
+    );
+    // always downloaded as the given content type, an uploaded html page is never run by the browser
+    headers.insert(
+        header::X_CONTENT_TYPE_OPTIONS,
+        HeaderValue::from_static("nosniff"),
+    );
+
+    Ok(response)
+}
+
+async fn attachment_delete(
+    todo_id: i64,
+    id: i64,
+    database: Arc<PostgresDatabase>,
+    utx: UserContext,
+) -> Result<WarpJSON, WarpRejection> {
+    let attachment = AttachmentModelAccessController::delete(&database, &utx, todo_id, id).await?;
+    Ok(serialize_to_warpjson(attachment))
+}
+
+// the file part of the form, the other parts are ignored
+async fn file_part(mut form: FormData) -> Result<Part, WarpRejection> {
+    while let Some(part) = form
+        .try_next()
+        .await
+        .map_err(|error| WarpRejection::from(WebError::FailInvalidBody(error.to_string())))?
+    {
+        if part.name() == FILE_FIELD {
+            return Ok(part);
+        }
+    }
+
+    Err(WebError::FailInvalidBody(format!("missing the '{FILE_FIELD}' field")).into())
+}
+
+// attachment, with an ascii fallback of the filename and its RFC 5987 utf-8 version
+fn content_disposition(filename: &str) -> HeaderValue {
+    let fallback: String = filename
+        .chars()
+        .map(|c| {
+            if c.is_ascii_graphic() && c != '"' && c != '\\' || c == ' ' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let encoded: String = filename
+        .bytes()
+        .map(|byte| {
+            if byte.is_ascii_alphanumeric() || b"!#$&+-.^_`|~".contains(&byte) {
+                char::from(byte).to_string()
+            } else {
+                format!("%{byte:02X}")
+            }
+        })
+        .collect();
+
+    HeaderValue::from_str(&format!(
+        "attachment; filename=\"{fallback}\"; filename*=UTF-8''{encoded}"
+    ))
+    .unwrap_or_else(|_| HeaderValue::from_static("attachment"))
+}
+
+#[cfg(test)]
+#[path = "../_tests/web_attachment.rs"]
+mod tests;
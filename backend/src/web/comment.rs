@@ -0,0 +1,108 @@
+use std::sync::Arc;
+
+use warp::{reject::Rejection as WarpRejection, reply::Json as WarpJSON, Filter};
+
+use crate::{
+    model::{self, CommentModelAccessController, PartialComment, PostgresDatabase},
+    security::UserContext,
+};
+
+use super::{
+    filter_utils::{do_auth, json_body, path_prefix, with_db},
+    serialize_to_warpjson,
+};
+
+pub fn rest_filters(
+    base_path: &str,
+    database: Arc<model::PostgresDatabase>,
+) -> impl Filter<Extract = impl warp::Reply, Error = WarpRejection> + Clone {
+    // base_path = api/v1 -> api/v1/todos/101/comments
+    let comments_path = path_prefix(base_path)
+        .and(warp::path("todos"))
+        .and(warp::path::param())
+        .and(warp::path("comments"));
+
+    let common = with_db(Arc::clone(&database)).and(do_auth(database));
+
+    // LIST the todo comments 'GET /todos/101/comments, oldest first
+    let list = comments_path
+        .clone()
+        .and(warp::get())
+        .and(warp::path::end())
+        .and(common.clone())
+        .and_then(comment_list);
+
+    // CREATE comment 'POST /todos/101/comments with body PartialComment, the user is its author
+    let create = comments_path
+        .clone()
+        .and(warp::post())
+        .and(warp::path::end())
+        .and(common.clone())
+        .and(json_body())
+        .and_then(comment_create);
+
+    // UPDATE comment 'PATCH /todos/101/comments/1 with body PartialComment, author only
+    let update = comments_path
+        .clone()
+        .and(warp::patch())
+        .and(warp::path::param())
+        .and(warp::path::end())
+        .and(common.clone())
+        .and(json_body())
+        .and_then(comment_update);
+
+    // DELETE comment 'DELETE /todos/101/comments/1, author only
+    let delete = comments_path
+        .and(warp::delete())
+        .and(warp::path::param())
+        .and(warp::path::end())
+        .and(common)
+        .and_then(comment_delete);
+
+    list.or(create).or(update).or(delete)
+}
+
+// the path params come first, then the database and the utx of common
+async fn comment_list(
+    todo_id: i64,
+    database: Arc<PostgresDatabase>,
+    utx: UserContext,
+) -> Result<WarpJSON, WarpRejection> {
+    let comments = CommentModelAccessController::list(&database, &utx, todo_id).await?;
+    Ok(serialize_to_warpjson(comments))
+}
+
+async fn comment_create(
+    todo_id: i64,
+    database: Arc<PostgresDatabase>,
+    utx: UserContext,
+    data: PartialComment,
+) -> Result<WarpJSON, WarpRejection> {
+    let comment = CommentModelAccessController::create(&database, &utx, todo_id, data).await?;
+    Ok(serialize_to_warpjson(comment))
+}
+
+async fn comment_update(
+    todo_id: i64,
+    id: i64,
+    database: Arc<PostgresDatabase>,
+    utx: UserContext,
+    data: PartialComment,
+) -> Result<WarpJSON, WarpRejection> {
+    let comment = CommentModelAccessController::update(&database, &utx, todo_id, id, data).await?;
+    Ok(serialize_to_warpjson(comment))
+}
+
+async fn comment_delete(
+    todo_id: i64,
+    id: i64,
+    database: Arc<PostgresDatabase>,
+    utx: UserContext,
+) -> Result<WarpJSON, WarpRejection> {
+    let comment = CommentModelAccessController::delete(&database, &utx, todo_id, id).await?;
+    Ok(serialize_to_warpjson(comment))
+}
+
+#[cfg(test)]
+#[path = "../_tests/web_comment.rs"]
+mod tests;
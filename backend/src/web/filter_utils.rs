@@ -2,7 +2,11 @@
 
 use std::{convert::Infallible, sync::Arc};
 
-use warp::{reject::Rejection as WarpRejection, Filter as WarpFilter};
+use serde::{de::DeserializeOwned, Deserialize};
+
+use warp::{
+    filters::BoxedFilter, path::FullPath, reject::Rejection as WarpRejection, Filter as WarpFilter,
+};
 
 use crate::{
     model,
@@ -12,12 +16,21 @@ use crate::{
 
 pub const HEADER_XAUTH: &str = "X-AUTH-TOKEN";
 
+// a larger body is refused from its Content-Length, before it's read
+pub const JSON_BODY_MAX_SIZE: u64 = 256 * 1024;
+
 pub fn with_db(
     database: Arc<model::PostgresDatabase>,
 ) -> impl WarpFilter<Extract = (Arc<model::PostgresDatabase>,), Error = Infallible> + Clone {
     warp::any().map(move || Arc::clone(&database))
 }
 
+// warp::body::json() reads the whole body, whatever its size, so it's limited first
+pub fn json_body<T: DeserializeOwned + Send>(
+) -> impl WarpFilter<Extract = (T,), Error = WarpRejection> + Clone {
+    warp::body::content_length_limit(JSON_BODY_MAX_SIZE).and(warp::body::json())
+}
+
 pub fn do_auth(
     database: Arc<model::PostgresDatabase>,
 ) -> impl WarpFilter<Extract = (UserContext,), Error = warp::Rejection> + Clone {
@@ -40,3 +53,112 @@ pub fn do_auth(
             },
         )
 }
+
+#[derive(Deserialize)]
+struct TokenQuery {
+    token: Option<String>,
+}
+
+// do_auth, or the ?token= query parameter for the browser APIs that can't send a header,
+// like WebSocket and EventSource. The header wins when both are sent
+pub fn do_auth_or_query(
+    database: Arc<model::PostgresDatabase>,
+) -> impl WarpFilter<Extract = (UserContext,), Error = warp::Rejection> + Clone {
+    warp::any()
+        .and(with_db(database))
+        .and(warp::header::optional(HEADER_XAUTH))
+        .and(warp::query::<TokenQuery>())
+        .and_then(
+            |database: Arc<model::PostgresDatabase>,
+             xauth_token: Option<String>,
+             query: TokenQuery| async move {
+                match xauth_token.or(query.token) {
+                    Some(token) => {
+                        let user_ctx = user_context_from_token(&database, &token).await?;
+
+                        Ok::<UserContext, WarpRejection>(user_ctx)
+                    }
+                    None => Err(WebError::FailAuthMissingXAuth.into()),
+                }
+            },
+        )
+}
+
+// Matches a possibly multi segments base path, like "api/v1", segment by segment
+// (warp::path only accepts a single segment without any '/')
+pub fn path_prefix(base_path: &str) -> BoxedFilter<()> {
+    base_path
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .fold(warp::any().boxed(), |filter, segment| {
+            filter.and(warp::path(segment.to_string())).boxed()
+        })
+}
+
+// Rejects with a not found every request living under the base path
+// so that the static site never answers in place of the API
+pub fn outside_path_prefix(
+    base_path: &str,
+) -> impl WarpFilter<Extract = (), Error = WarpRejection> + Clone {
+    let prefix = format!("/{}", base_path.trim_matches('/'));
+
+    warp::path::full()
+        .and_then(move |full_path: FullPath| {
+            let is_under_prefix = full_path.as_str() == prefix
+                || full_path.as_str().starts_with(&format!("{prefix}/"));
+            async move {
+                if is_under_prefix {
+                    Err(warp::reject::not_found())
+                } else {
+                    Ok(())
+                }
+            }
+        })
+        .untuple_one()
+}
+
+// Conditional requests, the ETag of a resource is its version, like "3"
+
+pub fn version_etag(version: i64) -> String {
+    format!("\"{version}\"")
+}
+
+// The versions the client expects from its If-Match header, None when absent or "*".
+// If-Match uses the strong comparison, a weak ETag like W/"3" or another one than a version matches none
+pub fn if_match() -> impl WarpFilter<Extract = (Option<Vec<i64>>,), Error = WarpRejection> + Clone {
+    warp::header::optional::<String>("if-match").and_then(|if_match: Option<String>| async move {
+        match if_match.as_deref().map(str::trim) {
+            None | Some("*") => Ok::<Option<Vec<i64>>, WarpRejection>(None),
+            Some(etags) => Ok(Some(strong_etag_versions(etags)?)),
+        }
+    })
+}
+
+fn strong_etag_versions(etags: &str) -> Result<Vec<i64>, WebError> {
+    let mut versions = Vec::new();
+
+    for etag in etags.split(',').map(str::trim) {
+        let (weak, opaque_tag) = etag
+            .strip_prefix("W/")
+            .map_or((false, etag), |etag| (true, etag));
+        let version = opaque_tag
+            .strip_prefix('"')
+            .and_then(|opaque_tag| opaque_tag.strip_suffix('"'))
+            .filter(|version| !version.contains('"'))
+            .ok_or_else(|| WebError::FailInvalidIfMatch(etags.to_string()))?;
+
+        if !weak {
+            versions.extend(version.parse::<i64>().ok());
+        }
+    }
+
+    Ok(versions)
+}
+
+// If-None-Match uses the weak comparison, W/"3" matches "3"
+pub fn etag_matches_none_match(if_none_match: &str, etag: &str) -> bool {
+    if_none_match
+        .split(',')
+        .map(str::trim)
+        .any(|candidate| candidate == "*" || candidate.trim_start_matches("W/") == etag)
+}
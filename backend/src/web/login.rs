@@ -0,0 +1,64 @@
+use std::sync::Arc;
+
+use serde_json::json;
+use warp::{reject::Rejection as WarpRejection, reply::Json as WarpJSON, Filter};
+
+use crate::{
+    model::{self, PostgresDatabase, UserCredentials},
+    security::{self, UserContext},
+};
+
+use super::filter_utils::{do_auth, json_body, path_prefix, with_db};
+
+pub fn rest_filters(
+    base_path: &str,
+    database: Arc<model::PostgresDatabase>,
+) -> impl Filter<Extract = impl warp::Reply, Error = WarpRejection> + Clone {
+    let base_path = path_prefix(base_path);
+
+    // LOGIN 'POST /login with body UserCredentials
+    let login = base_path
+        .clone()
+        .and(warp::path("login"))
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(with_db(Arc::clone(&database)))
+        .and(json_body())
+        .and_then(login);
+
+    // LOGOUT 'POST /logout, invalidates every token of the user
+    let logout = base_path
+        .and(warp::path("logout"))
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(with_db(Arc::clone(&database)))
+        .and(do_auth(database))
+        .and_then(logout);
+
+    login.or(logout)
+}
+
+async fn login(
+    database: Arc<PostgresDatabase>,
+    credentials: UserCredentials,
+) -> Result<WarpJSON, WarpRejection> {
+    let (user, token) =
+        security::login(&database, &credentials.username, &credentials.password).await?;
+
+    let response = json!({"data": {"user": user, "token": token}});
+    Ok(warp::reply::json(&response))
+}
+
+async fn logout(
+    database: Arc<PostgresDatabase>,
+    user_ctx: UserContext,
+) -> Result<WarpJSON, WarpRejection> {
+    security::logout(&database, &user_ctx).await?;
+
+    let response = json!({"data": true});
+    Ok(warp::reply::json(&response))
+}
+
+#[cfg(test)]
+#[path = "../_tests/web_login.rs"]
+mod tests;
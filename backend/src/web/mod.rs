@@ -1,40 +1,116 @@
-use std::{convert::Infallible, path::Path, sync::Arc};
+use std::{convert::Infallible, net::SocketAddr, path::Path, sync::Arc};
 
-use warp::Filter;
+use warp::body::BodyDeserializeError;
+use warp::http::StatusCode;
+use warp::{reject, reply::Response, Filter};
 use warp::{reject::Rejection as WarpRejection, reply::Reply as WarpReply};
 
-use crate::{model, security};
+use serde::Serialize;
+use warp::reply::Json as WarpJSON;
+
+use crate::{
+    config::WebConfig,
+    model::{self, EventHub, FieldError, Page},
+    security, storage,
+};
+mod admin;
+mod attachment;
+mod comment;
 mod filter_utils;
-pub use filter_utils::HEADER_XAUTH;
+use filter_utils::outside_path_prefix;
+mod login;
+mod notification;
+mod problem;
+mod project;
+mod share;
+mod sse;
+use problem::{new_request_id, with_request_id, Problem};
+mod tag;
 mod todo;
+mod webhook;
+mod ws;
 
 pub async fn start_web(
-    web_folder: &str,
-    web_port: u16,
+    web_config: &WebConfig,
     database: Arc<model::PostgresDatabase>,
 ) -> Result<(), Error> {
+    let web_folder = &web_config.folder;
+    let api_base_path = &web_config.api_base_path;
+
     // validate the web folder
     if !Path::new(web_folder).exists() {
-        return Err(Error::FailStartWebFolderNotFound(web_folder.to_string()));
+        return Err(Error::FailStartWebFolderNotFound(web_folder.clone()));
     }
 
+    // Combine all routes
+    let routes = routes(web_folder, api_base_path, database);
+
+    let address = SocketAddr::new(web_config.bind_address, web_config.port);
+    println!("Start {address} at {web_folder}, api at /{api_base_path}");
+    warp::serve(routes).run(address).await;
+
+    Ok(())
+}
+
+fn routes(
+    web_folder: &str,
+    api_base_path: &str,
+    database: Arc<model::PostgresDatabase>,
+) -> impl Filter<Extract = impl WarpReply, Error = Infallible> + Clone {
+    // one Postgres listener for all the WebSockets and event streams of the server
+    let hub = Arc::new(EventHub::new(database.as_ref().clone()));
+
+    // Apis
+    let apis = ws::rest_filters(api_base_path, Arc::clone(&database), Arc::clone(&hub))
+        .or(sse::rest_filters(api_base_path, Arc::clone(&database), hub))
+        .or(login::rest_filters(api_base_path, Arc::clone(&database)))
+        .or(tag::rest_filters(api_base_path, Arc::clone(&database)))
+        .or(project::rest_filters(api_base_path, Arc::clone(&database)))
+        .or(comment::rest_filters(api_base_path, Arc::clone(&database)))
+        .or(attachment::rest_filters(
+            api_base_path,
+            Arc::clone(&database),
+        ))
+        .or(share::rest_filters(api_base_path, Arc::clone(&database)))
+        .or(admin::rest_filters(api_base_path, Arc::clone(&database)))
+        .or(notification::rest_filters(
+            api_base_path,
+            Arc::clone(&database),
+        ))
+        .or(webhook::rest_filters(api_base_path, Arc::clone(&database)))
+        .or(todo::rest_filters(api_base_path, database));
+
     // Static content
     let content = warp::fs::dir(web_folder.to_string());
     let root_index = warp::get()
         .and(warp::path::end())
         .and(warp::fs::file(format!("{web_folder}/index.html")));
 
-    let static_site = content.or(root_index);
+    // the static site never answers for a path under the api base path, so api 404s stay api 404s
+    let static_site = outside_path_prefix(api_base_path).and(content.or(root_index));
 
-    // Combine all routes
-    let routes = static_site.recover(handle_rejection);
+    // every response gets a request id, and every rejection becomes a problem+json response
+    with_request_id(apis.or(static_site))
+}
 
-    println!("Start 127.0.0.1:{web_port} at {web_folder}");
-    warp::serve(routes).run(([127, 0, 0, 1], web_port)).await;
+// Response bodies, the data is always under "data"
 
-    Ok(())
+fn serialize_to_warpjson<S: Serialize>(data: S) -> WarpJSON {
+    let response = serde_json::json!({"data": data});
+    warp::reply::json(&response)
 }
 
+// the page items are the data, next to them the pagination
+fn serialize_page_to_warpjson<S: Serialize>(page: &Page<S>) -> WarpJSON {
+    let response = serde_json::json!({
+        "data": page.items,
+        "next_cursor": page.next_cursor,
+        "total": page.total,
+    });
+    warp::reply::json(&response)
+}
+
+#[allow(clippy::enum_variant_names)]
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
     #[error("Web server failed to start because web-folder '{0}' not found")]
@@ -42,57 +118,230 @@ pub enum Error {
 
     #[error("Fail authentication missing X-Auth-Token header.")]
     FailAuthMissingXAuth,
+
+    #[error("Fail unsupported content type '{0}'")]
+    FailUnsupportedContentType(String),
+
+    #[error("Fail invalid body _ {0}")]
+    FailInvalidBody(String),
+
+    #[error("Fail invalid If-Match header '{0}', expected a list of ETags or *")]
+    FailInvalidIfMatch(String),
 }
 
 // Warp Custom Message
+// code is the stable, machine readable, error code sent to the client
 #[derive(Debug)]
 pub struct WebErrorMessage {
-    pub typ: &'static str,
+    pub code: &'static str,
     pub message: String,
+    pub status: StatusCode,
+    pub field_errors: Vec<FieldError>,
 }
 
 impl warp::reject::Reject for WebErrorMessage {}
 
 impl WebErrorMessage {
-    pub fn rejection(typ: &'static str, message: String) -> warp::Rejection {
-        warp::reject::custom(WebErrorMessage { typ, message })
+    pub fn rejection(code: &'static str, message: String, status: StatusCode) -> warp::Rejection {
+        warp::reject::custom(Self {
+            code,
+            message,
+            status,
+            field_errors: Vec::new(),
+        })
     }
 }
 
 impl From<self::Error> for warp::Rejection {
     fn from(other: self::Error) -> Self {
-        WebErrorMessage::rejection("web::Error", format!("{other}"))
+        let (status, code) = match other {
+            Error::FailAuthMissingXAuth => (StatusCode::UNAUTHORIZED, "AUTH_MISSING_TOKEN"),
+            Error::FailUnsupportedContentType(_) => {
+                (StatusCode::UNSUPPORTED_MEDIA_TYPE, "UNSUPPORTED_MEDIA_TYPE")
+            }
+            // same code than the warp json body rejection
+            Error::FailInvalidBody(_) => (StatusCode::UNPROCESSABLE_ENTITY, "INVALID_BODY"),
+            Error::FailInvalidIfMatch(_) => (StatusCode::BAD_REQUEST, "INVALID_HEADER"),
+            Error::FailStartWebFolderNotFound(_) => {
+                (StatusCode::INTERNAL_SERVER_ERROR, "INTERNAL_ERROR")
+            }
+        };
+        WebErrorMessage::rejection(code, format!("{other}"), status)
     }
 }
 impl From<model::Error> for warp::Rejection {
     fn from(other: model::Error) -> Self {
-        WebErrorMessage::rejection("model::Error", format!("{other}"))
+        let (status, code) = model_error_status(&other);
+        let message = match &other {
+            model::Error::EntityNotFound(typ, id) => format!("{typ} {id} not found"),
+            model::Error::AccessDenied(typ, id) => format!("{typ} {id} access denied"),
+            model::Error::VersionConflict(typ, id, version) => {
+                format!("{typ} {id} was modified, its current version is {version}")
+            }
+            other => format!("{other}"),
+        };
+        let field_errors = match other {
+            model::Error::ValidationFailed(field_errors) => field_errors,
+            _ => Vec::new(),
+        };
+        warp::reject::custom(WebErrorMessage {
+            code,
+            message,
+            status,
+            field_errors,
+        })
     }
 }
 impl From<security::Error> for warp::Rejection {
     fn from(other: security::Error) -> Self {
-        WebErrorMessage::rejection("security::Error", format!("{other}"))
+        let (status, code) = security_error_status(&other);
+        WebErrorMessage::rejection(code, format!("{other}"), status)
     }
 }
 
-async fn handle_rejection(err: WarpRejection) -> Result<impl WarpReply, Infallible> {
-    //Print to server side
+// Once published, a code must never change, the clients rely on it
+const fn model_error_status(error: &model::Error) -> (StatusCode, &'static str) {
+    match error {
+        model::Error::EntityNotFound(_, _) => (StatusCode::NOT_FOUND, "ENTITY_NOT_FOUND"),
+        model::Error::AccessDenied(_, _) => (StatusCode::FORBIDDEN, "ACCESS_DENIED"),
+        model::Error::VersionConflict(_, _, _) => {
+            (StatusCode::PRECONDITION_FAILED, "VERSION_CONFLICT")
+        }
+        model::Error::ValidationFailed(_) => {
+            (StatusCode::UNPROCESSABLE_ENTITY, "VALIDATION_FAILED")
+        }
+        model::Error::SecurityError(error) => security_error_status(error),
+        model::Error::StorageError(error) => storage_error_status(error),
+        model::Error::SqlxError(_)
+        | model::Error::SqlFileFailed(_, _)
+        | model::Error::MigrationFailed(_, _)
+        | model::Error::MigrationChecksumMismatch(_)
+        | model::Error::MigrationNotReversible(_)
+        | model::Error::IOError(_) => (StatusCode::INTERNAL_SERVER_ERROR, "INTERNAL_ERROR"),
+    }
+}
 
-    //Call log API for capture and store
+const fn security_error_status(error: &security::Error) -> (StatusCode, &'static str) {
+    match error {
+        security::Error::MalformedToken => (StatusCode::UNAUTHORIZED, "TOKEN_MALFORMED"),
+        security::Error::InvalidTokenSignature => {
+            (StatusCode::UNAUTHORIZED, "TOKEN_INVALID_SIGNATURE")
+        }
+        security::Error::ExpiredToken => (StatusCode::UNAUTHORIZED, "TOKEN_EXPIRED"),
+        security::Error::LoginFail | security::Error::WrongPassword => {
+            (StatusCode::UNAUTHORIZED, "LOGIN_FAIL")
+        }
+        security::Error::PasswordHash(_) | security::Error::ModelError(_) => {
+            (StatusCode::INTERNAL_SERVER_ERROR, "INTERNAL_ERROR")
+        }
+    }
+}
 
-    //Build user message
+const fn storage_error_status(error: &storage::Error) -> (StatusCode, &'static str) {
+    match error {
+        storage::Error::TooLarge(_) => (StatusCode::PAYLOAD_TOO_LARGE, "PAYLOAD_TOO_LARGE"),
+        // the upload was interrupted, or was not a valid multipart body
+        storage::Error::StreamFailed(_) => (StatusCode::BAD_REQUEST, "INVALID_BODY"),
+        storage::Error::InvalidKey(_)
+        | storage::Error::NotFound(_)
+        | storage::Error::IOError(_) => (StatusCode::INTERNAL_SERVER_ERROR, "INTERNAL_ERROR"),
+    }
+}
 
-    let user_message: String = match err.find::<WebErrorMessage>() {
-        Some(err) => String::from(err.typ),
-        None => String::from("Unknown error"),
-    };
+fn warp_rejection_status(err: &WarpRejection) -> (StatusCode, &'static str, String) {
+    if err.is_not_found() {
+        (
+            StatusCode::NOT_FOUND,
+            "NOT_FOUND",
+            String::from("Not found"),
+        )
+    } else if let Some(err) = err.find::<BodyDeserializeError>() {
+        // the serde message, like "missing field `title`", helps the client and leaks nothing
+        (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "INVALID_BODY",
+            err.to_string(),
+        )
+    } else if err.find::<reject::UnsupportedMediaType>().is_some() {
+        (
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            "UNSUPPORTED_MEDIA_TYPE",
+            String::from("Unsupported media type"),
+        )
+    } else if err.find::<reject::PayloadTooLarge>().is_some() {
+        (
+            StatusCode::PAYLOAD_TOO_LARGE,
+            "PAYLOAD_TOO_LARGE",
+            String::from("Payload too large"),
+        )
+    } else if err.find::<reject::LengthRequired>().is_some() {
+        (
+            StatusCode::LENGTH_REQUIRED,
+            "LENGTH_REQUIRED",
+            String::from("Content-Length required"),
+        )
+    } else if let Some(err) = err.find::<reject::InvalidQuery>() {
+        (StatusCode::BAD_REQUEST, "INVALID_QUERY", err.to_string())
+    } else if let Some(err) = err.find::<reject::InvalidHeader>() {
+        (StatusCode::BAD_REQUEST, "INVALID_HEADER", err.to_string())
+    } else if let Some(err) = err.find::<reject::MissingHeader>() {
+        (StatusCode::BAD_REQUEST, "MISSING_HEADER", err.to_string())
+    } else if err.find::<reject::MethodNotAllowed>().is_some() {
+        // lowest priority, one of the other routes only had another method
+        (
+            StatusCode::METHOD_NOT_ALLOWED,
+            "METHOD_NOT_ALLOWED",
+            String::from("Method not allowed"),
+        )
+    } else {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "INTERNAL_ERROR",
+            String::from("Internal server error"),
+        )
+    }
+}
+
+// For a filter without with_request_id, like in the tests, every rejection gets a new request id
+// async because warp's recover expects a future
+#[allow(clippy::unused_async)]
+async fn handle_rejection(err: WarpRejection) -> Result<impl WarpReply, Infallible> {
+    Ok(problem_response(&err, &new_request_id()))
+}
 
-    let result: serde_json::Value = serde_json::json!({"{errorMessage": user_message});
+fn problem_response(err: &WarpRejection, request_id: &str) -> Response {
+    // Ours first, then the warp ones. find() looks into every combined rejection
+    let (status, code, message, field_errors) = err.find::<WebErrorMessage>().map_or_else(
+        || {
+            let (status, code, message) = warp_rejection_status(err);
+            (status, code, message, Vec::new())
+        },
+        |err| {
+            (
+                err.status,
+                err.code,
+                err.message.clone(),
+                err.field_errors.clone(),
+            )
+        },
+    );
 
-    let result = warp::reply::json(&result);
+    //Print to server side, the details of a server error are never sent to the client
+    let message = if status.is_server_error() {
+        println!("ERROR  - {status} - request {request_id} - {err:?}");
+        String::from("Internal server error")
+    } else {
+        message
+    };
+
+    //Call log API for capture and store
 
-    Ok(warp::reply::with_status(
-        result,
-        warp::http::StatusCode::BAD_REQUEST,
-    ))
+    //Build user message
+    Problem::new(status, code, message, request_id.to_string())
+        .with_field_errors(field_errors)
+        .into_response()
 }
+
+#[cfg(test)]
+#[path = "../_tests/web.rs"]
+mod tests;
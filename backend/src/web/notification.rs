@@ -0,0 +1,65 @@
+use std::sync::Arc;
+
+use warp::{reject::Rejection as WarpRejection, reply::Json as WarpJSON, Filter};
+
+use crate::{
+    model::{
+        self, NotificationModelAccessController, PartialNotificationPreferences, PostgresDatabase,
+    },
+    security::UserContext,
+};
+
+use super::{
+    filter_utils::{do_auth, json_body, path_prefix, with_db},
+    serialize_to_warpjson,
+};
+
+pub fn rest_filters(
+    base_path: &str,
+    database: Arc<model::PostgresDatabase>,
+) -> impl Filter<Extract = impl warp::Reply, Error = WarpRejection> + Clone {
+    // base_path = api/v1 -> api/v1/notifications/preferences
+    let preferences_path = path_prefix(base_path)
+        .and(warp::path("notifications"))
+        .and(warp::path("preferences"))
+        .and(warp::path::end());
+
+    let common = with_db(Arc::clone(&database)).and(do_auth(database));
+
+    // GET the notification preferences of the user 'GET /notifications/preferences
+    let get = preferences_path
+        .clone()
+        .and(warp::get())
+        .and(common.clone())
+        .and_then(preferences_get);
+
+    // UPDATE them 'PATCH /notifications/preferences with body PartialNotificationPreferences
+    let update = preferences_path
+        .and(warp::patch())
+        .and(common)
+        .and(json_body())
+        .and_then(preferences_update);
+
+    get.or(update)
+}
+
+async fn preferences_get(
+    database: Arc<PostgresDatabase>,
+    utx: UserContext,
+) -> Result<WarpJSON, WarpRejection> {
+    let preferences = NotificationModelAccessController::get(&database, &utx).await?;
+    Ok(serialize_to_warpjson(preferences))
+}
+
+async fn preferences_update(
+    database: Arc<PostgresDatabase>,
+    utx: UserContext,
+    data: PartialNotificationPreferences,
+) -> Result<WarpJSON, WarpRejection> {
+    let preferences = NotificationModelAccessController::update(&database, &utx, data).await?;
+    Ok(serialize_to_warpjson(preferences))
+}
+
+#[cfg(test)]
+#[path = "../_tests/web_notification.rs"]
+mod tests;
@@ -0,0 +1,113 @@
+// RFC 7807 problem details, the body of every error response
+
+use std::convert::Infallible;
+
+use serde::Serialize;
+use uuid::Uuid;
+use warp::{
+    http::{header::CONTENT_TYPE, HeaderMap, HeaderValue, StatusCode},
+    reject::Rejection as WarpRejection,
+    reply::{Reply as WarpReply, Response},
+    Filter,
+};
+
+use crate::model::FieldError;
+
+use super::problem_response;
+
+pub const HEADER_REQUEST_ID: &str = "X-Request-Id";
+const PROBLEM_CONTENT_TYPE: &str = "application/problem+json";
+const REQUEST_ID_MAX_LEN: usize = 128;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Problem {
+    #[serde(rename = "type")]
+    pub typ: String,
+    pub title: &'static str,
+    pub status: u16,
+    pub detail: String,
+    pub code: &'static str,
+    pub request_id: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
+}
+
+impl Problem {
+    pub fn new(status: StatusCode, code: &'static str, detail: String, request_id: String) -> Self {
+        Self {
+            // a stable relative uri per code, like "/problems/entity-not-found"
+            typ: format!("/problems/{}", code.to_lowercase().replace('_', "-")),
+            title: status.canonical_reason().unwrap_or("Error"),
+            status: status.as_u16(),
+            detail,
+            code,
+            request_id,
+            errors: Vec::new(),
+        }
+    }
+
+    pub fn with_field_errors(mut self, errors: Vec<FieldError>) -> Self {
+        self.errors = errors;
+        self
+    }
+}
+
+impl WarpReply for Problem {
+    fn into_response(self) -> Response {
+        let status = StatusCode::from_u16(self.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        let mut response = warp::reply::json(&self).into_response();
+
+        *response.status_mut() = status;
+        response
+            .headers_mut()
+            .insert(CONTENT_TYPE, HeaderValue::from_static(PROBLEM_CONTENT_TYPE));
+
+        response
+    }
+}
+
+pub fn new_request_id() -> String {
+    Uuid::new_v4().to_string()
+}
+
+// Wraps the whole routes : the client X-Request-Id is kept when sane, otherwise a new one is generated.
+// It's sent back in the X-Request-Id header, and in the problem body of the rejections
+pub fn with_request_id<F, R>(
+    filter: F,
+) -> impl Filter<Extract = (Response,), Error = Infallible> + Clone
+where
+    F: Filter<Extract = (R,), Error = WarpRejection> + Clone + Send + Sync + 'static,
+    R: WarpReply,
+{
+    let request_id = warp::header::headers_cloned().map(|headers: HeaderMap| {
+        headers
+            .get(HEADER_REQUEST_ID)
+            .and_then(|value| value.to_str().ok())
+            .filter(|value| {
+                !value.is_empty()
+                    && value.len() <= REQUEST_ID_MAX_LEN
+                    && value.chars().all(|c| c.is_ascii_graphic())
+            })
+            .map_or_else(new_request_id, str::to_string)
+    });
+
+    let result = filter
+        .map(|reply: R| Ok::<Response, WarpRejection>(reply.into_response()))
+        .or_else(|rejection: WarpRejection| async move {
+            Ok::<_, Infallible>((Err::<Response, WarpRejection>(rejection),))
+        });
+
+    request_id.and(result).map(
+        |request_id: String, result: Result<Response, WarpRejection>| {
+            let mut response =
+                result.unwrap_or_else(|rejection| problem_response(&rejection, &request_id));
+
+            if let Ok(value) = HeaderValue::from_str(&request_id) {
+                response.headers_mut().insert(HEADER_REQUEST_ID, value);
+            }
+
+            response
+        },
+    )
+}
@@ -0,0 +1,182 @@
+use std::sync::Arc;
+
+use warp::{reject::Rejection as WarpRejection, reply::Json as WarpJSON, Filter};
+
+use crate::{
+    model::{
+        self, ModelAccessController, PartialProject, PartialTodo, PostgresDatabase,
+        ProjectModelAccessController, TodoListOptions,
+    },
+    security::UserContext,
+};
+
+use super::{
+    filter_utils::{do_auth, json_body, path_prefix, with_db},
+    serialize_page_to_warpjson, serialize_to_warpjson,
+};
+
+pub fn rest_filters(
+    base_path: &str,
+    database: Arc<model::PostgresDatabase>,
+) -> impl Filter<Extract = impl warp::Reply, Error = WarpRejection> + Clone {
+    let projects_path = path_prefix(base_path).and(warp::path("projects")); // base_path = api/v1 and projects -> api/v1/projects
+
+    let common = with_db(Arc::clone(&database)).and(do_auth(database));
+
+    // LIST projects 'GET /projects
+    let list = projects_path
+        .clone()
+        .and(warp::get())
+        .and(warp::path::end())
+        .and(common.clone())
+        .and_then(project_list);
+
+    // GET project 'GET /projects/1
+    let get = projects_path
+        .clone()
+        .and(warp::get())
+        .and(common.clone())
+        .and(warp::path::param())
+        .and(warp::path::end()) // GET /projects/1/todos is another route
+        .and_then(project_get);
+
+    // CREATE project 'POST /projects with body PartialProject
+    let create = projects_path
+        .clone()
+        .and(warp::post())
+        .and(warp::path::end())
+        .and(common.clone())
+        .and(json_body())
+        .and_then(project_create);
+
+    // UPDATE project 'PATCH /projects/1 with body PartialProject, archiving cascades to its todos
+    let update = projects_path
+        .clone()
+        .and(warp::patch())
+        .and(common.clone())
+        .and(warp::path::param())
+        .and(warp::path::end())
+        .and(json_body())
+        .and_then(project_update);
+
+    // DELETE project 'DELETE /projects/1, its todos are kept without project
+    let delete = projects_path
+        .clone()
+        .and(warp::delete())
+        .and(common.clone())
+        .and(warp::path::param())
+        .and(warp::path::end())
+        .and_then(project_delete);
+
+    // LIST the project todos 'GET /projects/1/todos, same query parameters than GET /todos
+    let list_todos = projects_path
+        .clone()
+        .and(warp::get())
+        .and(common.clone())
+        .and(warp::path::param())
+        .and(warp::path("todos"))
+        .and(warp::path::end())
+        .and(warp::query::<TodoListOptions>())
+        .and_then(project_todo_list);
+
+    // CREATE a todo in the project 'POST /projects/1/todos with body PartialTodo
+    let create_todo = projects_path
+        .and(warp::post())
+        .and(common)
+        .and(warp::path::param())
+        .and(warp::path("todos"))
+        .and(warp::path::end())
+        .and(json_body())
+        .and_then(project_todo_create);
+
+    list.or(get)
+        .or(create)
+        .or(update)
+        .or(delete)
+        .or(list_todos)
+        .or(create_todo)
+}
+
+async fn project_list(
+    database: Arc<PostgresDatabase>,
+    utx: UserContext,
+) -> Result<WarpJSON, WarpRejection> {
+    let projects = ProjectModelAccessController::list(&database, &utx).await?;
+    Ok(serialize_to_warpjson(projects))
+}
+
+async fn project_get(
+    database: Arc<PostgresDatabase>,
+    utx: UserContext,
+    id: i64,
+) -> Result<WarpJSON, WarpRejection> {
+    let project = ProjectModelAccessController::get(&database, &utx, id).await?;
+    Ok(serialize_to_warpjson(project))
+}
+
+async fn project_create(
+    database: Arc<PostgresDatabase>,
+    utx: UserContext,
+    data: PartialProject,
+) -> Result<WarpJSON, WarpRejection> {
+    let project = ProjectModelAccessController::create(&database, &utx, data).await?;
+    Ok(serialize_to_warpjson(project))
+}
+
+async fn project_update(
+    database: Arc<PostgresDatabase>,
+    utx: UserContext,
+    id: i64,
+    data: PartialProject,
+) -> Result<WarpJSON, WarpRejection> {
+    let project = ProjectModelAccessController::update(&database, &utx, id, data).await?;
+    Ok(serialize_to_warpjson(project))
+}
+
+async fn project_delete(
+    database: Arc<PostgresDatabase>,
+    utx: UserContext,
+    id: i64,
+) -> Result<WarpJSON, WarpRejection> {
+    let project = ProjectModelAccessController::delete(&database, &utx, id).await?;
+    Ok(serialize_to_warpjson(project))
+}
+
+async fn project_todo_list(
+    database: Arc<PostgresDatabase>,
+    utx: UserContext,
+    id: i64,
+    options: TodoListOptions,
+) -> Result<WarpJSON, WarpRejection> {
+    // 404 for a project of another user, rather than an empty list
+    let project = ProjectModelAccessController::get(&database, &utx, id).await?;
+
+    let options = TodoListOptions {
+        project_id: Some(project.id),
+        // the todos of an archived project are all archived
+        archived: options.archived.or(Some(project.archived)),
+        ..options
+    };
+    let page = ModelAccessController::list(&database, &utx, &options).await?;
+
+    Ok(serialize_page_to_warpjson(&page))
+}
+
+async fn project_todo_create(
+    database: Arc<PostgresDatabase>,
+    utx: UserContext,
+    id: i64,
+    data: PartialTodo,
+) -> Result<WarpJSON, WarpRejection> {
+    let data = PartialTodo {
+        project_id: Some(Some(id)),
+        ..data
+    };
+    let todo = ModelAccessController::create(&database, &utx, data).await?;
+
+    Ok(serialize_to_warpjson(todo))
+}
+
+#[cfg(test)]
+#[path = "../_tests/web_project.rs"]
+mod tests;
@@ -0,0 +1,89 @@
+use std::sync::Arc;
+
+use warp::{reject::Rejection as WarpRejection, reply::Json as WarpJSON, Filter};
+
+use crate::{
+    model::{self, PartialShare, PostgresDatabase, ShareModelAccessController},
+    security::UserContext,
+};
+
+use super::{
+    filter_utils::{do_auth, json_body, path_prefix, with_db},
+    serialize_to_warpjson,
+};
+
+pub fn rest_filters(
+    base_path: &str,
+    database: Arc<model::PostgresDatabase>,
+) -> impl Filter<Extract = impl warp::Reply, Error = WarpRejection> + Clone {
+    // base_path = api/v1 -> api/v1/todos/101/shares
+    let shares_path = path_prefix(base_path)
+        .and(warp::path("todos"))
+        .and(warp::path::param())
+        .and(warp::path("shares"));
+
+    let common = with_db(Arc::clone(&database)).and(do_auth(database));
+
+    // LIST the users the todo is shared with 'GET /todos/101/shares
+    let list = shares_path
+        .clone()
+        .and(warp::get())
+        .and(warp::path::end())
+        .and(common.clone())
+        .and_then(share_list);
+
+    // SHARE the todo with a user 'PUT /todos/101/shares/124 with body PartialShare, owner only
+    let set = shares_path
+        .clone()
+        .and(warp::put())
+        .and(warp::path::param())
+        .and(warp::path::end())
+        .and(common.clone())
+        .and(json_body())
+        .and_then(share_set);
+
+    // UNSHARE the todo 'DELETE /todos/101/shares/124, owner only
+    let delete = shares_path
+        .and(warp::delete())
+        .and(warp::path::param())
+        .and(warp::path::end())
+        .and(common)
+        .and_then(share_delete);
+
+    list.or(set).or(delete)
+}
+
+// the path params come first, then the database and the utx of common
+async fn share_list(
+    todo_id: i64,
+    database: Arc<PostgresDatabase>,
+    utx: UserContext,
+) -> Result<WarpJSON, WarpRejection> {
+    let shares = ShareModelAccessController::list(&database, &utx, todo_id).await?;
+    Ok(serialize_to_warpjson(shares))
+}
+
+async fn share_set(
+    todo_id: i64,
+    user_id: i64,
+    database: Arc<PostgresDatabase>,
+    utx: UserContext,
+    data: PartialShare,
+) -> Result<WarpJSON, WarpRejection> {
+    let share = ShareModelAccessController::set(&database, &utx, todo_id, user_id, data).await?;
+    Ok(serialize_to_warpjson(share))
+}
+
+async fn share_delete(
+    todo_id: i64,
+    user_id: i64,
+    database: Arc<PostgresDatabase>,
+    utx: UserContext,
+) -> Result<WarpJSON, WarpRejection> {
+    let share = ShareModelAccessController::delete(&database, &utx, todo_id, user_id).await?;
+    Ok(serialize_to_warpjson(share))
+}
+
+#[cfg(test)]
+#[path = "../_tests/web_share.rs"]
+mod tests;
@@ -0,0 +1,148 @@
+use std::{collections::VecDeque, sync::Arc, time::Duration};
+
+use futures::stream;
+use tokio::{
+    sync::broadcast::{error::RecvError, Receiver},
+    time::timeout,
+};
+use warp::{reject::Rejection as WarpRejection, sse::Event, Filter};
+
+use crate::{
+    config::config,
+    model::{self, ChangeLog, ChangeLogEntry, EventHub, LogPosition, PostgresDatabase},
+    security::UserContext,
+};
+
+use super::filter_utils::{do_auth_or_query, path_prefix, with_db};
+
+// the changes read from the change log at once
+const CHANGES_BATCH: i64 = 100;
+// before reading again a change notified but not readable yet, see ChangeLog::after
+const READ_RETRY: Duration = Duration::from_millis(500);
+
+pub fn rest_filters(
+    base_path: &str,
+    database: Arc<model::PostgresDatabase>,
+    hub: Arc<EventHub>,
+) -> impl Filter<Extract = impl warp::Reply, Error = WarpRejection> + Clone {
+    // base_path = api/v1 -> api/v1/todos/events
+    let events_path = path_prefix(base_path)
+        .and(warp::path("todos"))
+        .and(warp::path("events"))
+        .and(warp::path::end());
+
+    // the todo changes as Server-Sent Events 'GET /todos/events, with the X-Auth-Token header or ?token=.
+    // A reconnecting client sends the Last-Event-ID header and gets the changes it missed first
+    events_path
+        .and(warp::get())
+        .and(with_db(Arc::clone(&database)))
+        .and(do_auth_or_query(database))
+        .and(warp::header::optional::<i64>("last-event-id"))
+        .and(warp::any().map(move || Arc::clone(&hub)))
+        .and_then(todo_events)
+}
+
+// subscribed before reading the change log, so no change falls between the two
+async fn todo_events(
+    database: Arc<PostgresDatabase>,
+    utx: UserContext,
+    last_event_id: Option<i64>,
+    hub: Arc<EventHub>,
+) -> Result<impl warp::Reply, WarpRejection> {
+    let changes = hub.subscribe().await?;
+    let sent = match last_event_id {
+        Some(last_event_id) => ChangeLog::position(&database, last_event_id).await?,
+        None => ChangeLog::last_position(&database).await?,
+    };
+
+    let events = EventStream {
+        database,
+        utx,
+        sent,
+        notified: sent,
+        changes,
+        pending: VecDeque::new(),
+    };
+    let events = stream::unfold(events, next_event);
+    let keep_alive = warp::sse::keep_alive()
+        .interval(Duration::from_secs(config().events.keep_alive_secs))
+        .text("keep-alive");
+
+    Ok(warp::sse::reply(keep_alive.stream(events)))
+}
+
+struct EventStream {
+    database: Arc<PostgresDatabase>,
+    utx: UserContext,
+    // of the last change sent
+    sent: LogPosition,
+    // of the last change of the user notified, to read again until it's sent
+    notified: LogPosition,
+    changes: Receiver<Arc<ChangeLogEntry>>,
+    pending: VecDeque<ChangeLogEntry>,
+}
+
+// The change log is the source of the events, a notified change of the user only wakes the stream up.
+// One not readable yet, behind an older transaction still running, is read again every READ_RETRY.
+// A failing read ends the stream, the client reconnects with its Last-Event-ID
+async fn next_event(
+    mut events: EventStream,
+) -> Option<(Result<Event, serde_json::Error>, EventStream)> {
+    loop {
+        if let Some(entry) = events.pending.pop_front() {
+            events.sent = entry.position();
+            let event = Event::default()
+                .id(entry.id.to_string())
+                .event(entry.event)
+                .json_data(entry.data);
+            return Some((event, events));
+        }
+
+        match ChangeLog::after(
+            &events.database,
+            events.utx.user_id,
+            events.sent,
+            CHANGES_BATCH,
+        )
+        .await
+        {
+            Ok(entries) if !entries.is_empty() => {
+                events.pending.extend(entries);
+                continue;
+            }
+            Ok(_) => {}
+            Err(error) => {
+                println!("ERROR  - todo events - {error}");
+                return None;
+            }
+        }
+
+        loop {
+            let change = if events.notified > events.sent {
+                match timeout(READ_RETRY, events.changes.recv()).await {
+                    Ok(change) => change,
+                    Err(_) => break,
+                }
+            } else {
+                events.changes.recv().await
+            };
+            match change {
+                Ok(entry)
+                    if entry.position() > events.sent
+                        && entry.user_ids.contains(&events.utx.user_id) =>
+                {
+                    events.notified = events.notified.max(entry.position());
+                    break;
+                }
+                Ok(_) => {}
+                // the change log has the missed ones
+                Err(RecvError::Lagged(_)) => break,
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "../_tests/web_sse.rs"]
+mod tests;
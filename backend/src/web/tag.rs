@@ -0,0 +1,39 @@
+use std::sync::Arc;
+
+use serde_json::json;
+use warp::{reject::Rejection as WarpRejection, reply::Json as WarpJSON, Filter};
+
+use crate::{
+    model::{self, PostgresDatabase, TagModelAccessController},
+    security::UserContext,
+};
+
+use super::filter_utils::{do_auth, path_prefix, with_db};
+
+pub fn rest_filters(
+    base_path: &str,
+    database: Arc<model::PostgresDatabase>,
+) -> impl Filter<Extract = impl warp::Reply, Error = WarpRejection> + Clone {
+    // LIST tags 'GET /tags, with their usage counts
+    path_prefix(base_path)
+        .and(warp::path("tags"))
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(with_db(Arc::clone(&database)))
+        .and(do_auth(database))
+        .and_then(tag_list)
+}
+
+async fn tag_list(
+    database: Arc<PostgresDatabase>,
+    utx: UserContext,
+) -> Result<WarpJSON, WarpRejection> {
+    let tags = TagModelAccessController::list(&database, &utx).await?;
+
+    let response = json!({ "data": tags });
+    Ok(warp::reply::json(&response))
+}
+
+#[cfg(test)]
+#[path = "../_tests/web_tag.rs"]
+mod tests;
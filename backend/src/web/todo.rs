@@ -1,50 +1,88 @@
-use std::{convert::Infallible, sync::Arc};
-
-use serde::Serialize;
-use warp::{reject::Rejection as WarpRejection, reply::Json as WarpJSON, Filter};
+use std::sync::Arc;
+
+use warp::http::{header, StatusCode};
+use warp::hyper::body::Bytes;
+use warp::{
+    reject::Rejection as WarpRejection,
+    reply::{Json as WarpJSON, Response},
+    Filter, Reply,
+};
 
 use crate::{
-    model::{self, ModelAccessController, PartialTodo, PostgresDatabase},
-    security::{user_context_from_token, UserContext},
+    model::{
+        self, ModelAccessController, PartialTodo, PostgresDatabase, Todo, TodoListOptions,
+        TodoPatch,
+    },
+    security::UserContext,
 };
 
-use super::filter_utils::{do_auth, with_db};
+use super::{
+    filter_utils::{
+        do_auth, etag_matches_none_match, if_match, json_body, path_prefix, version_etag, with_db,
+        JSON_BODY_MAX_SIZE,
+    },
+    serialize_page_to_warpjson, serialize_to_warpjson, Error as WebError,
+};
+
+const CONTENT_TYPE_JSON: &str = "application/json";
+const CONTENT_TYPE_MERGE_PATCH: &str = "application/merge-patch+json";
+const CONTENT_TYPE_JSON_PATCH: &str = "application/json-patch+json";
 
 pub fn rest_filters(
-    base_path: &'static str,
+    base_path: &str,
     database: Arc<model::PostgresDatabase>,
 ) -> impl Filter<Extract = impl warp::Reply, Error = WarpRejection> + Clone {
-    let todos_path = warp::path(base_path).and(warp::path("todos")); // base_path = api/v1 and todos -> api/v1/todos
+    let todos_path = path_prefix(base_path).and(warp::path("todos")); // base_path = api/v1 and todos -> api/v1/todos
 
-    let common = with_db(Arc::clone(&database)).and(do_auth(Arc::clone(&database)));
+    let common = with_db(Arc::clone(&database)).and(do_auth(database));
 
-    // LIST todos 'GET todos/'
+    // LIST todos 'GET todos/?status=Open&title=abc&sort=title:asc&limit=20&cursor=...'
     let list = todos_path
+        .clone()
         .and(warp::get())
         .and(warp::path::end()) // must end there to be GET todos/ because if there's more, then it will be GET todos/1
         .and(common.clone())
+        .and(warp::query::<TodoListOptions>())
         .and_then(todo_list);
 
-    // GET todo 'GET /todos/101
+    // GET todo 'GET /todos/101, 304 when If-None-Match has its ETag
     let get = todos_path
+        .clone()
         .and(warp::get())
         .and(common.clone())
         .and(warp::path::param())
+        .and(warp::path::end())
+        .and(warp::header::optional::<String>("if-none-match"))
         .and_then(todo_get);
 
+    // GET todo with its subtasks 'GET /todos/101/tree
+    let tree = todos_path
+        .clone()
+        .and(warp::get())
+        .and(common.clone())
+        .and(warp::path::param())
+        .and(warp::path("tree"))
+        .and(warp::path::end())
+        .and_then(todo_tree);
+
     // CREATE todo 'POST /todos with body TodoPatch
     let create = todos_path
+        .clone()
         .and(warp::post())
+        .and(warp::path::end())
         .and(common.clone())
-        .and(warp::body::json()) // ask warp to parse the body as JSON, and because PartialTodo derives Deserialize, warp will do the right thing and right deserialization will happen because of the todo_create signature
+        .and(json_body()) // ask warp to parse the body as JSON, and because PartialTodo derives Deserialize, warp will do the right thing and right deserialization will happen because of the todo_create signature
         .and_then(todo_create);
 
-    // UPDATE todo 'PATCH /todos/100 with body PartialTodo
+    // UPDATE todo 'PATCH /todos/100 with body PartialTodo, a JSON Merge Patch or a JSON Patch
     let update = todos_path
+        .clone()
         .and(warp::patch())
         .and(common.clone()) // 2 first arguments
         .and(warp::path::param()) // 3rd argument, the param
-        .and(warp::body::json()) // 4th argument the body aka PartialTodo
+        .and(warp::path::end()) // PATCH /todos/100/comments/1 is another route
+        .and(if_match()) // 4th argument, the versions from If-Match, 412 when none is the current one
+        .and(todo_patch_body()) // 5th argument the body, parsed according to its content type
         .and_then(todo_update); // function receives arguments in the order of the chaining
 
     // DELETE todo 'DELETE /todos/100
@@ -52,20 +90,23 @@ pub fn rest_filters(
         .and(warp::delete())
         .and(common.clone())
         .and(warp::path::param())
+        .and(warp::path::end())
+        .and(if_match())
         .and_then(todo_delete);
 
-    list.or(get).or(create).or(update).or(delete)
+    list.or(get).or(tree).or(create).or(update).or(delete)
 }
 
 // because common extracts the PostgresDatabase clone and the utx, it will be provided to the function in that order
 async fn todo_list(
     database: Arc<PostgresDatabase>,
     utx: UserContext,
+    options: TodoListOptions,
 ) -> Result<WarpJSON, warp::Rejection> {
     // Arc implements the AsRef trait, so Arc<PostgresDatabase> can be given to expecting &PostgresDatabase
-    let todos = model::ModelAccessController::list(&database, &utx).await?;
+    let page = model::ModelAccessController::list(&database, &utx, &options).await?;
 
-    let response = serialize_to_warpjson(todos);
+    let response = serialize_page_to_warpjson(&page);
     Ok(response)
 }
 
@@ -73,49 +114,104 @@ async fn todo_get(
     database: Arc<PostgresDatabase>,
     user_ctx: UserContext,
     id: i64,
-) -> Result<WarpJSON, WarpRejection> {
+    if_none_match: Option<String>,
+) -> Result<Response, WarpRejection> {
     let todo = ModelAccessController::get(&database, &user_ctx, id).await?;
-    let response = serialize_to_warpjson(todo);
-    Ok(response)
+
+    let etag = version_etag(todo.version);
+    if if_none_match.is_some_and(|if_none_match| etag_matches_none_match(&if_none_match, &etag)) {
+        let response = warp::reply::with_status(warp::reply(), StatusCode::NOT_MODIFIED);
+        return Ok(warp::reply::with_header(response, header::ETAG, etag).into_response());
+    }
+
+    Ok(reply_with_etag(&todo))
+}
+
+async fn todo_tree(
+    database: Arc<PostgresDatabase>,
+    user_ctx: UserContext,
+    id: i64,
+) -> Result<WarpJSON, WarpRejection> {
+    let tree = ModelAccessController::get_tree(&database, &user_ctx, id).await?;
+    Ok(serialize_to_warpjson(tree))
 }
+
 async fn todo_create(
     database: Arc<PostgresDatabase>,
     user_ctx: UserContext,
     patch_data: PartialTodo,
-) -> Result<WarpJSON, WarpRejection> {
+) -> Result<Response, WarpRejection> {
     let todo = ModelAccessController::create(&database, &user_ctx, patch_data).await?;
-    let response = serialize_to_warpjson(todo);
-    Ok(response)
+    Ok(reply_with_etag(&todo))
 }
 
 async fn todo_update(
     database: Arc<PostgresDatabase>,
     user_ctx: UserContext,
     todo_id: i64,
-    patch_data: PartialTodo,
-) -> Result<WarpJSON, WarpRejection> {
-    let todo = ModelAccessController::update(&database, &user_ctx, todo_id, patch_data).await?;
-
-    let response = serialize_to_warpjson(todo);
-
-    Ok(response)
+    expected_versions: Option<Vec<i64>>,
+    patch: TodoPatch,
+) -> Result<Response, WarpRejection> {
+    let todo =
+        ModelAccessController::patch(&database, &user_ctx, todo_id, patch, expected_versions)
+            .await?;
+
+    Ok(reply_with_etag(&todo))
 }
 
 async fn todo_delete(
     database: Arc<PostgresDatabase>,
     user_ctx: UserContext,
     todo_id: i64,
+    expected_versions: Option<Vec<i64>>,
 ) -> Result<WarpJSON, WarpRejection> {
-    let todo = ModelAccessController::delete(&database, &user_ctx, todo_id).await?;
+    let todo =
+        ModelAccessController::delete(&database, &user_ctx, todo_id, expected_versions).await?;
 
     let response = serialize_to_warpjson(todo);
 
     Ok(response)
 }
 
-fn serialize_to_warpjson<S: Serialize>(data: S) -> WarpJSON {
-    let response = serde_json::json!({"data": data});
-    warp::reply::json(&response)
+// the todo as data, and its version as ETag for the next If-Match
+fn reply_with_etag(todo: &Todo) -> Response {
+    let response = serialize_to_warpjson(todo);
+    warp::reply::with_header(response, header::ETAG, version_etag(todo.version)).into_response()
+}
+
+// warp::body::json() only accepts application/json, so the body is parsed here
+fn todo_patch_body() -> impl Filter<Extract = (TodoPatch,), Error = WarpRejection> + Clone {
+    warp::header::optional::<String>("content-type")
+        .and(warp::body::content_length_limit(JSON_BODY_MAX_SIZE))
+        .and(warp::body::bytes())
+        .and_then(|content_type: Option<String>, body: Bytes| async move {
+            parse_todo_patch(content_type.as_deref(), &body).map_err(WarpRejection::from)
+        })
+}
+
+fn parse_todo_patch(content_type: Option<&str>, body: &[u8]) -> Result<TodoPatch, WebError> {
+    // the parameters, like "; charset=utf-8", don't matter
+    let mime = content_type
+        .unwrap_or(CONTENT_TYPE_JSON)
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    let invalid_body = |error: serde_json::Error| WebError::FailInvalidBody(error.to_string());
+
+    match mime.as_str() {
+        CONTENT_TYPE_JSON => serde_json::from_slice(body)
+            .map(TodoPatch::Partial)
+            .map_err(invalid_body),
+        CONTENT_TYPE_MERGE_PATCH => serde_json::from_slice(body)
+            .map(TodoPatch::Merge)
+            .map_err(invalid_body),
+        CONTENT_TYPE_JSON_PATCH => serde_json::from_slice(body)
+            .map(TodoPatch::Json)
+            .map_err(invalid_body),
+        _ => Err(WebError::FailUnsupportedContentType(mime)),
+    }
 }
 
 #[cfg(test)]
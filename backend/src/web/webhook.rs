@@ -0,0 +1,141 @@
+use std::sync::Arc;
+
+use warp::{reject::Rejection as WarpRejection, reply::Json as WarpJSON, Filter};
+
+use crate::{
+    model::{self, PartialWebhook, PostgresDatabase, WebhookModelAccessController},
+    security::UserContext,
+};
+
+use super::{
+    filter_utils::{do_auth, json_body, path_prefix, with_db},
+    serialize_to_warpjson,
+};
+
+pub fn rest_filters(
+    base_path: &str,
+    database: Arc<model::PostgresDatabase>,
+) -> impl Filter<Extract = impl warp::Reply, Error = WarpRejection> + Clone {
+    let webhooks_path = path_prefix(base_path).and(warp::path("webhooks")); // base_path = api/v1 and webhooks -> api/v1/webhooks
+
+    let common = with_db(Arc::clone(&database)).and(do_auth(database));
+
+    // LIST the webhooks of the user 'GET /webhooks
+    let list = webhooks_path
+        .clone()
+        .and(warp::get())
+        .and(warp::path::end())
+        .and(common.clone())
+        .and_then(webhook_list);
+
+    // CREATE webhook 'POST /webhooks with body PartialWebhook, the response has its secret
+    let create = webhooks_path
+        .clone()
+        .and(warp::post())
+        .and(warp::path::end())
+        .and(common.clone())
+        .and(json_body())
+        .and_then(webhook_create);
+
+    // UPDATE webhook 'PATCH /webhooks/1 with body PartialWebhook
+    let update = webhooks_path
+        .clone()
+        .and(warp::patch())
+        .and(common.clone())
+        .and(warp::path::param())
+        .and(warp::path::end())
+        .and(json_body())
+        .and_then(webhook_update);
+
+    // DELETE webhook 'DELETE /webhooks/1
+    let delete = webhooks_path
+        .clone()
+        .and(warp::delete())
+        .and(common.clone())
+        .and(warp::path::param())
+        .and(warp::path::end())
+        .and_then(webhook_delete);
+
+    // LIST the delivery attempts of the webhook 'GET /webhooks/1/deliveries, the most recent first
+    let deliveries = webhooks_path
+        .clone()
+        .and(warp::get())
+        .and(common.clone())
+        .and(warp::path::param())
+        .and(warp::path("deliveries"))
+        .and(warp::path::end())
+        .and_then(webhook_deliveries);
+
+    // SEND a test event to the webhook 'POST /webhooks/1/test, delivered by the worker
+    let test = webhooks_path
+        .and(warp::post())
+        .and(common)
+        .and(warp::path::param())
+        .and(warp::path("test"))
+        .and(warp::path::end())
+        .and_then(webhook_test);
+
+    list.or(create)
+        .or(update)
+        .or(delete)
+        .or(deliveries)
+        .or(test)
+}
+
+async fn webhook_list(
+    database: Arc<PostgresDatabase>,
+    utx: UserContext,
+) -> Result<WarpJSON, WarpRejection> {
+    let webhooks = WebhookModelAccessController::list(&database, &utx).await?;
+    Ok(serialize_to_warpjson(webhooks))
+}
+
+async fn webhook_create(
+    database: Arc<PostgresDatabase>,
+    utx: UserContext,
+    data: PartialWebhook,
+) -> Result<WarpJSON, WarpRejection> {
+    let webhook = WebhookModelAccessController::create(&database, &utx, data).await?;
+    Ok(serialize_to_warpjson(webhook))
+}
+
+async fn webhook_update(
+    database: Arc<PostgresDatabase>,
+    utx: UserContext,
+    id: i64,
+    data: PartialWebhook,
+) -> Result<WarpJSON, WarpRejection> {
+    let webhook = WebhookModelAccessController::update(&database, &utx, id, data).await?;
+    Ok(serialize_to_warpjson(webhook))
+}
+
+async fn webhook_delete(
+    database: Arc<PostgresDatabase>,
+    utx: UserContext,
+    id: i64,
+) -> Result<WarpJSON, WarpRejection> {
+    let webhook = WebhookModelAccessController::delete(&database, &utx, id).await?;
+    Ok(serialize_to_warpjson(webhook))
+}
+
+async fn webhook_deliveries(
+    database: Arc<PostgresDatabase>,
+    utx: UserContext,
+    id: i64,
+) -> Result<WarpJSON, WarpRejection> {
+    let deliveries = WebhookModelAccessController::deliveries(&database, &utx, id).await?;
+    Ok(serialize_to_warpjson(deliveries))
+}
+
+async fn webhook_test(
+    database: Arc<PostgresDatabase>,
+    utx: UserContext,
+    id: i64,
+) -> Result<WarpJSON, WarpRejection> {
+    let event = WebhookModelAccessController::send_test(&database, &utx, id).await?;
+    Ok(serialize_to_warpjson(event))
+}
+
+#[cfg(test)]
+#[path = "../_tests/web_webhook.rs"]
+mod tests;
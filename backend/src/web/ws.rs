@@ -0,0 +1,98 @@
+use std::sync::Arc;
+
+use futures::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use tokio::sync::broadcast::{error::RecvError, Receiver};
+use warp::{
+    reject::Rejection as WarpRejection,
+    ws::{Message, WebSocket, Ws},
+    Filter,
+};
+
+use crate::{
+    model::{self, ChangeLogEntry, EventHub, TodoEvent},
+    security::UserContext,
+};
+
+use super::filter_utils::{do_auth_or_query, path_prefix};
+
+pub fn rest_filters(
+    base_path: &str,
+    database: Arc<model::PostgresDatabase>,
+    hub: Arc<EventHub>,
+) -> impl Filter<Extract = impl warp::Reply, Error = WarpRejection> + Clone {
+    // base_path = api/v1 -> api/v1/ws
+    let ws_path = path_prefix(base_path)
+        .and(warp::path("ws"))
+        .and(warp::path::end());
+
+    // the todo events, pushed as {"type": "todo.updated", "data": todo} 'GET /ws upgraded to a WebSocket,
+    // with the X-Auth-Token header or ?token=
+    ws_path
+        .and(warp::ws())
+        .and(do_auth_or_query(database))
+        .and(warp::any().map(move || Arc::clone(&hub)))
+        .and_then(ws_connect)
+}
+
+// subscribed before the upgrade, so the client gets every change made once it's connected
+async fn ws_connect(
+    ws: Ws,
+    utx: UserContext,
+    hub: Arc<EventHub>,
+) -> Result<impl warp::Reply, WarpRejection> {
+    let changes = hub.subscribe().await?;
+
+    Ok(ws.on_upgrade(move |socket| ws_session(socket, utx, changes)))
+}
+
+// Until the client leaves. What it sends is ignored, the pings are answered by warp
+async fn ws_session(
+    socket: WebSocket,
+    utx: UserContext,
+    mut changes: Receiver<Arc<ChangeLogEntry>>,
+) {
+    let (mut sink, mut stream) = socket.split();
+
+    loop {
+        let message = tokio::select! {
+            incoming = stream.next() => match incoming {
+                Some(Ok(message)) if !message.is_close() => continue,
+                _ => break,
+            },
+            change = changes.recv() => match change {
+                Ok(entry) => match change_message(&utx, &entry) {
+                    Some(message) => message,
+                    None => continue,
+                },
+                // the client missed some changes, it has to reload its todos
+                Err(RecvError::Lagged(missed)) => json!({ "type": "events.lagged", "data": { "missed": missed } }),
+                Err(RecvError::Closed) => break,
+            },
+        };
+
+        if sink.send(Message::text(message.to_string())).await.is_err() {
+            break;
+        }
+    }
+}
+
+// None for a todo the user can't see, checked with the users of the change. The hub read the change
+// for all the sockets: a created or updated todo is sent as it was after the change, a deleted one is only its id
+fn change_message(utx: &UserContext, entry: &ChangeLogEntry) -> Option<Value> {
+    if !entry.user_ids.contains(&utx.user_id) {
+        return None;
+    }
+
+    let data = if entry.event == TodoEvent::Deleted.as_str() {
+        json!({ "id": entry.todo_id })
+    } else {
+        entry.data.clone()
+    };
+
+    Some(json!({ "type": entry.event, "data": data }))
+}
+
+#[cfg(test)]
+#[path = "../_tests/web_ws.rs"]
+mod tests;
@@ -0,0 +1,137 @@
+use std::{sync::Arc, time::Duration};
+
+use thiserror::Error as ThisError;
+use tokio::time::{interval, MissedTickBehavior};
+
+use crate::config::JobConfig;
+use crate::email::{self, Mailer};
+use crate::model::{self, ChangeLog, Job, JobKind, JobQueue, PostgresDatabase};
+
+mod notification;
+mod reminder;
+mod webhook;
+
+// Polls the job table until the process ends. A failing poll is logged, and retried at the next tick
+pub async fn start_worker(
+    job_config: JobConfig,
+    database: Arc<PostgresDatabase>,
+    mailer: Arc<dyn Mailer>,
+) {
+    println!(
+        "Start worker, polling the jobs every {}ms",
+        job_config.poll_interval_ms
+    );
+
+    let mut ticks = interval(Duration::from_millis(job_config.poll_interval_ms));
+    ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    loop {
+        ticks.tick().await;
+        if let Err(error) = run_once(&job_config, &database, mailer.as_ref()).await {
+            println!("ERROR  - worker - {error}");
+        }
+    }
+}
+
+// One poll : the reminders due are enqueued, the expired todo changes, jobs and deliveries deleted,
+// then one batch of jobs is run. Returns the jobs, as they are once run
+pub async fn run_once(
+    job_config: &JobConfig,
+    database: &PostgresDatabase,
+    mailer: &dyn Mailer,
+) -> Result<Vec<Job>, model::Error> {
+    // a failing housekeeping is logged, the jobs are run anyway
+    let retention = Duration::from_hours(job_config.retention_hours);
+    let housekeeping = [
+        (
+            "reminders",
+            JobQueue::enqueue_reminders(
+                database,
+                job_config.reminder_lead_minutes,
+                job_config.max_attempts,
+            )
+            .await
+            .err(),
+        ),
+        (
+            "todo changes purge",
+            ChangeLog::purge(
+                database,
+                Duration::from_hours(job_config.change_retention_hours),
+            )
+            .await
+            .err(),
+        ),
+        (
+            "jobs purge",
+            JobQueue::purge(database, retention).await.err(),
+        ),
+        (
+            "deliveries purge",
+            model::purge_deliveries(database, retention).await.err(),
+        ),
+    ];
+    for (task, error) in housekeeping {
+        if let Some(error) = error {
+            println!("ERROR  - worker - {task} - {error}");
+        }
+    }
+
+    let backoff_base = Duration::from_millis(job_config.backoff_base_ms);
+    let mut finished = Vec::new();
+    for job in JobQueue::claim(database, job_config.batch_size).await? {
+        let result = match run_job(database, mailer, &job).await {
+            Ok(()) => JobQueue::complete(database, &job).await,
+            Err(error) => {
+                println!(
+                    "ERROR  - job {} {} attempt {} - {error}",
+                    job.id, job.kind, job.attempts
+                );
+                JobQueue::fail(database, &job, &error.to_string(), backoff_base).await
+            }
+        };
+        // the next jobs are still run, this one stays running until its lease is over
+        match result {
+            Ok(Some(job)) => finished.push(job),
+            // claimed again by another worker, its result is the one kept
+            Ok(None) => println!(
+                "ERROR  - job {} {} attempt {} - lease lost, result dropped",
+                job.id, job.kind, job.attempts
+            ),
+            Err(error) => println!("ERROR  - job {} {} - {error}", job.id, job.kind),
+        }
+    }
+
+    Ok(finished)
+}
+
+async fn run_job(database: &PostgresDatabase, mailer: &dyn Mailer, job: &Job) -> Result<(), Error> {
+    let kind: JobKind = job.kind.parse().map_err(Error::UnknownKind)?;
+
+    match kind {
+        JobKind::Reminder => reminder::run(database, job).await,
+        JobKind::Notification => notification::run(database, mailer, job).await,
+        JobKind::Webhook => webhook::run(database, job).await,
+    }
+}
+
+#[derive(ThisError, Debug)]
+pub enum Error {
+    #[error("Unknown job _ {0}")]
+    UnknownKind(String),
+
+    #[error("Invalid job payload _ {0}")]
+    InvalidPayload(#[from] serde_json::Error),
+
+    #[error(transparent)]
+    Model(#[from] model::Error),
+
+    #[error(transparent)]
+    Email(#[from] email::Error),
+
+    #[error("Webhook delivery failed _ {0}")]
+    WebhookFailed(String),
+}
+
+#[cfg(test)]
+#[path = "../_tests/worker.rs"]
+mod tests;
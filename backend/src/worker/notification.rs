@@ -0,0 +1,116 @@
+use crate::config::config;
+use crate::email::{Mailer, Template};
+use crate::model::{
+    self, preferences_of, CommentModelAccessController, Job, ModelAccessController, Notification,
+    NotificationEvent, PostgresDatabase, Todo, UserModelAccessController,
+};
+use crate::security::UserContext;
+
+use super::Error;
+
+const ASSIGNED: Template = Template {
+    subject: "You were assigned \"{{title}}\"",
+    text: "Hello {{username}},\n\n\
+           The todo \"{{title}}\" is now assigned to you.\n\n\
+           {{url}}\n",
+    html: "<p>Hello {{username}},</p>\
+           <p>The todo <a href=\"{{url}}\">{{title}}</a> is now assigned to you.</p>",
+};
+
+const COMMENTED: Template = Template {
+    subject: "New comment on \"{{title}}\"",
+    text: "Hello {{username}},\n\n\
+           {{author}} commented the todo \"{{title}}\":\n\n\
+           {{body}}\n\n\
+           {{url}}\n",
+    html: "<p>Hello {{username}},</p>\
+           <p>{{author}} commented the todo <a href=\"{{url}}\">{{title}}</a>:</p>\
+           <blockquote style=\"white-space: pre-wrap\">{{body}}</blockquote>",
+};
+
+const DUE: Template = Template {
+    subject: "\"{{title}}\" is due at {{due_at}}",
+    text: "Hello {{username}},\n\n\
+           The todo \"{{title}}\" is due at {{due_at}}.\n\n\
+           {{url}}\n",
+    html: "<p>Hello {{username}},</p>\
+           <p>The todo <a href=\"{{url}}\">{{title}}</a> is due at {{due_at}}.</p>",
+};
+
+// Emails the notification, if the user wants it. It's made from the current data: nothing is sent
+// about a todo they can't see anymore, or a deleted comment
+pub async fn run(database: &PostgresDatabase, mailer: &dyn Mailer, job: &Job) -> Result<(), Error> {
+    let notification: Notification = serde_json::from_value(job.payload.clone())?;
+
+    let preferences = preferences_of(database, notification.user_id).await?;
+    let wanted = match notification.event {
+        NotificationEvent::Assigned => preferences.assigned,
+        NotificationEvent::Commented => preferences.commented,
+        NotificationEvent::Due => preferences.due,
+    };
+    let Some(to) = preferences.email.filter(|_| wanted) else {
+        return Ok(());
+    };
+
+    let utx = UserContext {
+        user_id: notification.user_id,
+    };
+    let todo = match ModelAccessController::get(database, &utx, notification.todo_id).await {
+        Ok(todo) => todo,
+        Err(model::Error::EntityNotFound(_, _)) => return Ok(()),
+        Err(error) => return Err(error.into()),
+    };
+    let user = UserModelAccessController::get(database, notification.user_id).await?;
+
+    let url = todo_url(&todo);
+    let mut values = vec![
+        ("username", user.username),
+        ("title", todo.title.clone()),
+        ("url", url),
+    ];
+    let template = match notification.event {
+        NotificationEvent::Assigned => &ASSIGNED,
+        NotificationEvent::Due => {
+            let due_at = todo
+                .due_at
+                .map(|due_at| due_at.format("%Y-%m-%d %H:%M UTC"));
+            values.push((
+                "due_at",
+                due_at.map(|due_at| due_at.to_string()).unwrap_or_default(),
+            ));
+            &DUE
+        }
+        NotificationEvent::Commented => {
+            let comment_id = notification.comment_id.unwrap_or_default();
+            let comment = match CommentModelAccessController::get(
+                database, &utx, todo.id, comment_id,
+            )
+            .await
+            {
+                Ok(comment) => comment,
+                Err(model::Error::EntityNotFound(_, _)) => return Ok(()),
+                Err(error) => return Err(error.into()),
+            };
+            let author = UserModelAccessController::get(database, comment.cid).await?;
+            values.push(("author", author.username));
+            values.push(("body", comment.body));
+            &COMMENTED
+        }
+    };
+
+    let values: Vec<(&str, &str)> = values
+        .iter()
+        .map(|(name, value)| (*name, value.as_str()))
+        .collect();
+    mailer.send(&template.render(&to, &values)).await?;
+
+    Ok(())
+}
+
+fn todo_url(todo: &Todo) -> String {
+    format!(
+        "{}/#/todos/{}",
+        config().email.app_url.trim_end_matches('/'),
+        todo.id
+    )
+}
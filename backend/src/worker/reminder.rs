@@ -0,0 +1,38 @@
+use chrono::{DateTime, Utc};
+use serde_derive::Deserialize;
+
+use crate::model::PostgresDatabase;
+use crate::model::{self, due_todo, recipients, Job, Notification, NotificationEvent};
+
+use super::Error;
+
+#[derive(Deserialize)]
+struct ReminderPayload {
+    todo_id: i64,
+    due_at: DateTime<Utc>,
+}
+
+// A todo closed, deleted or moved to another date since the job was enqueued is not reminded,
+// the job is still done. Otherwise its users are notified, once per due date
+pub async fn run(database: &PostgresDatabase, job: &Job) -> Result<(), Error> {
+    let payload: ReminderPayload = serde_json::from_value(job.payload.clone())?;
+
+    let Some(todo) = due_todo(database, payload.todo_id, payload.due_at).await? else {
+        return Ok(());
+    };
+
+    let mut transaction = database.begin().await.map_err(model::Error::from)?;
+    for user_id in recipients(todo.cid, todo.assignee_id, None) {
+        let notification = Notification {
+            event: NotificationEvent::Due,
+            user_id,
+            todo_id: todo.id,
+            comment_id: None,
+        };
+        let dedup_key = format!("due:{}:{}:{user_id}", todo.id, payload.due_at.timestamp());
+        model::notify(&mut *transaction, &notification, Some(dedup_key)).await?;
+    }
+    transaction.commit().await.map_err(model::Error::from)?;
+
+    Ok(())
+}
@@ -0,0 +1,93 @@
+use std::{
+    sync::OnceLock,
+    time::{Duration, Instant},
+};
+
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use reqwest::{header::CONTENT_TYPE, Client};
+use sha2::Sha256;
+
+use crate::config::config;
+use crate::model::{record_delivery, webhook_for_delivery, Job, PostgresDatabase, WebhookJob};
+
+use super::Error;
+
+pub const HEADER_EVENT_ID: &str = "X-Webhook-Id";
+pub const HEADER_EVENT_TYPE: &str = "X-Webhook-Event";
+// "t=<unix seconds>,v1=<hex HMAC-SHA256 of '<unix seconds>.<body>' with the webhook secret>"
+pub const HEADER_SIGNATURE: &str = "X-Webhook-Signature";
+
+static CLIENT: OnceLock<Client> = OnceLock::new();
+
+// Posts the event, every attempt is logged. An error or a non 2xx response fails the job,
+// so it's retried with the backoff of the jobs
+pub async fn run(database: &PostgresDatabase, job: &Job) -> Result<(), Error> {
+    let WebhookJob { webhook_id, event } = serde_json::from_value(job.payload.clone())?;
+
+    let Some(webhook) = webhook_for_delivery(database, webhook_id, &event.event_type).await? else {
+        return Ok(());
+    };
+
+    let body = serde_json::to_vec(&event)?;
+    let timestamp = Utc::now().timestamp();
+    let started = Instant::now();
+    let response = client()
+        .post(&webhook.url)
+        .header(CONTENT_TYPE, "application/json")
+        .header(HEADER_EVENT_ID, event.id.to_string())
+        .header(HEADER_EVENT_TYPE, &event.event_type)
+        .header(
+            HEADER_SIGNATURE,
+            format!(
+                "t={timestamp},v1={}",
+                sign(&webhook.secret, timestamp, &body)
+            ),
+        )
+        .body(body)
+        .send()
+        .await;
+    let duration_ms = i64::try_from(started.elapsed().as_millis()).unwrap_or(i64::MAX);
+
+    let (status_code, error) = match response {
+        Ok(response) if response.status().is_success() => (Some(response.status().as_u16()), None),
+        Ok(response) => (
+            Some(response.status().as_u16()),
+            Some(format!("HTTP status {}", response.status())),
+        ),
+        Err(error) => (None, Some(error.to_string())),
+    };
+    record_delivery(
+        database,
+        webhook.id,
+        &event,
+        job.attempts,
+        status_code,
+        error.as_deref(),
+        duration_ms,
+    )
+    .await?;
+
+    error.map_or(Ok(()), |error| Err(Error::WebhookFailed(error)))
+}
+
+// The hex signature of the body, what the receiver computes again to check it
+pub fn sign(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    // HMAC accepts keys of any size, so this can't fail
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC can take a key of any size");
+    mac.update(format!("{timestamp}.").as_bytes());
+    mac.update(body);
+
+    format!("{:x}", mac.finalize().into_bytes())
+}
+
+// one client, so the connections are reused
+fn client() -> &'static Client {
+    CLIENT.get_or_init(|| {
+        Client::builder()
+            .timeout(Duration::from_millis(config().webhooks.timeout_ms))
+            .build()
+            .unwrap_or_default()
+    })
+}